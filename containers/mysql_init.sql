@@ -22,11 +22,25 @@ values
     (1, 'UK', 'laptop', null, 2, 123.4, 3, '2025-01-01'),
     (2, 'CY', 'phone', 'Just a phone desc', 20000, 7.890, 30000, '2025-06-01');
 
--- create table `sample` (
---     t tinyint not null,
---     i int not null,
---     s longtext not null
--- );
---
--- insert into `sample` (t, i, s)
--- SELECT 1, 2147483647, REPEAT('A', 10000000);
\ No newline at end of file
+drop table if exists `sample`;
+
+create table `sample` (
+    t tinyint not null,
+    i int not null,
+    s longtext not null
+);
+
+insert into `sample` (t, i, s)
+SELECT 1, 2147483647, REPEAT('A', 10000000);
+
+drop procedure if exists `product_overview`;
+
+delimiter //
+
+create procedure `product_overview`(in p_country char(2))
+begin
+    select id, title from products where country = p_country;
+    select id, price, quantity from products where country = p_country;
+end //
+
+delimiter ;
@@ -0,0 +1,91 @@
+//! Runs queries against a MySQL server and prints the result sets as tab separated rows.
+//!
+//! cargo run -p mysql-protocol --example mysql_client -- 127.0.0.1:3306 "select 1" "select @@version"
+
+use std::net::TcpStream;
+
+use mysql_protocol::charset::Charset;
+use mysql_protocol::column::{ColumnDefinition41, UTF8MB4_GENERAL_CI};
+use mysql_protocol::command::{Command, HandshakeResponse};
+use mysql_protocol::connection::Connection;
+use mysql_protocol::flags::{CLIENT_DEPRECATE_EOF, CLIENT_PLUGIN_AUTH, CLIENT_PROTOCOL_41, CLIENT_SECURE_CONNECTION, CLIENT_TRANSACTIONS};
+use mysql_protocol::packet::{Handshake, Packet, TextRow};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:3306".to_string());
+
+    let mut conn = Connection::new(TcpStream::connect(addr)?);
+
+    let greeting = Handshake::parse(&conn.read_packet()?)?;
+    println!("connected to {} as connection {}", greeting.server_version, greeting.connection_id);
+
+    // no password, the server is asked for nothing it may not have
+    let capabilities = (CLIENT_PROTOCOL_41 | CLIENT_SECURE_CONNECTION | CLIENT_PLUGIN_AUTH | CLIENT_TRANSACTIONS | CLIENT_DEPRECATE_EOF)
+        & greeting.capabilities;
+    let response = HandshakeResponse {
+        capabilities,
+        max_packet_size: 16_777_216,
+        collation: UTF8MB4_GENERAL_CI,
+        user: "root".to_string(),
+        auth_response: Vec::new(),
+        database: None,
+        auth_plugin: greeting.auth_plugin.clone(),
+        attributes: Vec::new(),
+        zstd_level: 0,
+    };
+    conn.write_packet(&response.as_bytes())?;
+    conn.flush()?;
+
+    // caching_sha2_password sends its fast auth result before the OK
+    loop {
+        match Packet::parse(&conn.read_packet()?, capabilities)? {
+            Packet::AuthSuccess => continue,
+            Packet::Error(error) => return Err(error.to_string().into()),
+            _ => break,
+        }
+    }
+
+    for query in args {
+        conn.write_command(&Command::Query(query.clone(), Vec::new()).as_bytes(capabilities, Charset::Utf8mb4))?;
+        conn.flush()?;
+
+        println!("> {query}");
+        match Packet::parse(&conn.read_packet()?, capabilities)? {
+            Packet::ColumnCount(count) => {
+                let columns = (0..count)
+                    .map(|_| Ok(ColumnDefinition41::parse(&conn.read_packet()?)?))
+                    .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+                println!("{}", columns.iter().map(|column| column.name.as_str()).collect::<Vec<_>>().join("\t"));
+
+                if capabilities & CLIENT_DEPRECATE_EOF == 0 {
+                    conn.read_packet()?; // EOF after the column definitions
+                }
+
+                loop {
+                    let data = conn.read_packet()?;
+                    // a row starting with 0xFE has a value of 16 MB or more and fills the packet, the end of the rows doesn't
+                    if (data.first() == Some(&0xfe) && data.len() < 0xff_ffff) || data.first() == Some(&0xff) {
+                        if let Packet::Error(error) = Packet::parse(&data, capabilities)? {
+                            println!("{error}");
+                        }
+                        break;
+                    }
+                    let row = TextRow::parse(&data, columns.len())?;
+                    let values = row.0.iter()
+                        .map(|value| value.as_deref().map_or("NULL".into(), String::from_utf8_lossy))
+                        .collect::<Vec<_>>();
+                    println!("{}", values.join("\t"));
+                }
+            }
+            Packet::OkInfo { affected_rows, .. } => println!("OK, {affected_rows} rows affected"),
+            Packet::Error(error) => println!("{error}"),
+            packet => println!("{packet:?}"),
+        }
+    }
+
+    conn.write_command(&Command::Quit.as_bytes(capabilities, Charset::Utf8mb4))?;
+    conn.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use pg_protocol::encoding::WIN1252_HIGH;
+
+/// Character sets the server can talk to clients in.
+///
+/// Text is kept as UTF-8 inside the server, it's only converted on the way in and out.
+/// https://dev.mysql.com/doc/refman/9.4/en/charset-charsets.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Charset {
+    Utf8mb4,
+    Utf8mb3,
+    Latin1, // cp1252 really, as MySQL has it
+    Binary,
+    Ascii,
+}
+
+/// A collation, what the protocol numbers stand for.
+#[derive(Debug)]
+pub struct Collation {
+    pub id: u16,
+    pub name: &'static str,
+    pub charset: Charset,
+    default: bool, // of its character set
+}
+
+// as in information_schema.COLLATIONS, by id
+const COLLATIONS: [Collation; 12] = [
+    Collation { id: 8, name: "latin1_swedish_ci", charset: Charset::Latin1, default: true },
+    Collation { id: 11, name: "ascii_general_ci", charset: Charset::Ascii, default: true },
+    Collation { id: 33, name: "utf8mb3_general_ci", charset: Charset::Utf8mb3, default: true },
+    Collation { id: 45, name: "utf8mb4_general_ci", charset: Charset::Utf8mb4, default: false },
+    Collation { id: 46, name: "utf8mb4_bin", charset: Charset::Utf8mb4, default: false },
+    Collation { id: 47, name: "latin1_bin", charset: Charset::Latin1, default: false },
+    Collation { id: 63, name: "binary", charset: Charset::Binary, default: true },
+    Collation { id: 65, name: "ascii_bin", charset: Charset::Ascii, default: false },
+    Collation { id: 83, name: "utf8mb3_bin", charset: Charset::Utf8mb3, default: false },
+    Collation { id: 192, name: "utf8mb3_unicode_ci", charset: Charset::Utf8mb3, default: false },
+    Collation { id: 224, name: "utf8mb4_unicode_ci", charset: Charset::Utf8mb4, default: false },
+    Collation { id: 255, name: "utf8mb4_0900_ai_ci", charset: Charset::Utf8mb4, default: true },
+];
+
+// the five bytes cp1252 leaves undefined map to the C1 controls like the rest of latin1
+fn cp1252_high(b: u8) -> Option<char> {
+    WIN1252_HIGH[(b - 0x80) as usize]
+}
+
+pub fn collation(id: u16) -> Option<&'static Collation> {
+    COLLATIONS.iter().find(|c| c.id == id)
+}
+
+pub fn collation_by_name(name: &str) -> Option<&'static Collation> {
+    COLLATIONS.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+}
+
+impl Charset {
+    // `utf8` is still taken for utf8mb3
+    pub fn from_name(name: &str) -> Option<Charset> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf8mb4" => Some(Charset::Utf8mb4),
+            "utf8mb3" | "utf8" => Some(Charset::Utf8mb3),
+            "latin1" => Some(Charset::Latin1),
+            "binary" => Some(Charset::Binary),
+            "ascii" => Some(Charset::Ascii),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8mb4 => "utf8mb4",
+            Charset::Utf8mb3 => "utf8mb3",
+            Charset::Latin1 => "latin1",
+            Charset::Binary => "binary",
+            Charset::Ascii => "ascii",
+        }
+    }
+
+    // bytes per character at most, column lengths are counted in them
+    pub fn max_len(self) -> u32 {
+        match self {
+            Charset::Utf8mb4 => 4,
+            Charset::Utf8mb3 => 3,
+            Charset::Latin1 | Charset::Binary | Charset::Ascii => 1,
+        }
+    }
+
+    pub fn default_collation(self) -> &'static Collation {
+        COLLATIONS.iter().find(|c| c.charset == self && c.default).expect("every charset has a default collation")
+    }
+
+    // characters the charset can't hold become `?`, as MySQL converts them
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Charset::Utf8mb4 | Charset::Binary => text.as_bytes().to_vec(),
+            Charset::Utf8mb3 => {
+                let mut bytes = Vec::with_capacity(text.len());
+                for c in text.chars() {
+                    if c.len_utf8() == 4 {
+                        bytes.push(b'?');
+                    } else {
+                        bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                }
+                bytes
+            }
+            Charset::Latin1 => text.chars()
+                .map(|c| match c as u32 {
+                    0x00..=0x7f | 0xa0..=0xff => c as u8,
+                    0x80..=0x9f if cp1252_high(c as u8).is_none() => c as u8,
+                    _ => WIN1252_HIGH.iter().position(|&high| high == Some(c)).map_or(b'?', |i| 0x80 + i as u8),
+                })
+                .collect(),
+            Charset::Ascii => text.chars().map(|c| if c.is_ascii() { c as u8 } else { b'?' }).collect(),
+        }
+    }
+
+    // what isn't valid in the charset is taken as U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Charset::Latin1 => bytes.iter()
+                .map(|&b| match b {
+                    0x80..=0x9f => cp1252_high(b).unwrap_or(b as char),
+                    b => b as char,
+                })
+                .collect(),
+            Charset::Utf8mb4 | Charset::Utf8mb3 | Charset::Binary | Charset::Ascii => String::from_utf8_lossy(bytes).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let text = "caf\u{e9} \u{20ac}5 \u{1f600} \u{3042}";
+        assert_eq!(Charset::Utf8mb4.encode(text), text.as_bytes());
+        assert_eq!(Charset::Binary.encode(text), text.as_bytes());
+        assert_eq!(Charset::Utf8mb3.encode(text), "caf\u{e9} \u{20ac}5 ? \u{3042}".as_bytes());
+        assert_eq!(Charset::Latin1.encode(text), b"caf\xe9 \x805 ? ?");
+        assert_eq!(Charset::Ascii.encode(text), b"caf? ?5 ? ?");
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(Charset::Latin1.decode(b"caf\xe9 \x805 \x81\x9f\xff"), "caf\u{e9} \u{20ac}5 \u{81}\u{178}\u{ff}");
+        assert_eq!(Charset::Utf8mb4.decode("\u{1f600}".as_bytes()), "\u{1f600}");
+        assert_eq!(Charset::Utf8mb4.decode(b"caf\xe9"), "caf\u{fffd}");
+        assert_eq!(Charset::Ascii.decode(b"abc"), "abc");
+    }
+
+    #[test]
+    fn latin1_round_trip() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(Charset::Latin1.encode(&Charset::Latin1.decode(&bytes)), bytes);
+    }
+
+    #[test]
+    fn collations() {
+        assert_eq!(collation(8).map(|c| c.charset), Some(Charset::Latin1));
+        assert_eq!(collation_by_name("UTF8MB4_BIN").map(|c| c.id), Some(46));
+        assert_eq!(Charset::from_name("utf8"), Some(Charset::Utf8mb3));
+        assert_eq!(Charset::Utf8mb4.default_collation().id, 255);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::column::ColumnType;
+use crate::error::ProtocolError;
+
+// marks a NULL column value in a text result row
+pub const NULL_VALUE: u8 = 0xfb;
+
+/// Writers for the length-encoded integers and strings.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_dt_integers.html#sect_protocol_basic_dt_int_le
+pub trait LenencWrite {
+    fn push_lenenc_int(&mut self, value: u64);
+    fn push_lenenc_str(&mut self, value: &[u8]);
+}
+
+impl LenencWrite for Vec<u8> {
+    fn push_lenenc_int(&mut self, value: u64) {
+        match value {
+            0..=250 => self.push(value as u8),
+            251..=0xffff => {
+                self.push(0xfc);
+                self.extend((value as u16).to_le_bytes());
+            }
+            0x1_0000..=0xff_ffff => {
+                self.push(0xfd);
+                self.extend(&(value as u32).to_le_bytes()[..3]);
+            }
+            _ => {
+                self.push(0xfe);
+                self.extend(value.to_le_bytes());
+            }
+        }
+    }
+
+    fn push_lenenc_str(&mut self, value: &[u8]) {
+        self.push_lenenc_int(value.len() as u64);
+        self.extend(value);
+    }
+}
+
+/// Cursor over an incoming payload, running past its end is a malformed packet.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ProtocolError> {
+        let bytes = self.data.get(self.pos..self.pos.saturating_add(len)).ok_or(ProtocolError::MalformedPacket)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, ProtocolError> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_u24(&mut self) -> Result<u32, ProtocolError> {
+        let bytes = self.read_bytes(3)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ProtocolError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, ProtocolError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_lenenc_int(&mut self) -> Result<u64, ProtocolError> {
+        match self.read_u8()? {
+            0xfc => Ok(self.read_u16()? as u64),
+            0xfd => Ok(self.read_u24()? as u64),
+            0xfe => self.read_u64(),
+            // 0xfb is NULL in rows, 0xff starts an ERR packet
+            0xfb | 0xff => Err(ProtocolError::MalformedPacket),
+            b => Ok(b as u64),
+        }
+    }
+
+    pub fn read_lenenc_str(&mut self) -> Result<&'a [u8], ProtocolError> {
+        let len = self.read_lenenc_int()? as usize;
+        self.read_bytes(len)
+    }
+
+    // a value of a text row, NULL_VALUE in place of the length for NULL
+    pub fn read_nullable_lenenc_str(&mut self) -> Result<Option<&'a [u8]>, ProtocolError> {
+        if self.data.get(self.pos) == Some(&NULL_VALUE) {
+            self.pos += 1;
+            return Ok(None);
+        }
+        self.read_lenenc_str().map(Some)
+    }
+
+    pub fn read_null_str(&mut self) -> Result<&'a [u8], ProtocolError> {
+        let len = self.data.get(self.pos..).unwrap_or_default().iter().position(|&b| b == 0).ok_or(ProtocolError::MalformedPacket)?;
+        let bytes = self.read_bytes(len)?;
+        self.pos += 1; // terminating 0
+        Ok(bytes)
+    }
+
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let bytes = self.data.get(self.pos..).unwrap_or_default();
+        self.pos = self.data.len();
+        bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    // a value of the binary protocol as it is, with its length prefix, its type says how long it is
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row_value
+    pub fn read_binary_value(&mut self, column_type: ColumnType) -> Result<&'a [u8], ProtocolError> {
+        let start = self.pos;
+        let len = match column_type {
+            ColumnType::Null => 0,
+            ColumnType::Tiny => 1,
+            ColumnType::Short | ColumnType::Year => 2,
+            ColumnType::Long | ColumnType::Int24 | ColumnType::Float => 4,
+            ColumnType::LongLong | ColumnType::Double => 8,
+            // length 0, 4, 7 or 11 for dates, 0, 8 or 12 for times
+            ColumnType::Date | ColumnType::DateTime | ColumnType::Timestamp | ColumnType::Time => self.read_u8()? as usize,
+            _ => self.read_lenenc_int()? as usize,
+        };
+        self.read_bytes(len)?;
+        Ok(&self.data[start..self.pos])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the last value of each length and the first of the next one
+    #[test]
+    fn lenenc_int_boundaries() {
+        let cases: [(u64, &[u8]); 8] = [
+            (250, &[0xfa]),
+            (251, &[0xfc, 0xfb, 0x00]),
+            (0xffff, &[0xfc, 0xff, 0xff]),
+            (0x1_0000, &[0xfd, 0x00, 0x00, 0x01]),
+            (0xff_ffff, &[0xfd, 0xff, 0xff, 0xff]),
+            (0x100_0000, &[0xfe, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]),
+            (0, &[0x00]),
+            (u64::MAX, &[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+        ];
+        for (value, bytes) in cases {
+            let mut data = Vec::new();
+            data.push_lenenc_int(value);
+            assert_eq!(data, bytes, "{value:#x}");
+
+            let mut reader = Reader::new(&data);
+            assert_eq!(reader.read_lenenc_int(), Ok(value));
+            assert!(reader.is_empty());
+        }
+    }
+
+    // NULL in a row and the header of an ERR packet aren't lengths
+    #[test]
+    fn lenenc_int_rejects_null_and_err() {
+        for first in [NULL_VALUE, 0xff] {
+            assert_eq!(Reader::new(&[first, 0x00, 0x00]).read_lenenc_int(), Err(ProtocolError::MalformedPacket));
+        }
+    }
+
+    #[test]
+    fn lenenc_int_truncated() {
+        assert_eq!(Reader::new(&[0xfc, 0x01]).read_lenenc_int(), Err(ProtocolError::MalformedPacket));
+        assert_eq!(Reader::new(&[0xfe, 0x01, 0x02, 0x03]).read_lenenc_int(), Err(ProtocolError::MalformedPacket));
+    }
+
+    #[test]
+    fn lenenc_str_round_trip() {
+        for len in [0, 250, 251, 0xffff, 0x1_0000] {
+            let value = vec![b'x'; len];
+            let mut data = Vec::new();
+            data.push_lenenc_str(&value);
+
+            let mut reader = Reader::new(&data);
+            assert_eq!(reader.read_lenenc_str(), Ok(value.as_slice()));
+            assert!(reader.is_empty());
+        }
+
+        // a length past the end of the payload
+        assert_eq!(Reader::new(&[0x03, b'a', b'b']).read_lenenc_str(), Err(ProtocolError::MalformedPacket));
+    }
+
+    #[test]
+    fn nullable_lenenc_str() {
+        let mut reader = Reader::new(&[NULL_VALUE, 0x01, b'a']);
+        assert_eq!(reader.read_nullable_lenenc_str(), Ok(None));
+        assert_eq!(reader.read_nullable_lenenc_str(), Ok(Some(b"a".as_slice())));
+        assert!(reader.is_empty());
+    }
+}
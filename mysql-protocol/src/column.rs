@@ -0,0 +1,236 @@
+use crate::charset::{self, Charset, Collation};
+use crate::codec::{LenencWrite, Reader};
+use crate::error::ProtocolError;
+
+// column types
+// https://dev.mysql.com/doc/dev/mysql-server/latest/field__types_8h.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum ColumnType {
+    Decimal = 0x00,
+    Tiny = 0x01,
+    Short = 0x02,
+    Long = 0x03,
+    Float = 0x04,
+    Double = 0x05,
+    Null = 0x06,
+    Timestamp = 0x07,
+    LongLong = 0x08,
+    Int24 = 0x09,
+    Date = 0x0a,
+    Time = 0x0b,
+    DateTime = 0x0c,
+    Year = 0x0d,
+    VarChar = 0x0f,
+    Bit = 0x10,
+    Json = 0xf5,
+    NewDecimal = 0xf6,
+    Enum = 0xf7,
+    Set = 0xf8,
+    TinyBlob = 0xf9,
+    MediumBlob = 0xfa,
+    LongBlob = 0xfb,
+    Blob = 0xfc,
+    VarString = 0xfd,
+    String = 0xfe,
+    Geometry = 0xff,
+}
+
+impl TryFrom<u8> for ColumnType {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use ColumnType::*;
+        let column_type = match value {
+            0x00 => Decimal,
+            0x01 => Tiny,
+            0x02 => Short,
+            0x03 => Long,
+            0x04 => Float,
+            0x05 => Double,
+            0x06 => Null,
+            0x07 => Timestamp,
+            0x08 => LongLong,
+            0x09 => Int24,
+            0x0a => Date,
+            0x0b => Time,
+            0x0c => DateTime,
+            0x0d => Year,
+            0x0f => VarChar,
+            0x10 => Bit,
+            0xf5 => Json,
+            0xf6 => NewDecimal,
+            0xf7 => Enum,
+            0xf8 => Set,
+            0xf9 => TinyBlob,
+            0xfa => MediumBlob,
+            0xfb => LongBlob,
+            0xfc => Blob,
+            0xfd => VarString,
+            0xfe => String,
+            0xff => Geometry,
+            _ => return Err(ProtocolError::MalformedPacket),
+        };
+        Ok(column_type)
+    }
+}
+
+// column definition flags
+// https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__column__definition__flags.html
+pub const NOT_NULL_FLAG: u16 = 0x0001;
+pub const PRI_KEY_FLAG: u16 = 0x0002;
+pub const UNIQUE_KEY_FLAG: u16 = 0x0004;
+pub const BLOB_FLAG: u16 = 0x0010;
+pub const UNSIGNED_FLAG: u16 = 0x0020;
+pub const BINARY_FLAG: u16 = 0x0080;
+pub const NO_DEFAULT_VALUE_FLAG: u16 = 0x1000;
+pub const PART_KEY_FLAG: u16 = 0x4000;
+
+// charset numbers, as in information_schema.COLLATIONS
+pub const BINARY_CHARSET: u16 = 63;
+pub const UTF8MB4_GENERAL_CI: u16 = 45;
+
+/// Column metadata sent before the rows of a result set and in the COM_STMT_PREPARE response.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset_column_definition.html
+#[derive(Debug, Clone)]
+pub struct ColumnDefinition41 {
+    pub schema: String,
+    pub table: String,
+    pub org_table: String,
+    pub name: String,
+    pub org_name: String,
+    pub character_set: u16,
+    pub column_length: u32,
+    pub column_type: ColumnType,
+    pub flags: u16,
+    pub decimals: u8,
+    pub names_charset: Charset, // the database, table and column names are sent in it
+}
+
+impl ColumnDefinition41 {
+    // a computed column, e.g. `select 123 as id`, without a table behind it
+    pub fn new(name: &str, column_type: ColumnType, column_length: u32) -> Self {
+        ColumnDefinition41 {
+            schema: String::new(),
+            table: String::new(),
+            org_table: String::new(),
+            name: name.to_string(),
+            org_name: String::new(),
+            character_set: BINARY_CHARSET,
+            column_length,
+            column_type,
+            flags: 0,
+            decimals: 0,
+            names_charset: Charset::Utf8mb4,
+        }
+    }
+
+    // a column of a table, named the same way it's selected
+    pub fn table_column(schema: &str, table: &str, name: &str, column_type: ColumnType, column_length: u32) -> Self {
+        ColumnDefinition41 {
+            schema: schema.to_string(),
+            table: table.to_string(),
+            org_table: table.to_string(),
+            name: name.to_string(),
+            org_name: name.to_string(),
+            ..ColumnDefinition41::new(name, column_type, column_length)
+        }
+    }
+
+    pub fn with_charset(mut self, character_set: u16) -> Self {
+        self.character_set = character_set;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u16) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    // the charset of a text column, `None` for binary ones
+    pub fn text_charset(&self) -> Option<Charset> {
+        charset::collation(self.character_set).map(|c| c.charset).filter(|charset| *charset != Charset::Binary)
+    }
+
+    // as a client with character_set_results `results` gets it, the names are in it, text columns report
+    // its number and their length in its bytes per character, TEXT columns take the whole length as characters
+    pub fn in_charset(&self, results: Option<&Collation>) -> ColumnDefinition41 {
+        let mut column = self.clone();
+        if let Some(results) = results {
+            column.names_charset = results.charset;
+        }
+        if let (Some(results), Some(charset)) = (results, self.text_charset()) {
+            let blob = matches!(self.column_type, ColumnType::TinyBlob | ColumnType::MediumBlob | ColumnType::LongBlob | ColumnType::Blob);
+            let characters = if blob { self.column_length } else { self.column_length / charset.max_len() };
+            column.character_set = results.id;
+            column.column_length = characters.saturating_mul(results.charset.max_len());
+        }
+        column
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut response = Vec::new();
+
+        // catalog
+        // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
+        response.push_lenenc_str(b"def");
+
+        let charset = self.names_charset;
+        response.push_lenenc_str(&charset.encode(&self.schema)); // database
+        response.push_lenenc_str(&charset.encode(&self.table)); // table
+        response.push_lenenc_str(&charset.encode(&self.org_table)); // original table
+        response.push_lenenc_str(&charset.encode(&self.name)); // name
+        response.push_lenenc_str(&charset.encode(&self.org_name)); // original name
+
+        response.push_lenenc_int(0x0c); // length of fixed length fields, 0x0c (12)
+
+        response.extend(self.character_set.to_le_bytes()); // charset number
+        response.extend(self.column_length.to_le_bytes()); // length
+        response.push(self.column_type as u8); // type
+        response.extend(self.flags.to_le_bytes()); // flags
+        response.push(self.decimals); // decimals
+
+        response.extend([0x00, 0x00]); // reserved
+
+        response
+    }
+
+    pub fn parse(data: &[u8]) -> Result<ColumnDefinition41, ProtocolError> {
+        let mut reader = Reader::new(data);
+        let mut text = || reader.read_lenenc_str().map(|text| String::from_utf8_lossy(text).to_string());
+
+        text()?; // catalog, always `def`
+        let schema = text()?;
+        let table = text()?;
+        let org_table = text()?;
+        let name = text()?;
+        let org_name = text()?;
+
+        reader.read_lenenc_int()?; // length of fixed length fields
+        let character_set = reader.read_u16()?;
+        let column_length = reader.read_u32()?;
+        let column_type = ColumnType::try_from(reader.read_u8()?)?;
+        let flags = reader.read_u16()?;
+        let decimals = reader.read_u8()?;
+
+        Ok(ColumnDefinition41 {
+            schema,
+            table,
+            org_table,
+            name,
+            org_name,
+            character_set,
+            column_length,
+            column_type,
+            flags,
+            decimals,
+            names_charset: Charset::Utf8mb4,
+        })
+    }
+}
@@ -0,0 +1,607 @@
+use crate::charset::Charset;
+use crate::codec::{LenencWrite, Reader};
+use crate::column::ColumnType;
+use crate::compression::{Compression, DEFAULT_ZSTD_LEVEL};
+use crate::error::ProtocolError;
+use crate::flags::{
+    BINLOG_THROUGH_GTID, CLIENT_COMPRESS, CLIENT_CONNECT_ATTRS, CLIENT_CONNECT_WITH_DB, CLIENT_PLUGIN_AUTH,
+    CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA, CLIENT_QUERY_ATTRIBUTES, CLIENT_SECURE_CONNECTION, CLIENT_ZSTD_COMPRESSION_ALGORITHM,
+};
+
+/// Commands of the command phase, the first packet of every exchange after the handshake.
+///
+/// The text of COM_QUERY and COM_STMT_PREPARE is in the client's character set on the wire.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_command_phase.html
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Ping,
+    Quit,
+    Query(String, Vec<QueryAttribute>),
+    PrepareStmt(String),
+    CloseStmt(u32),
+    // stmt_id, flags, iterations, parameters, which can't be read without the statement's placeholder count
+    ExecuteStmt(u32, u8, u32, Vec<u8>),
+    SendLongData(u32, u16, Vec<u8>), // stmt_id, param_id, data
+    ResetStmt(u32),
+    FetchStmt(u32, u32), // stmt_id, num_rows
+    InitDb(String),
+    ChangeUser(ChangeUser),
+    ResetConnection,
+    FieldList(String, String), // table, column wildcard
+    Statistics,
+    ProcessInfo,
+    ProcessKill(u32),
+    Debug,
+    SetOption(u16),
+    RegisterReplica(u32), // server id
+    BinlogDump(BinlogDump),
+}
+
+impl Command {
+    pub fn as_bytes(&self, capabilities: u32, charset: Charset) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        match self {
+            Command::Ping => data.push(14),
+            Command::Quit => data.push(1),
+            Command::InitDb(database) => {
+                data.push(2);
+                data.extend(database.as_bytes());
+            }
+            Command::ChangeUser(change_user) => {
+                data.push(17);
+                data.extend(change_user.as_bytes(capabilities));
+            }
+            Command::ResetConnection => data.push(31),
+            Command::FieldList(table, wildcard) => {
+                data.push(4);
+                data.extend(table.as_bytes());
+                data.push(0x00);
+                data.extend(wildcard.as_bytes());
+            }
+            Command::Statistics => data.push(9),
+            Command::ProcessInfo => data.push(10),
+            Command::ProcessKill(id) => {
+                data.push(12);
+                data.extend(id.to_le_bytes());
+            }
+            Command::Debug => data.push(13),
+            Command::SetOption(option) => {
+                data.push(27);
+                data.extend(option.to_le_bytes());
+            }
+            Command::RegisterReplica(server_id) => {
+                data.push(21);
+                data.extend(server_id.to_le_bytes());
+                data.extend([0x00, 0x00, 0x00]); // hostname, user and password, all empty
+                data.extend(0u16.to_le_bytes()); // port
+                data.extend(0u32.to_le_bytes()); // replication rank
+                data.extend(0u32.to_le_bytes()); // source id
+            }
+            Command::BinlogDump(dump) => data.extend(dump.as_bytes()),
+            Command::Query(query, attributes) => {
+                data.push(3);
+                if capabilities & CLIENT_QUERY_ATTRIBUTES != 0 {
+                    data.extend(QueryAttribute::as_bytes(attributes));
+                }
+                data.extend(charset.encode(query));
+            }
+            Command::PrepareStmt(query) => {
+                data.push(22);
+                data.extend(charset.encode(query));
+            }
+            Command::CloseStmt(stmt_id) => {
+                data.push(25);
+                data.extend(stmt_id.to_le_bytes());
+            }
+            Command::SendLongData(stmt_id, param_id, long_data) => {
+                data.push(24);
+                data.extend(stmt_id.to_le_bytes());
+                data.extend(param_id.to_le_bytes());
+                data.extend(long_data);
+            }
+            Command::ResetStmt(stmt_id) => {
+                data.push(26);
+                data.extend(stmt_id.to_le_bytes());
+            }
+            Command::FetchStmt(stmt_id, num_rows) => {
+                data.push(28);
+                data.extend(stmt_id.to_le_bytes());
+                data.extend(num_rows.to_le_bytes());
+            }
+            Command::ExecuteStmt(stmt_id, flags, iterations, params) => {
+                data.push(23);
+                data.extend(stmt_id.to_le_bytes());
+                data.push(*flags);
+                data.extend(iterations.to_le_bytes());
+                data.extend(params);
+            }
+        }
+
+        data
+    }
+
+    // the query text comes in the client's character set
+    pub fn parse(data: &[u8], capabilities: u32, charset: Charset) -> Result<Command, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        match reader.read_u8()? {
+            14 => Ok(Command::Ping),
+            1 => Ok(Command::Quit),
+            2 => Ok(Command::InitDb(String::from_utf8_lossy(reader.read_rest()).to_string())),
+            17 => Ok(Command::ChangeUser(ChangeUser::parse(reader.read_rest(), capabilities)?)),
+            31 => Ok(Command::ResetConnection),
+            4 => {
+                let table = String::from_utf8_lossy(reader.read_null_str()?).to_string();
+                let wildcard = String::from_utf8_lossy(reader.read_rest()).to_string();
+
+                Ok(Command::FieldList(table, wildcard))
+            }
+            9 => Ok(Command::Statistics),
+            10 => Ok(Command::ProcessInfo),
+            12 => Ok(Command::ProcessKill(reader.read_u32()?)),
+            13 => Ok(Command::Debug),
+            27 => Ok(Command::SetOption(reader.read_u16()?)),
+            21 => {
+                // the hostname, user, password, port, rank and source id that follow aren't kept
+                Ok(Command::RegisterReplica(reader.read_u32()?))
+            }
+            18 => Ok(Command::BinlogDump(BinlogDump::parse(&mut reader)?)),
+            30 => Ok(Command::BinlogDump(BinlogDump::parse_gtid(&mut reader)?)),
+            3 => {
+                let attributes = if capabilities & CLIENT_QUERY_ATTRIBUTES != 0 {
+                    QueryAttribute::parse(&mut reader)?
+                } else {
+                    Vec::new()
+                };
+
+                Ok(Command::Query(charset.decode(reader.read_rest()), attributes))
+            }
+            22 => Ok(Command::PrepareStmt(charset.decode(reader.read_rest()))),
+            25 => Ok(Command::CloseStmt(reader.read_u32()?)),
+            24 => {
+                let stmt_id = reader.read_u32()?;
+                let param_id = reader.read_u16()?;
+
+                Ok(Command::SendLongData(stmt_id, param_id, reader.read_rest().to_vec()))
+            }
+            26 => Ok(Command::ResetStmt(reader.read_u32()?)),
+            28 => {
+                let stmt_id = reader.read_u32()?;
+                let num_rows = reader.read_u32()?;
+
+                Ok(Command::FetchStmt(stmt_id, num_rows))
+            }
+            23 => {
+                let stmt_id = reader.read_u32()?;
+                let flags = reader.read_u8()?;
+                let iterations = reader.read_u32()?;
+
+                Ok(Command::ExecuteStmt(stmt_id, flags, iterations, reader.read_rest().to_vec()))
+            }
+            command => Err(ProtocolError::UnknownCommand(command)),
+        }
+    }
+}
+
+/// A named value sent along with a COM_QUERY, with CLIENT_QUERY_ATTRIBUTES.
+///
+/// The value is kept as the binary protocol has it, its type says how to read it.
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryAttribute {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub unsigned: bool,
+    pub value: Option<Vec<u8>>, // `None` for NULL
+}
+
+impl QueryAttribute {
+    // parameter count, parameter set count, NULL bitmap, types with names and the values
+    fn as_bytes(attributes: &[QueryAttribute]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.push_lenenc_int(attributes.len() as u64);
+        data.push_lenenc_int(1); // parameter set count, always 1
+        if attributes.is_empty() {
+            return data;
+        }
+
+        let mut null_bitmap = vec![0u8; attributes.len().div_ceil(8)];
+        for (i, attribute) in attributes.iter().enumerate() {
+            if attribute.value.is_none() {
+                null_bitmap[i / 8] |= 1 << (i % 8);
+            }
+        }
+        data.extend(null_bitmap);
+
+        data.push(1); // new params bound
+        for attribute in attributes {
+            data.push(attribute.column_type as u8);
+            data.push(if attribute.unsigned { 0x80 } else { 0x00 });
+            data.push_lenenc_str(attribute.name.as_bytes());
+        }
+        for value in attributes.iter().filter_map(|attribute| attribute.value.as_ref()) {
+            data.extend(value);
+        }
+
+        data
+    }
+
+    fn parse(reader: &mut Reader) -> Result<Vec<QueryAttribute>, ProtocolError> {
+        let count = reader.read_lenenc_int()? as usize;
+        reader.read_lenenc_int()?; // parameter set count, always 1
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let null_bitmap = reader.read_bytes(count.div_ceil(8))?;
+        if reader.read_u8()? != 1 {
+            // there's nothing bound the attributes could take their types from
+            return Err(ProtocolError::MalformedPacket);
+        }
+
+        let mut attributes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let column_type = ColumnType::try_from(reader.read_u8()?)?;
+            let unsigned = reader.read_u8()? & 0x80 != 0;
+            let name = String::from_utf8_lossy(reader.read_lenenc_str()?).to_string();
+            attributes.push(QueryAttribute { name, column_type, unsigned, value: None });
+        }
+        for (i, attribute) in attributes.iter_mut().enumerate() {
+            if null_bitmap[i / 8] & (1 << (i % 8)) == 0 {
+                attribute.value = Some(reader.read_binary_value(attribute.column_type)?.to_vec());
+            }
+        }
+
+        Ok(attributes)
+    }
+}
+
+/// Server UUIDs and the [start, end) intervals of their transactions.
+pub type GtidSet = Vec<([u8; 16], Vec<(u64, u64)>)>;
+
+/// A replica's COM_BINLOG_DUMP, or COM_BINLOG_DUMP_GTID when it comes with the GTID set it has.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinlogDump {
+    pub file: String, // empty for the first file
+    pub position: u64, // of the next event to send
+    pub flags: u16,
+    pub server_id: u32, // of the replica
+    pub gtid_set: Option<GtidSet>, // what the replica has already
+}
+
+impl BinlogDump {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        match &self.gtid_set {
+            None => {
+                data.push(18);
+                data.extend((self.position as u32).to_le_bytes());
+                data.extend(self.flags.to_le_bytes());
+                data.extend(self.server_id.to_le_bytes());
+                data.extend(self.file.as_bytes());
+            }
+            Some(gtid_set) => {
+                data.push(30);
+                data.extend(self.flags.to_le_bytes());
+                data.extend(self.server_id.to_le_bytes());
+                data.extend((self.file.len() as u32).to_le_bytes());
+                data.extend(self.file.as_bytes());
+                data.extend(self.position.to_le_bytes());
+
+                if self.flags & BINLOG_THROUGH_GTID != 0 {
+                    let mut set = Vec::new();
+                    set.extend((gtid_set.len() as u64).to_le_bytes());
+                    for (uuid, intervals) in gtid_set {
+                        set.extend(uuid);
+                        set.extend((intervals.len() as u64).to_le_bytes());
+                        for (start, end) in intervals {
+                            set.extend(start.to_le_bytes());
+                            set.extend(end.to_le_bytes());
+                        }
+                    }
+                    data.extend((set.len() as u32).to_le_bytes());
+                    data.extend(set);
+                }
+            }
+        }
+
+        data
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump.html
+    fn parse(reader: &mut Reader) -> Result<BinlogDump, ProtocolError> {
+        let position = reader.read_u32()? as u64;
+        let flags = reader.read_u16()?;
+        let server_id = reader.read_u32()?;
+        let file = String::from_utf8_lossy(reader.read_rest()).to_string();
+
+        Ok(BinlogDump { file, position, flags, server_id, gtid_set: None })
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_binlog_dump_gtid.html
+    fn parse_gtid(reader: &mut Reader) -> Result<BinlogDump, ProtocolError> {
+        let flags = reader.read_u16()?;
+        let server_id = reader.read_u32()?;
+        let name_size = reader.read_u32()? as usize;
+        let file = String::from_utf8_lossy(reader.read_bytes(name_size)?).to_string();
+        let position = reader.read_u64()?;
+
+        // the GTID set of the replica, the intervals of each server UUID
+        let mut gtid_set = Vec::new();
+        if flags & BINLOG_THROUGH_GTID != 0 {
+            reader.read_u32()?; // data size
+            let sids = reader.read_u64()?;
+            for _ in 0..sids {
+                let uuid = reader.read_bytes(16)?.try_into().unwrap();
+                let intervals = (0..reader.read_u64()?)
+                    .map(|_| Ok((reader.read_u64()?, reader.read_u64()?)))
+                    .collect::<Result<Vec<_>, ProtocolError>>()?;
+                gtid_set.push((uuid, intervals));
+            }
+        }
+
+        Ok(BinlogDump { file, position, flags, server_id, gtid_set: Some(gtid_set) })
+    }
+}
+
+/// The client's answer to the greeting.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_response.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandshakeResponse {
+    pub capabilities: u32,
+    pub max_packet_size: u32,
+    pub collation: u16, // only the low byte goes over the wire
+    pub user: String,
+    pub auth_response: Vec<u8>,
+    pub database: Option<String>,
+    pub auth_plugin: String,
+    pub attributes: Vec<(String, String)>, // connection attributes
+    pub zstd_level: u8,
+}
+
+impl HandshakeResponse {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        data.extend(self.capabilities.to_le_bytes());
+        data.extend(self.max_packet_size.to_le_bytes());
+        data.push(self.collation as u8);
+        data.extend([0; 23]); // filler
+
+        data.extend(self.user.as_bytes());
+        data.push(0x00);
+
+        if self.capabilities & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA != 0 {
+            data.push_lenenc_str(&self.auth_response);
+        } else if self.capabilities & CLIENT_SECURE_CONNECTION != 0 {
+            data.push(self.auth_response.len() as u8);
+            data.extend(&self.auth_response);
+        } else {
+            data.extend(&self.auth_response);
+            data.push(0x00);
+        }
+
+        if self.capabilities & CLIENT_CONNECT_WITH_DB != 0 {
+            data.extend(self.database.as_deref().unwrap_or_default().as_bytes());
+            data.push(0x00);
+        }
+
+        if self.capabilities & CLIENT_PLUGIN_AUTH != 0 {
+            data.extend(self.auth_plugin.as_bytes());
+            data.push(0x00);
+        }
+
+        if self.capabilities & CLIENT_CONNECT_ATTRS != 0 {
+            let mut attributes = Vec::new();
+            for (name, value) in &self.attributes {
+                attributes.push_lenenc_str(name.as_bytes());
+                attributes.push_lenenc_str(value.as_bytes());
+            }
+            data.push_lenenc_str(&attributes);
+        }
+
+        if self.capabilities & CLIENT_ZSTD_COMPRESSION_ALGORITHM != 0 {
+            data.push(self.zstd_level);
+        }
+
+        data
+    }
+
+    pub fn parse(data: &[u8]) -> Result<HandshakeResponse, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        let capabilities = reader.read_u32()?;
+        let max_packet_size = reader.read_u32()?;
+
+        let collation = reader.read_u8()? as u16; // only the low byte of the collation number
+        reader.read_bytes(23)?; // filler
+
+        let user = String::from_utf8_lossy(reader.read_null_str()?).to_string();
+
+        let auth_response = if capabilities & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA != 0 {
+            reader.read_lenenc_str()?
+        } else if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+            let len = reader.read_u8()? as usize;
+            reader.read_bytes(len)?
+        } else {
+            reader.read_null_str()?
+        };
+
+        let database = if capabilities & CLIENT_CONNECT_WITH_DB != 0 {
+            Some(String::from_utf8_lossy(reader.read_null_str()?).to_string()).filter(|db| !db.is_empty())
+        } else {
+            None
+        };
+
+        let auth_plugin = if capabilities & CLIENT_PLUGIN_AUTH != 0 {
+            String::from_utf8_lossy(reader.read_null_str()?).to_string()
+        } else {
+            String::new()
+        };
+
+        let mut attributes = Vec::new();
+        if capabilities & CLIENT_CONNECT_ATTRS != 0 {
+            let mut pairs = Reader::new(reader.read_lenenc_str()?);
+            while !pairs.is_empty() {
+                let name = String::from_utf8_lossy(pairs.read_lenenc_str()?).to_string();
+                let value = String::from_utf8_lossy(pairs.read_lenenc_str()?).to_string();
+                attributes.push((name, value));
+            }
+        }
+
+        let zstd_level = if capabilities & CLIENT_ZSTD_COMPRESSION_ALGORITHM != 0 {
+            reader.read_u8()?
+        } else {
+            DEFAULT_ZSTD_LEVEL
+        };
+
+        Ok(HandshakeResponse {
+            capabilities,
+            max_packet_size,
+            collation,
+            user,
+            auth_response: auth_response.to_vec(),
+            database,
+            auth_plugin,
+            attributes,
+            zstd_level,
+        })
+    }
+
+    // what the client asked for, zstd over zlib when it offers both
+    pub fn compression(&self) -> Compression {
+        if self.capabilities & CLIENT_ZSTD_COMPRESSION_ALGORITHM != 0 {
+            Compression::Zstd(self.zstd_level)
+        } else if self.capabilities & CLIENT_COMPRESS != 0 {
+            Compression::Zlib
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// COM_CHANGE_USER after its command byte, it logs in again as another user.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_change_user.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeUser {
+    pub user: String,
+    pub auth_response: Vec<u8>,
+    pub database: Option<String>,
+    pub collation: Option<u16>, // clients before 5.1.23 don't send one
+}
+
+impl ChangeUser {
+    pub fn as_bytes(&self, capabilities: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        data.extend(self.user.as_bytes());
+        data.push(0x00);
+
+        if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+            data.push(self.auth_response.len() as u8);
+            data.extend(&self.auth_response);
+        } else {
+            data.extend(&self.auth_response);
+            data.push(0x00);
+        }
+
+        data.extend(self.database.as_deref().unwrap_or_default().as_bytes());
+        data.push(0x00);
+
+        if let Some(collation) = self.collation {
+            data.extend(collation.to_le_bytes());
+        }
+
+        data
+    }
+
+    pub fn parse(data: &[u8], capabilities: u32) -> Result<ChangeUser, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        let user = String::from_utf8_lossy(reader.read_null_str()?).to_string();
+
+        let auth_response = if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+            let len = reader.read_u8()? as usize;
+            reader.read_bytes(len)?
+        } else {
+            reader.read_null_str()?
+        };
+
+        let database = String::from_utf8_lossy(reader.read_null_str()?).to_string();
+        let collation = reader.read_u16().ok();
+
+        // auth plugin name and connection attributes follow, neither of them matters here
+        Ok(ChangeUser {
+            user,
+            auth_response: auth_response.to_vec(),
+            database: Some(database).filter(|db| !db.is_empty()),
+            collation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(name: &str, column_type: ColumnType, unsigned: bool, value: Option<&[u8]>) -> QueryAttribute {
+        QueryAttribute { name: name.to_string(), column_type, unsigned, value: value.map(<[u8]>::to_vec) }
+    }
+
+    #[test]
+    fn query_attributes() {
+        let data = [
+            &[3][..], // COM_QUERY
+            &[3, 1], // parameter count, parameter set count
+            &[0b010], // NULL bitmap
+            &[1], // new params bound
+            &[ColumnType::LongLong as u8, 0x80, 1, b'a'],
+            &[ColumnType::Null as u8, 0x00, 1, b'b'],
+            &[ColumnType::VarString as u8, 0x00, 2, b'c', b'd'],
+            &42u64.to_le_bytes(),
+            &[3, b'x', b'y', b'z'],
+            b"select 1",
+        ]
+        .concat();
+
+        let attributes = vec![
+            attribute("a", ColumnType::LongLong, true, Some(&42u64.to_le_bytes())),
+            attribute("b", ColumnType::Null, false, None),
+            attribute("cd", ColumnType::VarString, false, Some(b"\x03xyz")),
+        ];
+        let command = Command::Query("select 1".to_string(), attributes);
+        assert_eq!(Command::parse(&data, CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4), Ok(command.clone()));
+        assert_eq!(command.as_bytes(CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4), data);
+
+        // without the capability the block isn't there
+        let command = Command::Query("select 1".to_string(), Vec::new());
+        assert_eq!(Command::parse(b"\x03select 1", 0, Charset::Utf8mb4), Ok(command.clone()));
+        assert_eq!(command.as_bytes(0, Charset::Utf8mb4), b"\x03select 1");
+        // with it, and no attributes, only the two counts are
+        assert_eq!(Command::parse(b"\x03\x00\x01select 1", CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4), Ok(command.clone()));
+        assert_eq!(command.as_bytes(CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4), b"\x03\x00\x01select 1");
+    }
+
+    #[test]
+    fn malformed_query_attributes() {
+        let data = [3, 1, 1, 0, 1, ColumnType::Long as u8, 0, 1, b'a', 1, 2, 3, 4];
+        assert!(Command::parse(&data, CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4).is_ok());
+
+        // cut anywhere inside the block
+        for len in 1..data.len() {
+            assert!(Command::parse(&data[..len], CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4).is_err(), "{len}");
+        }
+
+        // types have to be sent with the values
+        let data = [3, 1, 1, 0, 0, 1, 2, 3, 4];
+        assert_eq!(Command::parse(&data, CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4), Err(ProtocolError::MalformedPacket));
+
+        let data = [3, 1, 1, 0, 1, 0xf0, 0, 1, b'a', 1, 2, 3, 4];
+        assert!(Command::parse(&data, CLIENT_QUERY_ATTRIBUTES, Charset::Utf8mb4).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use std::io::{Read, Write};
+
+use flate2::Compression as ZlibLevel;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+// payloads shorter than this are sent as is, with the uncompressed length set to 0
+const MIN_COMPRESS_LENGTH: usize = 50;
+
+// compressed payload length (3 bytes) + compressed sequence id + uncompressed payload length (3 bytes)
+const COMPRESSED_HEADER_SIZE: usize = 7;
+
+const MAX_COMPRESSED_CHUNK: usize = 16_777_215; // 2 ** 24 - 1
+
+pub const DEFAULT_ZSTD_LEVEL: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Zlib,
+    Zstd(u8), // compression level
+}
+
+impl Compression {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), ZlibLevel::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zstd(level) => zstd::bulk::compress(data, *level as i32),
+        }
+    }
+
+    fn decompress(&self, data: &[u8], uncompressed_len: usize) -> Result<Vec<u8>, std::io::Error> {
+        let decompressed = match self {
+            Compression::None => data.to_vec(),
+            Compression::Zlib => {
+                let mut buf = Vec::with_capacity(uncompressed_len);
+                ZlibDecoder::new(data).read_to_end(&mut buf)?;
+                buf
+            }
+            Compression::Zstd(_) => zstd::bulk::decompress(data, uncompressed_len)?,
+        };
+
+        if decompressed.len() != uncompressed_len {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Wraps a stream with the compressed protocol framing.
+///
+/// Everything written until `flush` goes out as one compressed packet (or several, when the data
+/// is larger than 16 MB), incoming compressed packets are unpacked into a buffer which `read` serves
+/// the regular MySQL packets from. With `Compression::None` the inner stream is used directly.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_compression.html
+pub struct CompressedStream<S> {
+    inner: S,
+    compression: Compression,
+    sequence_id: u8,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    write_buf: Vec<u8>,
+}
+
+impl<S: Read + Write> CompressedStream<S> {
+    pub fn new(inner: S) -> Self {
+        CompressedStream {
+            inner,
+            compression: Compression::None,
+            sequence_id: 0,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write_buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn enable(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    pub fn reset_sequence(&mut self) {
+        self.sequence_id = 0;
+    }
+
+    fn read_compressed_packet(&mut self) -> Result<(), std::io::Error> {
+        let mut header = [0u8; COMPRESSED_HEADER_SIZE];
+        self.inner.read_exact(&mut header)?;
+
+        let compressed_len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+        // the compressed sequence is independent of the packet sequence, the client starts it
+        // from 0 for every command and we continue from the last id we've seen
+        self.sequence_id = header[3].wrapping_add(1);
+        let uncompressed_len = u32::from_le_bytes([header[4], header[5], header[6], 0]) as usize;
+
+        let mut payload = vec![0; compressed_len];
+        self.inner.read_exact(&mut payload)?;
+
+        self.read_buf = if uncompressed_len == 0 {
+            payload
+        } else {
+            self.compression.decompress(&payload, uncompressed_len)?
+        };
+        self.read_pos = 0;
+
+        Ok(())
+    }
+
+    fn write_compressed_packet(&mut self, chunk: &[u8]) -> Result<(), std::io::Error> {
+        let (payload, uncompressed_len) = if chunk.len() < MIN_COMPRESS_LENGTH {
+            (chunk.to_vec(), 0)
+        } else {
+            let compressed = self.compression.compress(chunk)?;
+            if compressed.len() < chunk.len() {
+                (compressed, chunk.len())
+            } else {
+                // not worth it, e.g. random or already compressed data
+                (chunk.to_vec(), 0)
+            }
+        };
+
+        let compressed_len = (payload.len() as u32).to_le_bytes();
+        let uncompressed_len = (uncompressed_len as u32).to_le_bytes();
+        let header = [
+            compressed_len[0], compressed_len[1], compressed_len[2],
+            self.sequence_id,
+            uncompressed_len[0], uncompressed_len[1], uncompressed_len[2],
+        ];
+        self.sequence_id = self.sequence_id.wrapping_add(1);
+
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&payload)?;
+        Ok(())
+    }
+}
+
+impl<S: Read + Write> Read for CompressedStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.compression == Compression::None {
+            return self.inner.read(buf);
+        }
+
+        while self.read_pos >= self.read_buf.len() {
+            self.read_compressed_packet()?;
+        }
+
+        let available = &self.read_buf[self.read_pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.read_pos += n;
+
+        Ok(n)
+    }
+}
+
+impl<S: Read + Write> Write for CompressedStream<S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.compression == Compression::None {
+            return self.inner.write(buf);
+        }
+
+        self.write_buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.write_buf.is_empty() {
+            let data = std::mem::take(&mut self.write_buf);
+            for chunk in data.chunks(MAX_COMPRESSED_CHUNK) {
+                self.write_compressed_packet(chunk)?;
+            }
+        }
+
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // what the client sent to read from, and what the server wrote
+    struct Pipe {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn stream(compression: Compression, input: Vec<u8>) -> CompressedStream<Pipe> {
+        let mut stream = CompressedStream::new(Pipe { input: Cursor::new(input), output: Vec::new() });
+        stream.enable(compression);
+        stream
+    }
+
+    // compressed length, sequence id and uncompressed length
+    fn header(packet: &[u8]) -> (usize, u8, usize) {
+        let len = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) as usize;
+        (len(&packet[..3]), packet[3], len(&packet[4..7]))
+    }
+
+    fn sent(compression: Compression, data: &[u8]) -> Vec<u8> {
+        let mut writer = stream(compression, Vec::new());
+        writer.write_all(data).unwrap();
+        writer.flush().unwrap();
+        writer.inner.output
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = b"SELECT id, title, description FROM products WHERE category_id = 1;".repeat(20);
+        for compression in [Compression::Zlib, Compression::Zstd(DEFAULT_ZSTD_LEVEL)] {
+            let packet = sent(compression, &data);
+            let (compressed_len, sequence_id, uncompressed_len) = header(&packet);
+            assert_eq!(compressed_len, packet.len() - COMPRESSED_HEADER_SIZE);
+            assert!(compressed_len < data.len(), "{compression:?}");
+            assert_eq!((sequence_id, uncompressed_len), (0, data.len()));
+
+            let mut reader = stream(compression, packet);
+            let mut received = vec![0; data.len()];
+            reader.read_exact(&mut received).unwrap();
+            assert_eq!(received, data, "{compression:?}");
+        }
+    }
+
+    #[test]
+    fn short_payload_is_sent_as_is() {
+        let data = [0x01; MIN_COMPRESS_LENGTH - 1];
+        let packet = sent(Compression::Zlib, &data);
+        assert_eq!(header(&packet), (data.len(), 0, 0));
+        assert_eq!(packet[COMPRESSED_HEADER_SIZE..], data);
+
+        let mut reader = stream(Compression::Zlib, packet);
+        let mut received = [0; MIN_COMPRESS_LENGTH - 1];
+        reader.read_exact(&mut received).unwrap();
+        assert_eq!(received, data);
+    }
+
+    // bytes that don't repeat, they come out longer compressed
+    #[test]
+    fn incompressible_payload_is_sent_as_is() {
+        let mut seed = 0x2545_f491_u32;
+        let data = (0..200)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect::<Vec<_>>();
+        for compression in [Compression::Zlib, Compression::Zstd(DEFAULT_ZSTD_LEVEL)] {
+            let packet = sent(compression, &data);
+            assert_eq!(header(&packet), (data.len(), 0, 0), "{compression:?}");
+            assert_eq!(packet[COMPRESSED_HEADER_SIZE..], data);
+        }
+    }
+
+    #[test]
+    fn sequence_id_continues_from_the_client() {
+        let mut incoming = vec![0x04, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00];
+        incoming.extend([0x00, 0x00, 0x00, 0x00]);
+        let mut conn = stream(Compression::Zlib, incoming);
+        conn.read_exact(&mut [0; 4]).unwrap();
+
+        for _ in 0..2 {
+            conn.write_all(b"ok").unwrap();
+            conn.flush().unwrap();
+        }
+        let output = &conn.inner.output;
+        assert_eq!(header(output), (2, 6, 0));
+        assert_eq!(header(&output[COMPRESSED_HEADER_SIZE + 2..]), (2, 7, 0));
+
+        conn.reset_sequence();
+        conn.write_all(b"ok").unwrap();
+        conn.flush().unwrap();
+        assert_eq!(header(&conn.inner.output[2 * (COMPRESSED_HEADER_SIZE + 2)..]), (2, 0, 0));
+    }
+}
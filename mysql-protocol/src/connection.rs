@@ -0,0 +1,226 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use crate::compression::{CompressedStream, Compression};
+use crate::error::ProtocolError;
+
+const MAX_PACKET_SIZE: usize = 16_777_215; // 2 ** 24 - 1
+
+// server side max_allowed_packet, 64 MB as in MySQL 8.0+
+pub const MAX_ALLOWED_PACKET: usize = 67_108_864;
+
+/// Packet level reader and writer which keeps track of the sequence id.
+///
+/// The sequence starts from 0 with every command sent by the client and increments with each
+/// packet in either direction, so the handlers never deal with packet numbers.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_packets.html#sect_protocol_basic_packets_sequence_id
+pub struct Connection {
+    stream: CompressedStream<TcpStream>,
+    sequence_id: u8,
+    max_allowed_packet: usize,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Self {
+        Connection {
+            stream: CompressedStream::new(stream),
+            sequence_id: 0,
+            max_allowed_packet: MAX_ALLOWED_PACKET,
+        }
+    }
+
+    pub fn enable_compression(&mut self, compression: Compression) {
+        self.stream.enable(compression);
+    }
+
+    pub fn set_max_allowed_packet(&mut self, max_allowed_packet: usize) {
+        self.max_allowed_packet = max_allowed_packet;
+    }
+
+    // whether a payload can be sent, before a response is started that it would leave half-finished
+    pub fn fits(&self, data: &[u8]) -> bool {
+        data.len() <= self.max_allowed_packet
+    }
+
+    // every command starts a new sequence
+    pub fn read_command(&mut self) -> Result<Vec<u8>, std::io::Error> {
+        self.sequence_id = 0;
+        self.read_packet()
+    }
+
+    // a client starts a new sequence, and a new compressed one, with every command it sends
+    pub fn write_command(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        self.sequence_id = 0;
+        self.stream.reset_sequence();
+        self.write_packet(data)
+    }
+
+    // joins the continuation packets of payloads of 16 MB and more
+    pub fn read_packet(&mut self) -> Result<Vec<u8>, std::io::Error> {
+        let mut buffer = Vec::new();
+
+        loop {
+            let mut header = [0u8; 4];
+            self.stream.read_exact(&mut header)?;
+
+            let packet_num = header[3];
+            // let packet_len = header[0] as usize + ((header[1] as usize) << 8) + ((header[2] as usize) << 16);
+            let packet_len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+
+            if buffer.len() + packet_len > self.max_allowed_packet {
+                std::io::copy(&mut (&mut self.stream).take(packet_len as u64), &mut std::io::sink())?;
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ProtocolError::PacketTooLarge));
+            }
+
+            let start = buffer.len();
+            buffer.resize(start + packet_len, 0);
+            self.stream.read_exact(&mut buffer[start..])?;
+
+            // the payload is consumed anyway, so the client gets the error instead of a reset connection
+            let expected = self.sequence_id;
+            self.sequence_id = packet_num.wrapping_add(1);
+            if packet_num != expected {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ProtocolError::PacketsOutOfOrder));
+            }
+
+            if packet_len < MAX_PACKET_SIZE {
+                return Ok(buffer);
+            }
+        }
+    }
+
+    // payloads of 16 MB and more are split into 0xFFFFFF-byte packets with increasing packet numbers,
+    // a payload which is an exact multiple of that is followed by an empty packet
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_packets.html#sect_protocol_basic_packets_sending_mt_16mb
+    pub fn write_packet(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        if !self.fits(data) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ProtocolError::PacketTooLarge));
+        }
+
+        let mut chunks = data.chunks(MAX_PACKET_SIZE);
+        loop {
+            let chunk = chunks.next().unwrap_or_default();
+
+            // Header: length (3 bytes, little-endian) followed by a packet number
+            let packet_len_bytes = (chunk.len() as u32).to_le_bytes();
+            let header = [packet_len_bytes[0], packet_len_bytes[1], packet_len_bytes[2], self.sequence_id];
+
+            self.stream.write_all(&header)?;
+            self.stream.write_all(chunk)?;
+            self.sequence_id = self.sequence_id.wrapping_add(1);
+
+            if chunk.len() < MAX_PACKET_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    // whether the client went away or the connection was killed, without waiting for it to send anything
+    pub fn is_closed(&self) -> bool {
+        let stream = self.stream.get_ref();
+        if stream.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let closed = match stream.peek(&mut [0]) {
+            Ok(len) => len == 0,
+            Err(e) => e.kind() != std::io::ErrorKind::WouldBlock,
+        };
+        let _ = stream.set_nonblocking(false);
+        closed
+    }
+
+    // the whole response goes out at once, so it's compressed as a single packet
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    fn connected() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    // the writer runs on its own thread, 16 MB don't fit in the socket buffers
+    fn write_packets(stream: TcpStream, payloads: Vec<Vec<u8>>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let mut conn = Connection::new(stream);
+            for payload in payloads {
+                conn.write_packet(&payload).unwrap();
+            }
+            conn.flush().unwrap();
+        })
+    }
+
+    #[test]
+    fn payload_of_max_packet_size_is_followed_by_an_empty_packet() {
+        let (client, mut server) = connected();
+        let writer = write_packets(client, vec![vec![0xab; MAX_PACKET_SIZE]]);
+
+        let mut raw = vec![0; 4 + MAX_PACKET_SIZE + 4];
+        server.read_exact(&mut raw).unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(raw[..4], [0xff, 0xff, 0xff, 0x00]);
+        assert!(raw[4..4 + MAX_PACKET_SIZE].iter().all(|&b| b == 0xab));
+        assert_eq!(raw[4 + MAX_PACKET_SIZE..], [0x00, 0x00, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn split_payloads_are_joined() {
+        let (client, server) = connected();
+        let lengths = [0, MAX_PACKET_SIZE - 1, MAX_PACKET_SIZE, MAX_PACKET_SIZE + 1, 2 * MAX_PACKET_SIZE];
+        let payloads = lengths.iter().enumerate().map(|(i, &len)| vec![i as u8; len]).collect::<Vec<_>>();
+        let writer = write_packets(client, payloads.clone());
+
+        let mut conn = Connection::new(server);
+        for payload in payloads {
+            assert_eq!(conn.read_packet().unwrap(), payload);
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn packet_over_max_allowed_packet() {
+        let (client, server) = connected();
+
+        let mut conn = Connection::new(client);
+        conn.set_max_allowed_packet(1024);
+        assert!(conn.fits(&[0; 1024]));
+        let error = conn.write_packet(&[0; 1025]).unwrap_err();
+        assert_eq!(error.get_ref().and_then(|e| e.downcast_ref()), Some(&ProtocolError::PacketTooLarge));
+
+        // what's too large is read and dropped, the next command is where it should be
+        let writer = thread::spawn(move || {
+            conn.set_max_allowed_packet(MAX_ALLOWED_PACKET);
+            conn.write_command(&[0; 1025]).unwrap();
+            conn.write_command(&[1; 10]).unwrap();
+            conn.flush().unwrap();
+        });
+        let mut conn = Connection::new(server);
+        conn.set_max_allowed_packet(1024);
+        let error = conn.read_command().unwrap_err();
+        assert_eq!(error.get_ref().and_then(|e| e.downcast_ref()), Some(&ProtocolError::PacketTooLarge));
+        assert_eq!(conn.read_command().unwrap(), [1; 10]);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn packets_out_of_order() {
+        let (mut client, server) = connected();
+        client.write_all(&[0x01, 0x00, 0x00, 0x05, 0x0e]).unwrap();
+
+        let mut conn = Connection::new(server);
+        let error = conn.read_packet().unwrap_err();
+        assert_eq!(error.get_ref().and_then(|e| e.downcast_ref()), Some(&ProtocolError::PacketsOutOfOrder));
+    }
+}
@@ -0,0 +1,23 @@
+/// A packet that can't be read, before anything in it means something to the server.
+///
+/// The server turns these into the ERR packets MySQL sends for them, a client just gives up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProtocolError {
+    MalformedPacket, // shorter than its contents, or a value out of range
+    PacketTooLarge, // over max_allowed_packet
+    PacketsOutOfOrder, // unexpected sequence id
+    UnknownCommand(u8),
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::MalformedPacket => write!(f, "malformed packet"),
+            ProtocolError::PacketTooLarge => write!(f, "packet bigger than max_allowed_packet"),
+            ProtocolError::PacketsOutOfOrder => write!(f, "packets out of order"),
+            ProtocolError::UnknownCommand(command) => write!(f, "unknown command 0x{command:02x}"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
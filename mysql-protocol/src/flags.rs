@@ -0,0 +1,41 @@
+//! Flags and option values the packets carry.
+
+// client capability flags
+// https://dev.mysql.com/doc/dev/mysql-server/latest/group__group__cs__capabilities__flags.html
+pub const CLIENT_LONG_PASSWORD: u32 = 0x0000_0001;
+pub const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+pub const CLIENT_COMPRESS: u32 = 0x0000_0020;
+pub const CLIENT_LOCAL_FILES: u32 = 0x0000_0080;
+pub const CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+pub const CLIENT_TRANSACTIONS: u32 = 0x0000_2000;
+pub const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+pub const CLIENT_MULTI_STATEMENTS: u32 = 0x0001_0000;
+pub const CLIENT_MULTI_RESULTS: u32 = 0x0002_0000;
+pub const CLIENT_PLUGIN_AUTH: u32 = 0x0008_0000;
+pub const CLIENT_CONNECT_ATTRS: u32 = 0x0010_0000;
+pub const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x0020_0000;
+pub const CLIENT_SESSION_TRACK: u32 = 0x0080_0000;
+pub const CLIENT_DEPRECATE_EOF: u32 = 0x0100_0000;
+pub const CLIENT_ZSTD_COMPRESSION_ALGORITHM: u32 = 0x0400_0000;
+pub const CLIENT_QUERY_ATTRIBUTES: u32 = 0x0800_0000;
+
+// server status flags
+// https://dev.mysql.com/doc/dev/mysql-server/latest/mysql__com_8h.html#a1d854e841086925be1883e4d7b4e8cad
+pub const SERVER_STATUS_AUTOCOMMIT: u16 = 0x0002;
+pub const SERVER_MORE_RESULTS_EXISTS: u16 = 0x0008;
+pub const SERVER_STATUS_NO_INDEX_USED: u16 = 0x0020;
+pub const SERVER_STATUS_CURSOR_EXISTS: u16 = 0x0040;
+pub const SERVER_STATUS_LAST_ROW_SENT: u16 = 0x0080;
+pub const SERVER_SESSION_STATE_CHANGED: u16 = 0x4000;
+
+// COM_SET_OPTION options
+pub const MYSQL_OPTION_MULTI_STATEMENTS_ON: u16 = 0;
+pub const MYSQL_OPTION_MULTI_STATEMENTS_OFF: u16 = 1;
+
+// COM_STMT_EXECUTE flags
+pub const CURSOR_TYPE_READ_ONLY: u8 = 0x01;
+pub const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+// COM_BINLOG_DUMP flags
+pub const BINLOG_DUMP_NON_BLOCK: u16 = 0x0001;
+pub const BINLOG_THROUGH_GTID: u16 = 0x0004;
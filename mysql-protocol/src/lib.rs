@@ -0,0 +1,20 @@
+//! Packets of the MySQL client/server protocol, as the server and its clients send them.
+//!
+//! Every message type has `as_bytes` to encode its payload and `parse` to decode it, the
+//! [`connection::Connection`] adds the packet headers, the compressed protocol and splits
+//! payloads of 16 MB and more. Text is UTF-8 on this side, [`charset::Charset`] converts it
+//! to and from the character set the client talks in.
+//!
+//! https://dev.mysql.com/doc/dev/mysql-server/latest/PAGE_PROTOCOL.html
+
+pub mod charset;
+pub mod codec;
+pub mod column;
+pub mod command;
+pub mod compression;
+pub mod connection;
+pub mod error;
+pub mod flags;
+pub mod packet;
+
+pub use error::ProtocolError;
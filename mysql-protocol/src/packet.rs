@@ -0,0 +1,509 @@
+use crate::codec::{LenencWrite, NULL_VALUE, Reader};
+use crate::error::ProtocolError;
+use crate::flags::{CLIENT_DEPRECATE_EOF, CLIENT_SESSION_TRACK, SERVER_SESSION_STATE_CHANGED};
+
+/// Packets the server sends, other than the column definitions and rows of a result set.
+///
+/// A packet doesn't say what it is on its own, `0x00` starts both an OK and a COM_STMT_PREPARE_OK,
+/// so [`Packet::parse`] takes the ones that can answer any command and the greeting and the
+/// COM_STMT_PREPARE response have parsers of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    Greeting(Handshake),
+    AuthSuccess,
+    OK(u16), // status_flags
+    // `session_state` is `None` without CLIENT_SESSION_TRACK
+    OkInfo { affected_rows: u64, warnings: u16, status_flags: u16, info: String, session_state: Option<Vec<StateChange>> },
+    ColumnCount(u64),
+    Eof(u16), // status_flags
+    // OK packet with the 0xFE header, ends a result set with CLIENT_DEPRECATE_EOF
+    OkEof { status_flags: u16, session_state: Option<Vec<StateChange>> },
+    PrepareOk { statement_id: u32, columns: u16, params: u16 },
+    LocalInfile(String), // file name
+    Error(ErrorPacket),
+}
+
+impl Packet {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut response = Vec::new();
+
+        match self {
+            Packet::Greeting(handshake) => {
+                response = handshake.as_bytes();
+            }
+            Packet::AuthSuccess => {
+                response.extend([0x01, 0x03]); // SHA2 Auth State: fast_auth_success
+            }
+            Packet::OK(status_flags) => {
+                response.push(0x00); // OK
+                response.push_lenenc_int(0); // affected_rows
+                response.push_lenenc_int(0); // last_insert_id
+
+                response.extend(status_flags.to_le_bytes());
+
+                response.extend([0x00, 0x00]); // warnings
+            }
+            Packet::OkInfo { affected_rows, warnings, status_flags, info, session_state } => {
+                response.push(0x00); // OK
+                push_ok(&mut response, *affected_rows, *warnings, *status_flags, info, session_state.as_deref());
+            }
+            Packet::ColumnCount(c) => {
+                response.push_lenenc_int(*c);
+            }
+            Packet::Eof(status_flags) => {
+                response.push(0xfe);
+                response.extend([0x00, 0x00]); // warnings
+                response.extend(status_flags.to_le_bytes());
+            }
+            Packet::OkEof { status_flags, session_state } => {
+                response.push(0xfe); // OK, EOF header
+                push_ok(&mut response, 0, 0, *status_flags, "", session_state.as_deref());
+            }
+            Packet::PrepareOk { statement_id, columns, params } => {
+                response.push(0x00); // OK
+                response.extend(statement_id.to_le_bytes()); // statement id
+                response.extend(columns.to_le_bytes()); // number of fields
+                response.extend(params.to_le_bytes()); // number of parameters
+                response.push(0x00);
+                response.extend(0u16.to_le_bytes()); // warnings
+            }
+            Packet::LocalInfile(file) => {
+                response.push(0xfb); // LOCAL INFILE
+                response.extend(file.as_bytes());
+            }
+            Packet::Error(error) => {
+                response = error.as_bytes();
+            }
+        }
+
+        response
+    }
+
+    /// A response to a command, an OK comes back as `OkInfo` and an EOF as `OkEof` when the client set
+    /// CLIENT_DEPRECATE_EOF. Anything that doesn't start like one of the others is a column count.
+    pub fn parse(data: &[u8], capabilities: u32) -> Result<Packet, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        match data.first() {
+            Some(0x00) => {
+                reader.read_u8()?;
+                let (affected_rows, warnings, status_flags, info, session_state) = read_ok(&mut reader, capabilities)?;
+                Ok(Packet::OkInfo { affected_rows, warnings, status_flags, info, session_state })
+            }
+            Some(0xff) => Ok(Packet::Error(ErrorPacket::parse(data)?)),
+            // the session state can make an OK with the 0xFE header as long as a column count, as the
+            // client library it's an OK unless it fills a whole packet, which no column count needs
+            Some(0xfe) if capabilities & CLIENT_DEPRECATE_EOF != 0 && data.len() < 0xff_ffff => {
+                reader.read_u8()?;
+                let (_, _, status_flags, _, session_state) = read_ok(&mut reader, capabilities)?;
+                Ok(Packet::OkEof { status_flags, session_state })
+            }
+            // an EOF is shorter than 9 bytes, a length-encoded column count with the 0xFE header isn't
+            Some(0xfe) if data.len() < 9 => {
+                reader.read_u8()?;
+                reader.read_u16()?; // warnings
+                Ok(Packet::Eof(reader.read_u16()?))
+            }
+            Some(0xfb) => Ok(Packet::LocalInfile(String::from_utf8_lossy(&data[1..]).to_string())),
+            Some(0x01) if data == [0x01, 0x03] => Ok(Packet::AuthSuccess),
+            _ => Ok(Packet::ColumnCount(reader.read_lenenc_int()?)),
+        }
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_prepare.html#sect_protocol_com_stmt_prepare_response_ok
+    pub fn parse_prepare_ok(data: &[u8]) -> Result<Packet, ProtocolError> {
+        if data.first() == Some(&0xff) {
+            return Ok(Packet::Error(ErrorPacket::parse(data)?));
+        }
+
+        let mut reader = Reader::new(data);
+        reader.read_u8()?; // OK
+        let statement_id = reader.read_u32()?;
+        let columns = reader.read_u16()?;
+        let params = reader.read_u16()?;
+
+        Ok(Packet::PrepareOk { statement_id, columns, params })
+    }
+}
+
+// an OK packet after its header, `session_state` is `None` without CLIENT_SESSION_TRACK
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_ok_packet.html
+fn push_ok(
+    response: &mut Vec<u8>,
+    affected_rows: u64,
+    warnings: u16,
+    status_flags: u16,
+    info: &str,
+    session_state: Option<&[StateChange]>,
+) {
+    response.push_lenenc_int(affected_rows);
+    response.push_lenenc_int(0); // last_insert_id
+
+    let changed = session_state.is_some_and(|changes| !changes.is_empty());
+    let status_flags = if changed { status_flags | SERVER_SESSION_STATE_CHANGED } else { status_flags };
+    response.extend(status_flags.to_le_bytes());
+    response.extend(warnings.to_le_bytes());
+
+    match session_state {
+        // the info is length-encoded, and left out when there's neither info nor state to report
+        Some(changes) => {
+            if changed || !info.is_empty() {
+                response.push_lenenc_str(info.as_bytes());
+            }
+            if changed {
+                response.push_lenenc_str(&changes.iter().flat_map(StateChange::as_bytes).collect::<Vec<_>>());
+            }
+        }
+        None => response.extend(info.as_bytes()), // human readable status, up to the end of the packet
+    }
+}
+
+type OkFields = (u64, u16, u16, String, Option<Vec<StateChange>>);
+
+// affected_rows, warnings, status_flags, info and session_state of an OK packet after its header
+fn read_ok(reader: &mut Reader, capabilities: u32) -> Result<OkFields, ProtocolError> {
+    let affected_rows = reader.read_lenenc_int()?;
+    reader.read_lenenc_int()?; // last_insert_id
+    let status_flags = reader.read_u16()?;
+    let warnings = reader.read_u16()?;
+
+    let (info, session_state) = if capabilities & CLIENT_SESSION_TRACK != 0 {
+        let info = if reader.is_empty() { Vec::new() } else { reader.read_lenenc_str()?.to_vec() };
+        let mut changes = Vec::new();
+        if status_flags & SERVER_SESSION_STATE_CHANGED != 0 {
+            let mut state = Reader::new(reader.read_lenenc_str()?);
+            while !state.is_empty() {
+                changes.push(StateChange::parse(&mut state)?);
+            }
+        }
+        (info, Some(changes))
+    } else {
+        (reader.read_rest().to_vec(), None)
+    };
+
+    let info = String::from_utf8_lossy(&info).to_string();
+    Ok((affected_rows, warnings, status_flags, info, session_state))
+}
+
+/// The greeting, protocol version 10, the first packet of every connection.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_handshake_v10.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake {
+    pub server_version: String,
+    pub connection_id: u32,
+    pub auth_plugin_data: Vec<u8>, // the scramble, 20 bytes
+    pub capabilities: u32,
+    pub collation: u8, // only the low byte of the collation number
+    pub status_flags: u16,
+    pub auth_plugin: String,
+}
+
+impl Handshake {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut response = Vec::new();
+
+        response.push(0x0A); // 10, protocol version number
+
+        response.extend(self.server_version.as_bytes());
+        response.push(0x00);
+
+        response.extend(self.connection_id.to_le_bytes()); // thread ID
+
+        // salt (part 1)
+        let split = self.auth_plugin_data.len().min(8);
+        response.extend(&self.auth_plugin_data[..split]);
+        response.push(0x00);
+
+        response.extend((self.capabilities as u16).to_le_bytes()); // server capabilities
+        response.push(self.collation);
+        response.extend(self.status_flags.to_le_bytes());
+        response.extend(((self.capabilities >> 16) as u16).to_le_bytes()); // extended server capabilities
+
+        // authentication plugin data length, with the 0 at the end of the salt
+        response.push(self.auth_plugin_data.len() as u8 + 1);
+
+        // unused, 10 bytes
+        let unused = [0; 10];
+        response.extend(unused);
+
+        // salt (part 2)
+        response.extend(&self.auth_plugin_data[split..]);
+        response.push(0x00);
+
+        // authentication plugin
+        response.extend(self.auth_plugin.as_bytes());
+        response.push(0x00);
+
+        response
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Handshake, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        if reader.read_u8()? != 0x0A {
+            return Err(ProtocolError::MalformedPacket);
+        }
+        let server_version = String::from_utf8_lossy(reader.read_null_str()?).to_string();
+        let connection_id = reader.read_u32()?;
+        let mut auth_plugin_data = reader.read_bytes(8)?.to_vec();
+        reader.read_u8()?; // filler
+
+        let mut capabilities = reader.read_u16()? as u32;
+        let collation = reader.read_u8()?;
+        let status_flags = reader.read_u16()?;
+        capabilities |= (reader.read_u16()? as u32) << 16;
+        let auth_plugin_data_len = reader.read_u8()? as usize;
+        reader.read_bytes(10)?; // unused
+
+        // at least 13 bytes, the last of them the 0 the scramble ends with
+        let part2 = reader.read_bytes(auth_plugin_data_len.saturating_sub(8).max(13))?;
+        auth_plugin_data.extend(part2.strip_suffix(&[0]).unwrap_or(part2));
+
+        let auth_plugin = match reader.read_null_str() {
+            Ok(name) => name,
+            Err(_) => reader.read_rest(), // some servers leave out the 0
+        };
+        let auth_plugin = String::from_utf8_lossy(auth_plugin).to_string();
+
+        Ok(Handshake { server_version, connection_id, auth_plugin_data, capabilities, collation, status_flags, auth_plugin })
+    }
+}
+
+/// An error in an ERR packet.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_err_packet.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorPacket {
+    pub code: u16,
+    pub sql_state: String, // 5 characters
+    pub message: String,
+}
+
+impl ErrorPacket {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut response = Vec::new();
+
+        response.push(0xff); // ERR
+        response.extend(self.code.to_le_bytes()); // error code
+        response.push(b'#'); // SQL state marker
+        response.extend(self.sql_state.as_bytes()); // SQL state, 5 characters
+        response.extend(self.message.as_bytes()); // human readable message
+
+        response
+    }
+
+    pub fn parse(data: &[u8]) -> Result<ErrorPacket, ProtocolError> {
+        let mut reader = Reader::new(data);
+
+        if reader.read_u8()? != 0xff {
+            return Err(ProtocolError::MalformedPacket);
+        }
+        let code = reader.read_u16()?;
+        let sql_state = if data.get(3) == Some(&b'#') {
+            reader.read_u8()?;
+            String::from_utf8_lossy(reader.read_bytes(5)?).to_string()
+        } else {
+            "HY000".to_string() // before the 4.1 protocol
+        };
+        let message = String::from_utf8_lossy(reader.read_rest()).to_string();
+
+        Ok(ErrorPacket { code, sql_state, message })
+    }
+}
+
+impl std::fmt::Display for ErrorPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ERROR {} ({}): {}", self.code, self.sql_state, self.message)
+    }
+}
+
+/// A row of a text result set, every value as a length-encoded string in the column's character set,
+/// `None` for NULL.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset_row.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRow(pub Vec<Option<Vec<u8>>>);
+
+impl TextRow {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut response = Vec::new();
+        for value in &self.0 {
+            match value {
+                Some(value) => response.push_lenenc_str(value),
+                None => response.push(NULL_VALUE),
+            }
+        }
+        response
+    }
+
+    // the column count comes from the result set, a row doesn't say how many values it has
+    pub fn parse(data: &[u8], columns: usize) -> Result<TextRow, ProtocolError> {
+        let mut reader = Reader::new(data);
+        let values = (0..columns)
+            .map(|_| reader.read_nullable_lenenc_str().map(|value| value.map(<[u8]>::to_vec)))
+            .collect::<Result<_, _>>()?;
+        Ok(TextRow(values))
+    }
+}
+
+/// Change to the session reported in the next OK packet, for clients with CLIENT_SESSION_TRACK.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_ok_packet.html#sect_protocol_basic_ok_packet_sessinfo
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateChange {
+    SystemVariable(String, String), // name, value
+    Schema(String),
+    StateChanged, // something in the session changed, so it can't be swapped for another one
+    Gtid(String), // of the transaction the statement committed
+    TransactionCharacteristics(String), // statements that restart the transaction as it is, empty once it ends
+    TransactionState(String), // 8 characters
+}
+
+impl StateChange {
+    // the order the trackers are reported in
+    pub fn tracker(&self) -> u8 {
+        match self {
+            StateChange::SystemVariable(..) => 0x00,
+            StateChange::Schema(_) => 0x01,
+            StateChange::StateChanged => 0x02,
+            StateChange::Gtid(_) => 0x03,
+            StateChange::TransactionCharacteristics(_) => 0x04,
+            StateChange::TransactionState(_) => 0x05,
+        }
+    }
+
+    // tracker type and its data as a length-encoded string
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        match self {
+            StateChange::SystemVariable(name, value) => {
+                data.push_lenenc_str(name.as_bytes());
+                data.push_lenenc_str(value.as_bytes());
+            }
+            StateChange::Schema(schema) => data.push_lenenc_str(schema.as_bytes()),
+            StateChange::StateChanged => data.push_lenenc_str(b"1"),
+            StateChange::Gtid(gtid) => {
+                data.push(0x00); // encoding specification, the GTIDs as text
+                data.push_lenenc_str(gtid.as_bytes());
+            }
+            StateChange::TransactionCharacteristics(text) | StateChange::TransactionState(text) => {
+                data.push_lenenc_str(text.as_bytes());
+            }
+        }
+
+        let mut response = vec![self.tracker()];
+        response.push_lenenc_str(&data);
+        response
+    }
+
+    pub fn parse(reader: &mut Reader) -> Result<StateChange, ProtocolError> {
+        let tracker = reader.read_u8()?;
+        let mut data = Reader::new(reader.read_lenenc_str()?);
+        let mut text = || data.read_lenenc_str().map(|text| String::from_utf8_lossy(text).to_string());
+
+        let change = match tracker {
+            0x00 => StateChange::SystemVariable(text()?, text()?),
+            0x01 => StateChange::Schema(text()?),
+            0x02 => {
+                text()?;
+                StateChange::StateChanged
+            }
+            0x03 => {
+                data.read_u8()?; // encoding specification
+                StateChange::Gtid(String::from_utf8_lossy(data.read_lenenc_str()?).to_string())
+            }
+            0x04 => StateChange::TransactionCharacteristics(text()?),
+            0x05 => StateChange::TransactionState(text()?),
+            _ => return Err(ProtocolError::MalformedPacket),
+        };
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::SERVER_STATUS_AUTOCOMMIT;
+
+    const SESSION: u32 = CLIENT_SESSION_TRACK | CLIENT_DEPRECATE_EOF;
+
+    #[test]
+    fn state_changes() {
+        let cases: [(StateChange, &[u8]); 7] = [
+            (StateChange::SystemVariable("autocommit".to_string(), "OFF".to_string()), b"\x00\x0f\x0aautocommit\x03OFF"),
+            (StateChange::Schema("test".to_string()), b"\x01\x05\x04test"),
+            (StateChange::StateChanged, b"\x02\x02\x011"),
+            (StateChange::Gtid("uuid:1-5".to_string()), b"\x03\x0a\x00\x08uuid:1-5"),
+            (StateChange::TransactionCharacteristics("START TRANSACTION;".to_string()), b"\x04\x13\x12START TRANSACTION;"),
+            (StateChange::TransactionCharacteristics(String::new()), b"\x04\x01\x00"),
+            (StateChange::TransactionState("T_______".to_string()), b"\x05\x09\x08T_______"),
+        ];
+
+        for (change, bytes) in cases {
+            assert_eq!(change.as_bytes(), bytes, "{change:?}");
+            assert_eq!(StateChange::parse(&mut Reader::new(bytes)), Ok(change));
+        }
+
+        assert_eq!(StateChange::parse(&mut Reader::new(b"\x06\x01\x00")), Err(ProtocolError::MalformedPacket));
+        assert_eq!(StateChange::parse(&mut Reader::new(b"\x01\x05\x04te")), Err(ProtocolError::MalformedPacket));
+    }
+
+    #[test]
+    fn ok_info() {
+        let ok = |info: &str, session_state| Packet::OkInfo {
+            affected_rows: 3,
+            warnings: 1,
+            status_flags: SERVER_STATUS_AUTOCOMMIT,
+            info: info.to_string(),
+            session_state,
+        };
+
+        // without CLIENT_SESSION_TRACK the info runs to the end of the packet
+        let bytes = b"\x00\x03\x00\x02\x00\x01\x00Rows matched: 3";
+        assert_eq!(ok("Rows matched: 3", None).as_bytes(), bytes);
+        assert_eq!(Packet::parse(bytes, 0), Ok(ok("Rows matched: 3", None)));
+
+        // with it the info is length-encoded, and left out when there's nothing to report
+        let bytes = b"\x00\x03\x00\x02\x00\x01\x00";
+        assert_eq!(ok("", Some(Vec::new())).as_bytes(), bytes);
+        assert_eq!(Packet::parse(bytes, SESSION), Ok(ok("", Some(Vec::new()))));
+        let bytes = b"\x00\x03\x00\x02\x00\x01\x00\x0fRows matched: 3";
+        assert_eq!(ok("Rows matched: 3", Some(Vec::new())).as_bytes(), bytes);
+        assert_eq!(Packet::parse(bytes, SESSION), Ok(ok("Rows matched: 3", Some(Vec::new()))));
+
+        // changes set SERVER_SESSION_STATE_CHANGED
+        let changes = vec![StateChange::Schema("test".to_string()), StateChange::StateChanged];
+        let bytes = b"\x00\x03\x00\x02\x40\x01\x00\x00\x0b\x01\x05\x04test\x02\x02\x011";
+        assert_eq!(ok("", Some(changes.clone())).as_bytes(), bytes);
+        let Ok(Packet::OkInfo { status_flags, session_state, .. }) = Packet::parse(bytes, SESSION) else {
+            panic!("not an OK");
+        };
+        assert_eq!(status_flags, SERVER_STATUS_AUTOCOMMIT | SERVER_SESSION_STATE_CHANGED);
+        assert_eq!(session_state, Some(changes));
+    }
+
+    #[test]
+    fn ok_or_eof() {
+        let eof = Packet::Eof(SERVER_STATUS_AUTOCOMMIT);
+        assert_eq!(eof.as_bytes(), b"\xfe\x00\x00\x02\x00");
+        assert_eq!(Packet::parse(&eof.as_bytes(), 0), Ok(eof));
+
+        let ok = Packet::OkEof { status_flags: SERVER_STATUS_AUTOCOMMIT, session_state: Some(Vec::new()) };
+        assert_eq!(ok.as_bytes(), b"\xfe\x00\x00\x02\x00\x00\x00");
+        assert_eq!(Packet::parse(&ok.as_bytes(), SESSION), Ok(ok));
+
+        // past the 9 bytes of an EOF, the 0xFE header is still an OK with CLIENT_DEPRECATE_EOF
+        let changes = vec![StateChange::SystemVariable("character_set_results".to_string(), "latin1".to_string())];
+        let ok = Packet::OkEof {
+            status_flags: SERVER_STATUS_AUTOCOMMIT | SERVER_SESSION_STATE_CHANGED,
+            session_state: Some(changes),
+        };
+        let bytes = ok.as_bytes();
+        assert!(bytes.len() > 9);
+        assert_eq!(Packet::parse(&bytes, SESSION), Ok(ok));
+
+        // and a column count without it
+        assert_eq!(Packet::parse(&[0xfe, 1, 0, 0, 0, 0, 0, 0, 0], 0), Ok(Packet::ColumnCount(1)));
+    }
+}
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+use mysql_protocol::codec::{LenencWrite, Reader};
+use mysql_protocol::column::ColumnType;
+use mysql_protocol::command::QueryAttribute;
+use mysql_protocol::flags::PARAMETER_COUNT_AVAILABLE;
+
+use crate::error::ServerError;
+use crate::resultset::{DateTime, Time, Value};
+
+/// Type of a bound parameter, sent with the first execution and reused until the client binds new ones.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamType {
+    pub column_type: ColumnType,
+    pub unsigned: bool,
+}
+
+/// Parameters of a COM_STMT_EXECUTE after the iteration count, `param_count` being the
+/// number of placeholders of the statement. With CLIENT_QUERY_ATTRIBUTES the client may
+/// send more values than that, the extra ones are named query attributes.
+///
+/// Parameters streamed with COM_STMT_SEND_LONG_DATA have no value in the packet, they take
+/// the contents of their `long_data` buffer instead.
+///
+/// Returns the values with their names, empty for the placeholders.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_execute.html
+pub fn read_params(
+    reader: &mut Reader,
+    flags: u8,
+    param_count: usize,
+    query_attributes: bool,
+    param_types: &mut Vec<ParamType>,
+    long_data: &HashMap<u16, Vec<u8>>,
+) -> Result<Vec<(String, Value)>, ServerError> {
+    let mut count = param_count;
+    if query_attributes && (param_count > 0 || flags & PARAMETER_COUNT_AVAILABLE != 0) {
+        count = reader.read_lenenc_int()? as usize;
+    }
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    read_values(reader, count, query_attributes, param_types, long_data)
+}
+
+/// Query attributes in front of the query text of a COM_QUERY, sent with CLIENT_QUERY_ATTRIBUTES.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query.html
+pub fn query_attributes(attributes: Vec<QueryAttribute>) -> Result<Vec<(String, Value)>, ServerError> {
+    attributes.into_iter()
+        .map(|attribute| {
+            let value = match &attribute.value {
+                Some(value) => read_value(&mut Reader::new(value), attribute.column_type, attribute.unsigned)?,
+                None => Value::Null,
+            };
+            Ok((attribute.name, value))
+        })
+        .collect()
+}
+
+// NULL bitmap, types, with names when `named`, and values of `count` parameters
+fn read_values(
+    reader: &mut Reader,
+    count: usize,
+    named: bool,
+    param_types: &mut Vec<ParamType>,
+    long_data: &HashMap<u16, Vec<u8>>,
+) -> Result<Vec<(String, Value)>, ServerError> {
+    let null_bitmap = reader.read_bytes(count.div_ceil(8))?;
+
+    let mut names = vec![String::new(); count];
+    if reader.read_u8()? == 1 {
+        // new params bound
+        param_types.clear();
+        for name in names.iter_mut() {
+            let column_type = ColumnType::try_from(reader.read_u8()?)?;
+            let unsigned = reader.read_u8()? & 0x80 != 0;
+            param_types.push(ParamType { column_type, unsigned });
+
+            if named {
+                *name = String::from_utf8_lossy(reader.read_lenenc_str()?).to_string();
+            }
+        }
+    }
+    if param_types.len() != count {
+        // nothing bound yet, or a different number of query attributes than the last time
+        return Err(ServerError::malformed_packet());
+    }
+
+    let mut params = Vec::with_capacity(count);
+    for (i, (name, param_type)) in names.into_iter().zip(param_types.iter()).enumerate() {
+        let value = if let Some(data) = long_data.get(&(i as u16)) {
+            Value::Bytes(data.clone())
+        } else if null_bitmap[i / 8] & (1 << (i % 8)) != 0 {
+            Value::Null
+        } else {
+            read_value(reader, param_type.column_type, param_type.unsigned)?
+        };
+        params.push((name, value));
+    }
+
+    Ok(params)
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row_value
+fn read_value(reader: &mut Reader, column_type: ColumnType, unsigned: bool) -> Result<Value, ServerError> {
+    let value = match column_type {
+        ColumnType::Null => Value::Null,
+        ColumnType::Tiny if unsigned => Value::Int(reader.read_u8()? as i64),
+        ColumnType::Tiny => Value::Int(reader.read_u8()? as i8 as i64),
+        ColumnType::Short | ColumnType::Year if unsigned => Value::Int(reader.read_u16()? as i64),
+        ColumnType::Short | ColumnType::Year => Value::Int(reader.read_u16()? as i16 as i64),
+        ColumnType::Long | ColumnType::Int24 if unsigned => Value::Int(reader.read_u32()? as i64),
+        ColumnType::Long | ColumnType::Int24 => Value::Int(reader.read_u32()? as i32 as i64),
+        ColumnType::LongLong if unsigned => Value::UInt(reader.read_u64()?),
+        ColumnType::LongLong => Value::Int(reader.read_u64()? as i64),
+        ColumnType::Float => Value::Float(f32::from_bits(reader.read_u32()?)),
+        ColumnType::Double => Value::Double(f64::from_bits(reader.read_u64()?)),
+        ColumnType::Date => Value::Date(read_datetime(reader)?),
+        ColumnType::DateTime | ColumnType::Timestamp => Value::DateTime(read_datetime(reader)?),
+        ColumnType::Time => Value::Time(read_time(reader)?),
+        // decimals, strings, blobs, BIT, JSON and GEOMETRY are all length-encoded strings
+        ColumnType::Decimal
+        | ColumnType::NewDecimal
+        | ColumnType::VarChar
+        | ColumnType::Bit
+        | ColumnType::Json
+        | ColumnType::Enum
+        | ColumnType::Set
+        | ColumnType::TinyBlob
+        | ColumnType::MediumBlob
+        | ColumnType::LongBlob
+        | ColumnType::Blob
+        | ColumnType::VarString
+        | ColumnType::String
+        | ColumnType::Geometry => Value::Bytes(reader.read_lenenc_str()?.to_vec()),
+    };
+
+    Ok(value)
+}
+
+// length 0, 4 (date only), 7 (with time) or 11 (with microseconds)
+fn read_datetime(reader: &mut Reader) -> Result<DateTime, ServerError> {
+    let len = reader.read_u8()?;
+    let mut value = DateTime::default();
+
+    if len >= 4 {
+        value.year = reader.read_u16()?;
+        value.month = reader.read_u8()?;
+        value.day = reader.read_u8()?;
+    }
+    if len >= 7 {
+        value.hour = reader.read_u8()?;
+        value.minute = reader.read_u8()?;
+        value.second = reader.read_u8()?;
+    }
+    if len >= 11 {
+        value.microsecond = reader.read_u32()?;
+    }
+    if !matches!(len, 0 | 4 | 7 | 11) {
+        return Err(ServerError::malformed_packet());
+    }
+
+    Ok(value)
+}
+
+// length 0, 8 or 12 (with microseconds)
+fn read_time(reader: &mut Reader) -> Result<Time, ServerError> {
+    let len = reader.read_u8()?;
+    let mut value = Time::default();
+
+    if len >= 8 {
+        value.negative = reader.read_u8()? == 1;
+        value.days = reader.read_u32()?;
+        value.hours = reader.read_u8()?;
+        value.minutes = reader.read_u8()?;
+        value.seconds = reader.read_u8()?;
+    }
+    if len >= 12 {
+        value.microseconds = reader.read_u32()?;
+    }
+    if !matches!(len, 0 | 8 | 12) {
+        return Err(ServerError::malformed_packet());
+    }
+
+    Ok(value)
+}
+
+/// Appends a non-NULL value of a binary result row, in the encoding of its column's type.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row_value
+pub fn push_value(response: &mut Vec<u8>, value: &Value, column_type: ColumnType) {
+    match column_type {
+        ColumnType::Null => {}
+        // the same bytes for signed and unsigned columns, UNSIGNED_FLAG tells them apart
+        ColumnType::Tiny => response.push(as_i64(value) as u8),
+        ColumnType::Short | ColumnType::Year => response.extend((as_i64(value) as u16).to_le_bytes()),
+        ColumnType::Long | ColumnType::Int24 => response.extend((as_i64(value) as u32).to_le_bytes()),
+        ColumnType::LongLong => match value {
+            Value::UInt(v) => response.extend(v.to_le_bytes()),
+            value => response.extend(as_i64(value).to_le_bytes()),
+        },
+        ColumnType::Float => response.extend((as_f64(value) as f32).to_le_bytes()),
+        ColumnType::Double => response.extend(as_f64(value).to_le_bytes()),
+        ColumnType::Date | ColumnType::DateTime | ColumnType::Timestamp => {
+            let datetime = match value {
+                Value::Date(v) | Value::DateTime(v) => Some(*v),
+                value => value.as_text().and_then(|text| parse_datetime(&text)),
+            };
+            push_datetime(response, &datetime.unwrap_or_default());
+        }
+        ColumnType::Time => {
+            let time = match value {
+                Value::Time(v) => Some(*v),
+                value => value.as_text().and_then(|text| parse_time(&text)),
+            };
+            push_time(response, &time.unwrap_or_default());
+        }
+        // decimals are sent in their text form, BIT, JSON and GEOMETRY as their raw bytes
+        ColumnType::Decimal
+        | ColumnType::NewDecimal
+        | ColumnType::VarChar
+        | ColumnType::Bit
+        | ColumnType::Json
+        | ColumnType::Enum
+        | ColumnType::Set
+        | ColumnType::TinyBlob
+        | ColumnType::MediumBlob
+        | ColumnType::LongBlob
+        | ColumnType::Blob
+        | ColumnType::VarString
+        | ColumnType::String
+        | ColumnType::Geometry => response.push_lenenc_str(&value.as_text().unwrap_or_default()),
+    }
+}
+
+fn as_i64(value: &Value) -> i64 {
+    match value {
+        Value::Int(v) => *v,
+        Value::UInt(v) => *v as i64,
+        Value::Float(v) => *v as i64,
+        Value::Double(v) => *v as i64,
+        value => String::from_utf8_lossy(&value.as_text().unwrap_or_default()).trim().parse().unwrap_or_default(),
+    }
+}
+
+fn as_f64(value: &Value) -> f64 {
+    match value {
+        Value::Int(v) => *v as f64,
+        Value::UInt(v) => *v as f64,
+        Value::Float(v) => *v as f64,
+        Value::Double(v) => *v,
+        value => String::from_utf8_lossy(&value.as_text().unwrap_or_default()).trim().parse().unwrap_or_default(),
+    }
+}
+
+// the shortest of the lengths read_datetime accepts that keeps every non-zero field
+fn push_datetime(response: &mut Vec<u8>, value: &DateTime) {
+    let len = if value.microsecond != 0 {
+        11
+    } else if value.hour != 0 || value.minute != 0 || value.second != 0 {
+        7
+    } else if value.year != 0 || value.month != 0 || value.day != 0 {
+        4
+    } else {
+        0
+    };
+
+    response.push(len);
+    if len >= 4 {
+        response.extend(value.year.to_le_bytes());
+        response.push(value.month);
+        response.push(value.day);
+    }
+    if len >= 7 {
+        response.extend([value.hour, value.minute, value.second]);
+    }
+    if len >= 11 {
+        response.extend(value.microsecond.to_le_bytes());
+    }
+}
+
+fn push_time(response: &mut Vec<u8>, value: &Time) {
+    let len = if value.microseconds != 0 {
+        12
+    } else if value.days != 0 || value.hours != 0 || value.minutes != 0 || value.seconds != 0 {
+        8
+    } else {
+        0
+    };
+
+    response.push(len);
+    if len >= 8 {
+        response.push(value.negative as u8);
+        response.extend(value.days.to_le_bytes());
+        response.extend([value.hours, value.minutes, value.seconds]);
+    }
+    if len >= 12 {
+        response.extend(value.microseconds.to_le_bytes());
+    }
+}
+
+// `YYYY-MM-DD[ hh:mm:ss[.ffffff]]`
+pub fn parse_datetime(text: &[u8]) -> Option<DateTime> {
+    let text = std::str::from_utf8(text).ok()?.trim();
+    let (date, time) = text.split_once([' ', 'T']).unwrap_or((text, ""));
+
+    // zero parts as in `0000-00-00` are allowed, anything past a calendar's isn't
+    let mut date = date.splitn(3, '-').map(str::parse::<u16>);
+    let mut value = DateTime {
+        year: date.next()?.ok()?,
+        month: date.next()?.ok().filter(|month| *month <= 12)? as u8,
+        day: date.next()?.ok().filter(|day| *day <= 31)? as u8,
+        ..DateTime::default()
+    };
+
+    if !time.is_empty() {
+        let time = parse_time(time.as_bytes()).filter(|time| !time.negative && time.days == 0)?;
+        value.hour = time.hours;
+        value.minute = time.minutes;
+        value.second = time.seconds;
+        value.microsecond = time.microseconds;
+    }
+
+    Some(value)
+}
+
+// `[-]hhh:mm:ss[.ffffff]`, hours past a day carry over into days
+pub fn parse_time(text: &[u8]) -> Option<Time> {
+    let text = std::str::from_utf8(text).ok()?.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (text, fraction) = text.split_once('.').unwrap_or((text, ""));
+
+    let mut parts = text.splitn(3, ':').map(str::parse::<u32>);
+    let hours = parts.next()?.ok()?;
+    let minutes = parts.next()?.ok().filter(|minutes| *minutes < 60)?;
+    let seconds = parts.next()?.ok().filter(|seconds| *seconds < 60)?;
+
+    // left aligned, `.5` is 500000 microseconds, digits past the sixth are dropped
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let microseconds = match fraction {
+        "" => 0,
+        fraction => format!("{:0<6}", fraction.chars().take(6).collect::<String>()).parse().ok()?,
+    };
+
+    Some(Time {
+        negative,
+        days: hours / 24,
+        hours: (hours % 24) as u8,
+        minutes: minutes as u8,
+        seconds: seconds as u8,
+        microseconds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datetimes() {
+        let datetime = |year, month, day, hour, minute, second, microsecond| DateTime {
+            year, month, day, hour, minute, second, microsecond,
+        };
+
+        assert_eq!(parse_datetime(b"2024-02-29"), Some(datetime(2024, 2, 29, 0, 0, 0, 0)));
+        assert_eq!(parse_datetime(b"0000-00-00"), Some(DateTime::default()));
+        assert_eq!(parse_datetime(b"2024-02-29 13:45:01"), Some(datetime(2024, 2, 29, 13, 45, 1, 0)));
+        assert_eq!(parse_datetime(b" 2024-02-29T13:45:01.5 "), Some(datetime(2024, 2, 29, 13, 45, 1, 500000)));
+
+        assert_eq!(parse_datetime(b""), None);
+        assert_eq!(parse_datetime(b"2024-13-45"), None);
+        assert_eq!(parse_datetime(b"2024-12-32"), None);
+        assert_eq!(parse_datetime(b"2024-12"), None);
+        assert_eq!(parse_datetime(b"2024-02-29 24:00:00"), None);
+        assert_eq!(parse_datetime(b"2024-02-29 -01:00:00"), None);
+        assert_eq!(parse_datetime(b"\xff"), None);
+    }
+
+    #[test]
+    fn times() {
+        let time = |negative, days, hours, minutes, seconds, microseconds| Time {
+            negative, days, hours, minutes, seconds, microseconds,
+        };
+
+        assert_eq!(parse_time(b"12:07:00"), Some(time(false, 0, 12, 7, 0, 0)));
+        assert_eq!(parse_time(b"-838:59:59"), Some(time(true, 34, 22, 59, 59, 0)));
+        assert_eq!(parse_time(b"00:00:01.000002"), Some(time(false, 0, 0, 0, 1, 2)));
+        assert_eq!(parse_time(b"00:00:01.1234567"), Some(time(false, 0, 0, 0, 1, 123456)));
+
+        assert_eq!(parse_time(b""), None);
+        assert_eq!(parse_time(b"12:7x:00"), None);
+        assert_eq!(parse_time(b"12:60:00"), None);
+        assert_eq!(parse_time(b"12:00:60"), None);
+        assert_eq!(parse_time(b"12:00"), None);
+        assert_eq!(parse_time(b"12:00:00.5x"), None);
+    }
+
+    #[test]
+    fn params() {
+        let mut packet = vec![0b10];
+        packet.push(1);
+        packet.extend([ColumnType::LongLong as u8, 0x80, ColumnType::VarString as u8, 0]);
+        packet.extend(u64::MAX.to_le_bytes());
+
+        let mut param_types = Vec::new();
+        let params = read_params(&mut Reader::new(&packet), 0, 2, false, &mut param_types, &HashMap::new()).unwrap();
+        assert_eq!(params, [(String::new(), Value::UInt(u64::MAX)), (String::new(), Value::Null)]);
+        assert_eq!(param_types.len(), 2);
+
+        // the types of the first execution are kept when no new ones are bound
+        let mut packet = vec![0, 0];
+        packet.extend((-1i64).to_le_bytes());
+        packet.push_lenenc_str(b"text");
+        let long_data = HashMap::from([(1, b"streamed".to_vec())]);
+        let params = read_params(&mut Reader::new(&packet), 0, 2, false, &mut param_types, &HashMap::new()).unwrap();
+        assert_eq!(params[1], (String::new(), Value::Bytes(b"text".to_vec())));
+        let params = read_params(&mut Reader::new(&packet), 0, 2, false, &mut param_types, &long_data).unwrap();
+        assert_eq!(params[1], (String::new(), Value::Bytes(b"streamed".to_vec())));
+
+        // nothing bound yet
+        let result = read_params(&mut Reader::new(&packet), 0, 2, false, &mut Vec::new(), &HashMap::new());
+        assert_eq!(result, Err(ServerError::malformed_packet()));
+
+        // truncated
+        let result = read_params(&mut Reader::new(&packet[..5]), 0, 2, false, &mut param_types, &HashMap::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn params_with_query_attributes() {
+        // one placeholder and one named query attribute
+        let mut packet = vec![2, 0, 1];
+        packet.extend([ColumnType::Tiny as u8, 0]);
+        packet.push_lenenc_str(b"");
+        packet.extend([ColumnType::Short as u8, 0x80]);
+        packet.push_lenenc_str(b"attribute");
+        packet.push(-1i8 as u8);
+        packet.extend(65535u16.to_le_bytes());
+
+        let mut param_types = Vec::new();
+        let params = read_params(&mut Reader::new(&packet), 0, 1, true, &mut param_types, &HashMap::new()).unwrap();
+        assert_eq!(params, [(String::new(), Value::Int(-1)), ("attribute".to_string(), Value::Int(65535))]);
+
+        // no placeholders and no PARAMETER_COUNT_AVAILABLE, nothing follows
+        assert_eq!(read_params(&mut Reader::new(&[]), 0, 0, true, &mut param_types, &HashMap::new()), Ok(Vec::new()));
+        let params = read_params(&mut Reader::new(&packet), PARAMETER_COUNT_AVAILABLE, 0, true, &mut param_types, &HashMap::new());
+        assert_eq!(params.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn values() {
+        let datetime = DateTime { year: 2024, month: 2, day: 29, hour: 13, ..DateTime::default() };
+        let time = Time { negative: true, days: 1, microseconds: 5, ..Time::default() };
+        let cases = [
+            (Value::Int(-2), ColumnType::Tiny, false, vec![0xfe]),
+            (Value::Int(-2), ColumnType::Short, false, vec![0xfe, 0xff]),
+            (Value::Int(70000), ColumnType::Long, false, vec![0x70, 0x11, 0x01, 0x00]),
+            (Value::UInt(u64::MAX), ColumnType::LongLong, true, vec![0xff; 8]),
+            (Value::Double(1.5), ColumnType::Double, false, 1.5f64.to_le_bytes().to_vec()),
+            (Value::Bytes(b"abc".to_vec()), ColumnType::VarString, false, b"\x03abc".to_vec()),
+            (Value::Date(DateTime::default()), ColumnType::Date, false, vec![0]),
+            (Value::DateTime(datetime), ColumnType::DateTime, false, vec![7, 0xe8, 0x07, 2, 29, 13, 0, 0]),
+            (Value::Time(time), ColumnType::Time, false, vec![12, 1, 1, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0]),
+        ];
+
+        for (value, column_type, unsigned, bytes) in cases {
+            let mut response = Vec::new();
+            push_value(&mut response, &value, column_type);
+            assert_eq!(response, bytes, "{value:?}");
+            assert_eq!(read_value(&mut Reader::new(&bytes), column_type, unsigned), Ok(value));
+        }
+    }
+
+    #[test]
+    fn values_from_text() {
+        let mut response = Vec::new();
+        push_value(&mut response, &Value::Bytes(b" 42 ".to_vec()), ColumnType::Long);
+        push_value(&mut response, &Value::Bytes(b"2024-02-29".to_vec()), ColumnType::Date);
+        push_value(&mut response, &Value::Bytes(b"12:7x:00".to_vec()), ColumnType::Time);
+        assert_eq!(response, [42, 0, 0, 0, 4, 0xe8, 0x07, 2, 29, 0]);
+    }
+}
@@ -0,0 +1,609 @@
+use std::sync::{Condvar, LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use flate2::Crc;
+use mysql_protocol::codec::LenencWrite;
+use mysql_protocol::column::{ColumnDefinition41, ColumnType, NOT_NULL_FLAG, UNSIGNED_FLAG, UTF8MB4_GENERAL_CI};
+use mysql_protocol::command::BinlogDump;
+use mysql_protocol::flags::BINLOG_DUMP_NON_BLOCK;
+
+use crate::catalog::Table;
+use crate::error::ServerError;
+use crate::gtid;
+use crate::resultset::{DateTime, ResultSet, Row, Value};
+
+/// The only binary log file, it starts with the server and lives in memory.
+pub const FILE_NAME: &str = "binlog.000001";
+pub const SERVER_ID: u32 = 1;
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html
+const QUERY_EVENT: u8 = 2;
+const ROTATE_EVENT: u8 = 4;
+const FORMAT_DESCRIPTION_EVENT: u8 = 15;
+const XID_EVENT: u8 = 16;
+const TABLE_MAP_EVENT: u8 = 19;
+const WRITE_ROWS_EVENT: u8 = 30; // v2
+const UPDATE_ROWS_EVENT: u8 = 31; // v2
+const DELETE_ROWS_EVENT: u8 = 32; // v2
+const GTID_LOG_EVENT: u8 = 33;
+const PREVIOUS_GTIDS_LOG_EVENT: u8 = 35;
+
+const EVENT_HEADER_LEN: usize = 19;
+const CHECKSUM_LEN: usize = 4;
+const BINLOG_CHECKSUM_ALG_CRC32: u8 = 1;
+const LOG_EVENT_ARTIFICIAL_F: u16 = 0x0020;
+const STMT_END_F: u16 = 0x0001;
+
+// post-header length of each event type from 1 on, as listed in the FORMAT_DESCRIPTION_EVENT
+const POST_HEADER_LENGTHS: [u8; 41] = [
+    56, 13, 0, 8, 0, 18, 0, 4, 4, 4, 4, 18, 0, 0, 98, 0, 4, 26, 8, 0, 0, 0, 8, 8, 8, 2, 0, 0, 0, 10, 10, 10, 42, 42, 0,
+    18, 52, 0, 10, 40, 0,
+];
+
+/// A row change made by a transaction, logged as a WRITE_, UPDATE_ or DELETE_ROWS_EVENT.
+#[derive(Debug)]
+pub enum RowChange {
+    Write(Row),
+    Update(Row, Row), // before, after
+    Delete(Row),
+}
+
+impl RowChange {
+    fn event_type(&self) -> u8 {
+        match self {
+            RowChange::Write(_) => WRITE_ROWS_EVENT,
+            RowChange::Update(..) => UPDATE_ROWS_EVENT,
+            RowChange::Delete(_) => DELETE_ROWS_EVENT,
+        }
+    }
+}
+
+// an event as written to the file, header and checksum included
+struct Event {
+    position: u64,
+    transaction: Option<u64>, // number of the GTID of the transaction it belongs to
+    bytes: Vec<u8>,
+}
+
+struct Binlog {
+    created: u32,
+    events: Vec<Event>,
+    end: u64, // size of the file
+    tables: Vec<String>, // `schema.table` of each table id, from 1
+}
+
+impl Binlog {
+    // the magic number, a FORMAT_DESCRIPTION_EVENT and no earlier GTIDs
+    fn new() -> Self {
+        let created = timestamp();
+        let mut binlog = Binlog { created, events: Vec::new(), end: 4, tables: Vec::new() };
+        binlog.append(None, FORMAT_DESCRIPTION_EVENT, 0, &format_description(created));
+        binlog.append(None, PREVIOUS_GTIDS_LOG_EVENT, 0, &0u64.to_le_bytes());
+        binlog
+    }
+
+    fn append(&mut self, transaction: Option<u64>, event_type: u8, flags: u16, body: &[u8]) {
+        let position = self.end;
+        let size = (EVENT_HEADER_LEN + body.len() + CHECKSUM_LEN) as u64;
+        let bytes = event(timestamp(), event_type, position + size, flags, body);
+
+        self.end += size;
+        self.events.push(Event { position, transaction, bytes });
+    }
+
+    fn table_id(&mut self, name: String) -> u64 {
+        let id = match self.tables.iter().position(|t| *t == name) {
+            Some(i) => i,
+            None => {
+                self.tables.push(name);
+                self.tables.len() - 1
+            }
+        };
+        id as u64 + 1
+    }
+}
+
+static BINLOG: LazyLock<(Mutex<Binlog>, Condvar)> = LazyLock::new(|| (Mutex::new(Binlog::new()), Condvar::new()));
+
+fn timestamp() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as u32)
+}
+
+// common header, body and CRC32 of the two
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication_binlog_event.html#sect_protocol_replication_binlog_event_header
+fn event(timestamp: u32, event_type: u8, log_pos: u64, flags: u16, body: &[u8]) -> Vec<u8> {
+    let size = EVENT_HEADER_LEN + body.len() + CHECKSUM_LEN;
+
+    let mut event = Vec::with_capacity(size);
+    event.extend(timestamp.to_le_bytes());
+    event.push(event_type);
+    event.extend(SERVER_ID.to_le_bytes());
+    event.extend((size as u32).to_le_bytes());
+    event.extend((log_pos as u32).to_le_bytes()); // where the next event starts
+    event.extend(flags.to_le_bytes());
+    event.extend(body);
+
+    let mut crc = Crc::new();
+    crc.update(&event);
+    event.extend(crc.sum().to_le_bytes());
+    event
+}
+
+fn format_description(created: u32) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(4u16.to_le_bytes()); // binlog version
+
+    let mut server_version = [0u8; 50];
+    server_version[..5].copy_from_slice(b"9.4.0");
+    body.extend(server_version);
+
+    body.extend(created.to_le_bytes());
+    body.push(EVENT_HEADER_LEN as u8);
+    body.extend(POST_HEADER_LENGTHS);
+    body.push(BINLOG_CHECKSUM_ALG_CRC32);
+    body
+}
+
+/// Logs a transaction that changed rows of `table`, and returns its GTID.
+///
+/// Called with the table locked, so transactions are logged in the order they're applied.
+pub fn commit(schema: &str, table: &Table, changes: &[RowChange]) -> String {
+    let (lock, appended) = &*BINLOG;
+    let mut binlog = lock.lock().unwrap();
+
+    let transaction = gtid::commit();
+    let table_id = binlog.table_id(format!("{schema}.{}", table.name));
+    let log = Some(transaction);
+
+    // GTID, with the logical clock of a transaction that depends on the previous one
+    let mut body = vec![0x01]; // commit flag
+    body.extend(gtid::server_uuid_bytes());
+    body.extend(transaction.to_le_bytes());
+    body.push(2); // logical timestamp type code
+    body.extend((transaction - 1).to_le_bytes()); // last committed
+    body.extend(transaction.to_le_bytes()); // sequence number
+    binlog.append(log, GTID_LOG_EVENT, 0, &body);
+
+    binlog.append(log, QUERY_EVENT, 0, &query(schema, "BEGIN"));
+    binlog.append(log, TABLE_MAP_EVENT, 0, &table_map(table_id, schema, table));
+
+    // consecutive changes of the same kind share an event
+    let groups = changes.chunk_by(|a, b| a.event_type() == b.event_type()).collect::<Vec<_>>();
+    for (i, group) in groups.iter().enumerate() {
+        let flags = if i + 1 == groups.len() { STMT_END_F } else { 0 };
+        binlog.append(log, group[0].event_type(), 0, &rows(table_id, flags, &table.columns, group));
+    }
+
+    binlog.append(log, XID_EVENT, 0, &transaction.to_le_bytes());
+    appended.notify_all();
+
+    gtid::format(transaction)
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Query__event.html
+fn query(schema: &str, query: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(0u32.to_le_bytes()); // thread id
+    body.extend(0u32.to_le_bytes()); // execution time
+    body.push(schema.len() as u8);
+    body.extend(0u16.to_le_bytes()); // error code
+    body.extend(0u16.to_le_bytes()); // status variables length
+    body.extend(schema.as_bytes());
+    body.push(0);
+    body.extend(query.as_bytes());
+    body
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Table__map__event.html
+fn table_map(table_id: u64, schema: &str, table: &Table) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(&table_id.to_le_bytes()[..6]);
+    body.extend(1u16.to_le_bytes()); // flags
+
+    body.push(schema.len() as u8);
+    body.extend(schema.as_bytes());
+    body.push(0);
+    body.push(table.name.len() as u8);
+    body.extend(table.name.as_bytes());
+    body.push(0);
+
+    body.push_lenenc_int(table.columns.len() as u64);
+    let mut metadata = Vec::new();
+    for column in &table.columns {
+        let (binlog_type, column_metadata) = binlog_type(column);
+        body.push(binlog_type);
+        metadata.extend(column_metadata);
+    }
+    body.push_lenenc_str(&metadata);
+
+    let nullable = table.columns.iter().map(|c| c.flags & NOT_NULL_FLAG == 0).collect::<Vec<_>>();
+    body.extend(bitmap(&nullable));
+    body
+}
+
+// https://dev.mysql.com/doc/dev/mysql-server/latest/classbinary__log_1_1Rows__event.html
+fn rows(table_id: u64, flags: u16, columns: &[ColumnDefinition41], changes: &[RowChange]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(&table_id.to_le_bytes()[..6]);
+    body.extend(flags.to_le_bytes());
+    body.extend(2u16.to_le_bytes()); // extra data length, only the length itself
+
+    // every column is in the before and after images
+    body.push_lenenc_int(columns.len() as u64);
+    let present = bitmap(&vec![true; columns.len()]);
+    body.extend(&present);
+    if matches!(changes[0], RowChange::Update(..)) {
+        body.extend(&present);
+    }
+
+    for change in changes {
+        match change {
+            RowChange::Write(row) | RowChange::Delete(row) => row_image(&mut body, columns, row),
+            RowChange::Update(before, after) => {
+                row_image(&mut body, columns, before);
+                row_image(&mut body, columns, after);
+            }
+        }
+    }
+    body
+}
+
+fn row_image(body: &mut Vec<u8>, columns: &[ColumnDefinition41], row: &Row) {
+    let nulls = row.0.iter().map(|v| *v == Value::Null).collect::<Vec<_>>();
+    body.extend(bitmap(&nulls));
+    for (column, value) in columns.iter().zip(&row.0) {
+        if *value != Value::Null {
+            binlog_value(body, column, value);
+        }
+    }
+}
+
+fn bitmap(bits: &[bool]) -> Vec<u8> {
+    let mut bitmap = vec![0u8; bits.len().div_ceil(8)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    bitmap
+}
+
+// fractional seconds of a temporal column, its decimals
+fn fsp(column: &ColumnDefinition41) -> u8 {
+    if column.decimals <= 6 { column.decimals } else { 0 }
+}
+
+// DECIMAL(M,D) has a display length of M, plus the point and the sign
+fn precision(column: &ColumnDefinition41) -> u8 {
+    let point = (column.decimals > 0) as u32;
+    let sign = (column.flags & UNSIGNED_FLAG == 0) as u32;
+    column.column_length.saturating_sub(point + sign) as u8
+}
+
+// bytes taken by the length of a string of up to `max` bytes
+fn length_size(max: u32) -> usize {
+    match max {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xff_ffff => 3,
+        _ => 4,
+    }
+}
+
+// the type a column has in the binary log, with its metadata in TABLE_MAP_EVENT
+fn binlog_type(column: &ColumnDefinition41) -> (u8, Vec<u8>) {
+    match column.column_type {
+        ColumnType::Tiny | ColumnType::Short | ColumnType::Int24 | ColumnType::Long | ColumnType::LongLong | ColumnType::Year => {
+            (column.column_type as u8, Vec::new())
+        }
+        ColumnType::Float => (ColumnType::Float as u8, vec![4]),
+        ColumnType::Double => (ColumnType::Double as u8, vec![8]),
+        ColumnType::Decimal | ColumnType::NewDecimal => (ColumnType::NewDecimal as u8, vec![precision(column), column.decimals]),
+        ColumnType::Date => (ColumnType::Date as u8, Vec::new()),
+        ColumnType::Timestamp => (0x11, vec![fsp(column)]), // TIMESTAMP2
+        ColumnType::DateTime => (0x12, vec![fsp(column)]), // DATETIME2
+        ColumnType::Time => (0x13, vec![fsp(column)]), // TIME2
+        ColumnType::VarChar | ColumnType::VarString => {
+            (ColumnType::VarChar as u8, (column.column_length.min(0xffff) as u16).to_le_bytes().to_vec())
+        }
+        // the real type and the length, with the length's bits past the first 8 folded into the type
+        ColumnType::String => {
+            let length = column.column_length.min(0x3ff);
+            let real_type = ColumnType::String as u8 ^ ((length & 0x300) >> 4) as u8;
+            (ColumnType::String as u8, vec![real_type, length as u8])
+        }
+        // blobs and anything else as a blob, with the size of its length
+        _ => (ColumnType::Blob as u8, vec![length_size(column.column_length) as u8]),
+    }
+}
+
+fn binlog_value(body: &mut Vec<u8>, column: &ColumnDefinition41, value: &Value) {
+    let text = value.as_text().unwrap_or_default();
+    let number = || match value {
+        Value::Int(v) => *v,
+        Value::UInt(v) => *v as i64,
+        _ => String::from_utf8_lossy(&text).trim().parse().unwrap_or_default(),
+    };
+    let real = || match value {
+        Value::Float(v) => *v as f64,
+        Value::Double(v) => *v,
+        _ => String::from_utf8_lossy(&text).trim().parse().unwrap_or_default(),
+    };
+
+    match column.column_type {
+        ColumnType::Tiny => body.extend(&number().to_le_bytes()[..1]),
+        ColumnType::Short => body.extend(&number().to_le_bytes()[..2]),
+        ColumnType::Int24 => body.extend(&number().to_le_bytes()[..3]),
+        ColumnType::Long => body.extend(&number().to_le_bytes()[..4]),
+        ColumnType::LongLong => body.extend(number().to_le_bytes()),
+        ColumnType::Year => body.push(number().checked_sub(1900).map_or(0, |year| year as u8)),
+        ColumnType::Float => body.extend((real() as f32).to_le_bytes()),
+        ColumnType::Double => body.extend(real().to_le_bytes()),
+        ColumnType::Decimal | ColumnType::NewDecimal => {
+            body.extend(decimal(&String::from_utf8_lossy(&text), precision(column) as usize, column.decimals as usize))
+        }
+        ColumnType::Date => {
+            let date = datetime(value);
+            let packed = date.day as u32 | (date.month as u32) << 5 | (date.year as u32) << 9;
+            body.extend(&packed.to_le_bytes()[..3]);
+        }
+        ColumnType::DateTime => {
+            let v = datetime(value);
+            let ymd = ((v.year as u64 * 13 + v.month as u64) << 5) | v.day as u64;
+            let hms = (v.hour as u64) << 12 | (v.minute as u64) << 6 | v.second as u64;
+            let packed = 0x80_0000_0000 + (ymd << 17 | hms);
+            body.extend(&packed.to_be_bytes()[3..]);
+            body.extend(fraction(v.microsecond, fsp(column)));
+        }
+        ColumnType::Timestamp => {
+            let v = datetime(value);
+            let seconds = days_from_civil(v.year as i64, v.month as i64, v.day as i64) * 86400
+                + v.hour as i64 * 3600
+                + v.minute as i64 * 60
+                + v.second as i64;
+            body.extend((seconds.max(0) as u32).to_be_bytes());
+            body.extend(fraction(v.microsecond, fsp(column)));
+        }
+        ColumnType::Time => {
+            let v = match value {
+                Value::Time(time) => *time,
+                _ => crate::binary::parse_time(&text).unwrap_or_default(),
+            };
+            let hours = v.days as i64 * 24 + v.hours as i64;
+            let hms = hours << 12 | (v.minutes as i64) << 6 | v.seconds as i64;
+            let packed = 0x80_0000 + if v.negative { -hms } else { hms };
+            body.extend(&packed.to_be_bytes()[5..]);
+            body.extend(fraction(v.microseconds, fsp(column)));
+        }
+        ColumnType::VarChar | ColumnType::VarString | ColumnType::String => {
+            let size = if column.column_length < 0x100 { 1 } else { 2 };
+            body.extend(&(text.len() as u32).to_le_bytes()[..size]);
+            body.extend(&text);
+        }
+        _ => {
+            let size = length_size(column.column_length);
+            body.extend(&(text.len() as u32).to_le_bytes()[..size]);
+            body.extend(&text);
+        }
+    }
+}
+
+fn datetime(value: &Value) -> DateTime {
+    match value {
+        Value::Date(v) | Value::DateTime(v) => *v,
+        value => crate::binary::parse_datetime(&value.as_text().unwrap_or_default()).unwrap_or_default(),
+    }
+}
+
+// the first `fsp` digits of the microseconds, big-endian in 1 byte per 2 digits
+fn fraction(microseconds: u32, fsp: u8) -> Vec<u8> {
+    let size = (fsp as usize).div_ceil(2);
+    let digits = microseconds / 10u32.pow(6 - size as u32 * 2);
+    digits.to_be_bytes()[4 - size..].to_vec()
+}
+
+// days since 1970-01-01
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// binary DECIMAL, groups of 9 digits in 4 bytes each, the sign in the high bit
+// https://dev.mysql.com/doc/refman/9.4/en/precision-math-decimal-characteristics.html
+fn decimal(text: &str, precision: usize, scale: usize) -> Vec<u8> {
+    const DIGITS_TO_BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+    let text = text.trim();
+    let negative = text.starts_with('-');
+    let (integer, fraction) = text.trim_start_matches(['-', '+']).split_once('.').unwrap_or((text.trim_start_matches(['-', '+']), ""));
+    let integer_digits = precision.saturating_sub(scale);
+    let integer = format!("{integer:0>integer_digits$}");
+    let integer = &integer[integer.len() - integer_digits..];
+    let fraction = format!("{fraction:0<scale$}");
+    let fraction = &fraction[..scale];
+
+    let mut bytes = Vec::<u8>::new();
+    let mut push = |digits: &[u8]| {
+        let group = std::str::from_utf8(digits).ok().and_then(|d| d.parse::<u32>().ok()).unwrap_or_default();
+        bytes.extend(&group.to_be_bytes()[4 - DIGITS_TO_BYTES[digits.len()]..]);
+    };
+
+    // the leftover integer digits come first, the leftover fraction digits last
+    let leading = integer.len() % 9;
+    if leading > 0 {
+        push(&integer.as_bytes()[..leading]);
+    }
+    integer.as_bytes()[leading..].chunks(9).for_each(&mut push);
+    fraction.as_bytes().chunks(9).for_each(&mut push);
+
+    if negative {
+        bytes.iter_mut().for_each(|b| *b = !*b);
+    }
+    if let Some(first) = bytes.first_mut() {
+        *first ^= 0x80;
+    }
+    bytes
+}
+
+/// A replica's COM_BINLOG_DUMP or COM_BINLOG_DUMP_GTID.
+#[derive(Debug)]
+pub struct Dump {
+    file: String, // empty for the first file
+    position: u64, // of the next event to send
+    flags: u16,
+    executed: Vec<(u64, u64)>, // transactions of this server the replica has, as [start, end) ranges
+}
+
+impl Dump {
+    // only the intervals of this server's UUID matter, the replica has none of the others' transactions
+    pub fn new(dump: BinlogDump) -> Dump {
+        let executed = dump.gtid_set.unwrap_or_default().into_iter()
+            .filter(|(uuid, _)| *uuid == gtid::server_uuid_bytes())
+            .flat_map(|(_, intervals)| intervals)
+            .collect();
+
+        Dump { file: dump.file, position: dump.position, flags: dump.flags, executed }
+    }
+
+    // the replica gets an EOF at the end of the log rather than waiting for more
+    pub fn non_block(&self) -> bool {
+        self.flags & BINLOG_DUMP_NON_BLOCK != 0
+    }
+
+    fn executed(&self, transaction: Option<u64>) -> bool {
+        transaction.is_some_and(|t| self.executed.iter().any(|&(start, end)| (start..end).contains(&t)))
+    }
+
+    // the fake ROTATE_EVENT with the file and position the dump starts from, and the
+    // FORMAT_DESCRIPTION_EVENT when the position is past it
+    pub fn start(&mut self) -> Result<Vec<Vec<u8>>, ServerError> {
+        if !self.file.is_empty() && self.file != FILE_NAME {
+            return Err(ServerError::binlog_read_error("Could not find first log file name in binary log index file"));
+        }
+        self.position = self.position.max(4);
+
+        let binlog = BINLOG.0.lock().unwrap();
+        if self.position > binlog.end {
+            return Err(ServerError::binlog_read_error(
+                "Client requested source to start replication from position > file size",
+            ));
+        }
+        if self.position < binlog.end && !binlog.events.iter().any(|e| e.position == self.position) {
+            return Err(ServerError::binlog_read_error("Client requested source to start replication from impossible position"));
+        }
+
+        let mut rotate = self.position.to_le_bytes().to_vec();
+        rotate.extend(FILE_NAME.as_bytes());
+        let mut events = vec![event(0, ROTATE_EVENT, 0, LOG_EVENT_ARTIFICIAL_F, &rotate)];
+        if self.position > 4 {
+            // with a zero position, so the replica doesn't take it for where it's at
+            events.push(event(binlog.created, FORMAT_DESCRIPTION_EVENT, 0, 0, &format_description(binlog.created)));
+        }
+        Ok(events)
+    }
+
+    // the events logged since the last call, waiting up to `timeout` when there are none yet
+    pub fn next(&mut self, timeout: Duration) -> Vec<Vec<u8>> {
+        let (lock, appended) = &*BINLOG;
+        let mut binlog = lock.lock().unwrap();
+        if binlog.end <= self.position && !timeout.is_zero() {
+            binlog = appended.wait_timeout(binlog, timeout).unwrap().0;
+        }
+
+        let events = binlog.events.iter()
+            .filter(|e| e.position >= self.position && !self.executed(e.transaction))
+            .map(|e| e.bytes.clone())
+            .collect();
+        self.position = binlog.end;
+        events
+    }
+}
+
+// SHOW BINARY LOG STATUS, the position of the next event and the transactions logged so far
+pub fn status() -> ResultSet {
+    let text = |name, len| ColumnDefinition41::new(name, ColumnType::VarString, len).with_charset(UTF8MB4_GENERAL_CI);
+    let columns = vec![
+        text("File", 1020),
+        ColumnDefinition41::new("Position", ColumnType::LongLong, 20).with_flags(NOT_NULL_FLAG | UNSIGNED_FLAG),
+        text("Binlog_Do_DB", 1020),
+        text("Binlog_Ignore_DB", 1020),
+        text("Executed_Gtid_Set", 4096),
+    ];
+
+    let end = BINLOG.0.lock().unwrap().end;
+    let rows = vec![Row(vec![FILE_NAME.into(), Value::UInt(end), "".into(), "".into(), gtid::executed().as_str().into()])];
+
+    ResultSet { columns, rows }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CRC-32 as a replica checks it, bit by bit rather than through flate2
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in data {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    fn column(column_type: ColumnType, column_length: u32, decimals: u8) -> ColumnDefinition41 {
+        ColumnDefinition41 { decimals, ..ColumnDefinition41::new("c", column_type, column_length) }
+    }
+
+    #[test]
+    fn format_description_event() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let body = format_description(1_700_000_000);
+        let bytes = event(1_700_000_000, FORMAT_DESCRIPTION_EVENT, 4 + 19 + 99 + 4, 0, &body);
+        assert_eq!(bytes.len(), 19 + 99 + 4);
+        assert_eq!(bytes[..19], [0x00, 0xf1, 0x53, 0x65, 0x0f, 0x01, 0x00, 0x00, 0x00, 0x7a, 0x00, 0x00, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        // its post-header length leaves out the checksum algorithm
+        assert_eq!(POST_HEADER_LENGTHS[FORMAT_DESCRIPTION_EVENT as usize - 1] as usize, body.len() - 1);
+        assert_eq!(body[body.len() - 1], BINLOG_CHECKSUM_ALG_CRC32);
+
+        let (event, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        assert_eq!(checksum, crc32(event).to_le_bytes());
+    }
+
+    // the example of strings/decimal.cc, DECIMAL(14,4)
+    #[test]
+    fn decimal_groups() {
+        assert_eq!(decimal("1234567890.1234", 14, 4), [0x81, 0x0d, 0xfb, 0x38, 0xd2, 0x04, 0xd2]);
+        assert_eq!(decimal("-1234567890.1234", 14, 4), [0x7e, 0xf2, 0x04, 0xc7, 0x2d, 0xfb, 0x2d]);
+    }
+
+    #[test]
+    fn decimal_value() {
+        // DECIMAL(10,2), its display length counts the point and the sign
+        let column = column(ColumnType::NewDecimal, 12, 2);
+        assert_eq!(binlog_type(&column), (ColumnType::NewDecimal as u8, vec![10, 2]));
+
+        let mut body = Vec::new();
+        binlog_value(&mut body, &column, &Value::Bytes(b"12345678.90".to_vec()));
+        binlog_value(&mut body, &column, &Value::Bytes(b"-0.05".to_vec()));
+        assert_eq!(body, [0x80, 0xbc, 0x61, 0x4e, 0x5a, 0x7f, 0xff, 0xff, 0xff, 0xfa]);
+    }
+
+    #[test]
+    fn datetime_value() {
+        // DATETIME(3), 5 bytes and the milliseconds in 2
+        let column = column(ColumnType::DateTime, 23, 3);
+        assert_eq!(binlog_type(&column), (0x12, vec![3]));
+
+        let value = DateTime { year: 2024, month: 1, day: 2, hour: 3, minute: 4, second: 5, microsecond: 678_000 };
+        let mut body = Vec::new();
+        binlog_value(&mut body, &column, &Value::DateTime(value));
+        binlog_value(&mut body, &column, &Value::Bytes(b"2024-01-02 03:04:05.678".to_vec()));
+        assert_eq!(body, [[0x99, 0xb2, 0x44, 0x31, 0x05, 0x1a, 0x7c], [0x99, 0xb2, 0x44, 0x31, 0x05, 0x1a, 0x7c]].concat());
+    }
+}
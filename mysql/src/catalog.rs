@@ -0,0 +1,222 @@
+use std::sync::{Arc, LazyLock, RwLock};
+
+use mysql_protocol::column::{
+    BLOB_FLAG, ColumnDefinition41, ColumnType, NO_DEFAULT_VALUE_FLAG, NOT_NULL_FLAG, PART_KEY_FLAG, PRI_KEY_FLAG,
+    UNIQUE_KEY_FLAG, UTF8MB4_GENERAL_CI,
+};
+
+use crate::binlog::{self, RowChange};
+use crate::error::ServerError;
+use crate::process;
+use crate::session::Session;
+use crate::variables;
+use crate::resultset::{DateTime, Row, Value};
+
+pub const DATABASE: &str = "protocols";
+pub const INFORMATION_SCHEMA: &str = "information_schema";
+pub const PERFORMANCE_SCHEMA: &str = "performance_schema";
+
+/// In-memory copy of the tables from containers/mysql_init.sql.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: &'static str,
+    pub columns: Vec<ColumnDefinition41>,
+    pub rows: Vec<Row>,
+}
+
+// a lookup hands out the current version of a table, inserts replace it rather than change it under a reader
+static TABLES: LazyLock<RwLock<Vec<Arc<Table>>>> = LazyLock::new(|| RwLock::new(vec![Arc::new(products()), Arc::new(sample())]));
+
+/// Stored procedure from containers/mysql_init.sql, its body statements take the arguments as `?` placeholders.
+#[derive(Debug)]
+pub struct Procedure {
+    pub name: &'static str,
+    pub params: usize,
+    pub body: &'static [&'static str],
+}
+
+static PROCEDURES: [Procedure; 1] = [
+    Procedure {
+        name: "product_overview",
+        params: 1, // p_country
+        body: &[
+            "select id, title from products where country = ?",
+            "select id, price, quantity from products where country = ?",
+        ],
+    },
+];
+
+pub fn database_exists(name: &str) -> bool {
+    name.eq_ignore_ascii_case(DATABASE) || is_system_schema(name)
+}
+
+// the schemas with views of the server state, nothing can be written to them
+pub fn is_system_schema(name: &str) -> bool {
+    name.eq_ignore_ascii_case(INFORMATION_SCHEMA) || name.eq_ignore_ascii_case(PERFORMANCE_SCHEMA)
+}
+
+pub fn table_count() -> usize {
+    TABLES.read().unwrap().len()
+}
+
+// information_schema and performance_schema tables are built on every lookup, they change with the
+// server and the session state
+pub fn table(schema: &str, name: &str, session: &Session) -> Option<Arc<Table>> {
+    if schema.eq_ignore_ascii_case(INFORMATION_SCHEMA) {
+        return name.eq_ignore_ascii_case("PROCESSLIST").then(|| Arc::new(processlist()));
+    }
+    if schema.eq_ignore_ascii_case(PERFORMANCE_SCHEMA) {
+        return variables::table(&session.variables, name).map(Arc::new);
+    }
+    if !schema.eq_ignore_ascii_case(DATABASE) {
+        return None;
+    }
+    TABLES.read().unwrap().iter().find(|t| t.name.eq_ignore_ascii_case(name)).cloned()
+}
+
+/// Rows written by an insert, and the ones left out or removed for a duplicate key.
+#[derive(Debug, Default)]
+pub struct InsertCount {
+    pub inserted: u64,
+    pub deleted: u64,
+    pub skipped: u64,
+    pub gtid: Option<String>, // of the transaction, `None` when no row changed
+}
+
+// appends rows to a table of the protocols schema, a row with the value of a primary or unique
+// key column that's already taken replaces the old row with `replace` and is skipped otherwise
+pub fn insert(schema: &str, name: &str, rows: Vec<Row>, replace: bool) -> Result<InsertCount, ServerError> {
+    if is_system_schema(schema) {
+        return Err(ServerError::read_only_table(name));
+    }
+    let mut tables = TABLES.write().unwrap();
+    let table = tables.iter_mut()
+        .find(|t| schema.eq_ignore_ascii_case(DATABASE) && t.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ServerError::no_such_table(schema, name))?;
+    let table = Arc::make_mut(table);
+
+    let keys = table.columns.iter()
+        .enumerate()
+        .filter(|(_, c)| c.flags & (PRI_KEY_FLAG | UNIQUE_KEY_FLAG) != 0)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut count = InsertCount::default();
+    let mut changes = Vec::new();
+    for row in rows {
+        let duplicate = |old: &Row| keys.iter().any(|&k| old.0[k].sql_eq(&row.0[k]));
+        if table.rows.iter().any(duplicate) {
+            if !replace {
+                count.skipped += 1;
+                continue;
+            }
+            let (mut replaced, kept) = std::mem::take(&mut table.rows).into_iter().partition::<Vec<_>, _>(duplicate);
+            table.rows = kept;
+            count.deleted += replaced.len() as u64;
+
+            // a row that takes the place of a single one is logged as its update
+            if replaced.len() == 1 {
+                changes.push(RowChange::Update(replaced.remove(0), row.clone()));
+            } else {
+                changes.extend(replaced.into_iter().map(RowChange::Delete));
+                changes.push(RowChange::Write(row.clone()));
+            }
+        } else {
+            changes.push(RowChange::Write(row.clone()));
+        }
+        table.rows.push(row);
+        count.inserted += 1;
+    }
+
+    if !changes.is_empty() {
+        count.gtid = Some(binlog::commit(DATABASE, table, &changes));
+    }
+    Ok(count)
+}
+
+pub fn procedure(schema: &str, name: &str) -> Option<&'static Procedure> {
+    if !schema.eq_ignore_ascii_case(DATABASE) {
+        return None;
+    }
+    PROCEDURES.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+fn products() -> Table {
+    let column = |name, column_type, column_length| {
+        ColumnDefinition41::table_column(DATABASE, "products", name, column_type, column_length)
+    };
+
+    Table {
+        name: "products",
+        columns: vec![
+            column("id", ColumnType::Long, 4)
+                .with_flags(NOT_NULL_FLAG | PRI_KEY_FLAG | NO_DEFAULT_VALUE_FLAG | PART_KEY_FLAG),
+            column("country", ColumnType::String, 8)
+                .with_charset(UTF8MB4_GENERAL_CI)
+                .with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+            column("title", ColumnType::VarString, 400)
+                .with_charset(UTF8MB4_GENERAL_CI)
+                .with_flags(NOT_NULL_FLAG | UNIQUE_KEY_FLAG | NO_DEFAULT_VALUE_FLAG | PART_KEY_FLAG),
+            column("description", ColumnType::Blob, 0xffff_ffff)
+                .with_charset(UTF8MB4_GENERAL_CI)
+                .with_flags(BLOB_FLAG),
+            column("category_id", ColumnType::Short, 6),
+            column("price", ColumnType::NewDecimal, 12)
+                .with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG)
+                .with_decimals(2),
+            column("quantity", ColumnType::LongLong, 20)
+                .with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+            column("create_dt", ColumnType::DateTime, 19)
+                .with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+        ],
+        rows: vec![
+            Row(vec![
+                1.into(), "UK".into(), "laptop".into(), Value::Null, 2.into(),
+                "123.40".into(), 3.into(), datetime(2025, 1, 1),
+            ]),
+            Row(vec![
+                2.into(), "CY".into(), "phone".into(), "Just a phone desc".into(), 20000.into(),
+                "7.89".into(), 30000.into(), datetime(2025, 6, 1),
+            ]),
+        ],
+    }
+}
+
+fn datetime(year: u16, month: u8, day: u8) -> Value {
+    Value::DateTime(DateTime { year, month, day, ..DateTime::default() })
+}
+
+// SHOW PROCESSLIST with upper case column names
+fn processlist() -> Table {
+    let result_set = process::result_set();
+    let columns = result_set.columns.into_iter()
+        .map(|c| {
+            ColumnDefinition41::table_column(INFORMATION_SCHEMA, "PROCESSLIST", &c.name.to_uppercase(), c.column_type, c.column_length)
+                .with_charset(c.character_set)
+                .with_flags(c.flags)
+        })
+        .collect();
+
+    Table { name: "PROCESSLIST", columns, rows: result_set.rows }
+}
+
+// a single 10 MB row
+fn sample() -> Table {
+    let column = |name, column_type, column_length| {
+        ColumnDefinition41::table_column(DATABASE, "sample", name, column_type, column_length)
+    };
+
+    Table {
+        name: "sample",
+        columns: vec![
+            column("t", ColumnType::Tiny, 4).with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+            column("i", ColumnType::Long, 11).with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+            column("s", ColumnType::Blob, 0xffff_ffff)
+                .with_charset(UTF8MB4_GENERAL_CI)
+                .with_flags(NOT_NULL_FLAG | BLOB_FLAG | NO_DEFAULT_VALUE_FLAG),
+        ],
+        rows: vec![
+            Row(vec![1.into(), 2147483647.into(), Value::Bytes(vec![b'A'; 10_000_000])]),
+        ],
+    }
+}
@@ -0,0 +1,368 @@
+use mysql_protocol::ProtocolError;
+use mysql_protocol::packet::ErrorPacket;
+
+// https://dev.mysql.com/doc/mysql-errors/9.4/en/server-error-reference.html
+pub const ER_OPEN_AS_READONLY: u16 = 1036;
+pub const ER_NO_DB_ERROR: u16 = 1046;
+pub const ER_UNKNOWN_COM_ERROR: u16 = 1047;
+pub const ER_BAD_DB_ERROR: u16 = 1049;
+pub const ER_BAD_FIELD_ERROR: u16 = 1054;
+pub const ER_PARSE_ERROR: u16 = 1064;
+pub const ER_WRONG_FIELD_TERMINATORS: u16 = 1083;
+pub const ER_NO_SUCH_THREAD: u16 = 1094;
+pub const ER_NO_TABLES_USED: u16 = 1096;
+pub const ER_UNKNOWN_CHARACTER_SET: u16 = 1115;
+pub const ER_NO_SUCH_TABLE: u16 = 1146;
+pub const ER_NET_PACKET_TOO_LARGE: u16 = 1153;
+pub const ER_NET_PACKETS_OUT_OF_ORDER: u16 = 1156;
+pub const ER_UNKNOWN_SYSTEM_VARIABLE: u16 = 1193;
+pub const ER_WRONG_ARGUMENTS: u16 = 1210;
+pub const ER_GLOBAL_VARIABLE: u16 = 1229;
+pub const ER_WRONG_VALUE_FOR_VAR: u16 = 1231;
+pub const ER_WRONG_TYPE_FOR_VAR: u16 = 1232;
+pub const ER_SOURCE_FATAL_ERROR_READING_BINLOG: u16 = 1236;
+pub const ER_INCORRECT_GLOBAL_LOCAL_VAR: u16 = 1238;
+pub const ER_UNKNOWN_STMT_HANDLER: u16 = 1243;
+pub const ER_COLLATION_CHARSET_MISMATCH: u16 = 1253;
+pub const ER_UNKNOWN_COLLATION: u16 = 1273;
+pub const ER_OPTION_PREVENTS_STATEMENT: u16 = 1290;
+pub const ER_UNSUPPORTED_PS: u16 = 1295;
+pub const ER_UNKNOWN_TIME_ZONE: u16 = 1298;
+pub const ER_SP_DOES_NOT_EXIST: u16 = 1305;
+pub const ER_SP_BADSELECT: u16 = 1312;
+pub const ER_SP_WRONG_NO_OF_ARGS: u16 = 1318;
+pub const ER_STMT_HAS_NO_OPEN_CURSOR: u16 = 1421;
+pub const ER_WRONG_PARAMCOUNT_TO_NATIVE_FCT: u16 = 1582;
+pub const ER_VARIABLE_IS_READONLY: u16 = 1621;
+pub const ER_MALFORMED_PACKET: u16 = 1835;
+pub const ER_CLIENT_LOCAL_FILES_DISABLED: u16 = 3948;
+
+/// Error sent to the client in an ERR packet, the connection stays usable unless noted otherwise.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_err_packet.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    pub code: u16,
+    pub sql_state: &'static str,
+    pub message: String,
+}
+
+impl ServerError {
+    pub fn unknown_command() -> Self {
+        ServerError {
+            code: ER_UNKNOWN_COM_ERROR,
+            sql_state: "08S01",
+            message: "Unknown command".to_string(),
+        }
+    }
+
+    pub fn no_database_selected() -> Self {
+        ServerError {
+            code: ER_NO_DB_ERROR,
+            sql_state: "3D000",
+            message: "No database selected".to_string(),
+        }
+    }
+
+    pub fn bad_database(db: &str) -> Self {
+        ServerError {
+            code: ER_BAD_DB_ERROR,
+            sql_state: "42000",
+            message: format!("Unknown database '{db}'"),
+        }
+    }
+
+    pub fn parse_error(near: &str) -> Self {
+        ServerError {
+            code: ER_PARSE_ERROR,
+            sql_state: "42000",
+            message: format!(
+                "You have an error in your SQL syntax; check the manual that corresponds to your MySQL server version for the right syntax to use near '{}' at line 1",
+                near.chars().take(80).collect::<String>()
+            ),
+        }
+    }
+
+    pub fn bad_field(column: &str) -> Self {
+        ServerError {
+            code: ER_BAD_FIELD_ERROR,
+            sql_state: "42S22",
+            message: format!("Unknown column '{column}' in 'field list'"),
+        }
+    }
+
+    pub fn unknown_charset(charset: &str) -> Self {
+        ServerError {
+            code: ER_UNKNOWN_CHARACTER_SET,
+            sql_state: "42000",
+            message: format!("Unknown character set: '{charset}'"),
+        }
+    }
+
+    pub fn no_such_table(db: &str, table: &str) -> Self {
+        ServerError {
+            code: ER_NO_SUCH_TABLE,
+            sql_state: "42S02",
+            message: format!("Table '{db}.{table}' doesn't exist"),
+        }
+    }
+
+    pub fn no_such_thread(id: u32) -> Self {
+        ServerError {
+            code: ER_NO_SUCH_THREAD,
+            sql_state: "HY000",
+            message: format!("Unknown thread id: {id}"),
+        }
+    }
+
+    pub fn read_only_table(table: &str) -> Self {
+        ServerError {
+            code: ER_OPEN_AS_READONLY,
+            sql_state: "HY000",
+            message: format!("Table '{table}' is read only"),
+        }
+    }
+
+    // ENCLOSED BY and ESCAPED BY of LOAD DATA take a single character
+    pub fn wrong_field_terminators() -> Self {
+        ServerError {
+            code: ER_WRONG_FIELD_TERMINATORS,
+            sql_state: "42000",
+            message: "Field separator argument is not what is expected; check the manual".to_string(),
+        }
+    }
+
+    // LOAD DATA without LOCAL, the server has no files to read
+    pub fn secure_file_priv() -> Self {
+        ServerError {
+            code: ER_OPTION_PREVENTS_STATEMENT,
+            sql_state: "HY000",
+            message: "The MySQL server is running with the --secure-file-priv option so it cannot execute this statement".to_string(),
+        }
+    }
+
+    pub fn unsupported_ps() -> Self {
+        ServerError {
+            code: ER_UNSUPPORTED_PS,
+            sql_state: "HY000",
+            message: "This command is not supported in the prepared statement protocol yet".to_string(),
+        }
+    }
+
+    // LOAD DATA LOCAL from a client without CLIENT_LOCAL_FILES
+    pub fn local_files_disabled() -> Self {
+        ServerError {
+            code: ER_CLIENT_LOCAL_FILES_DISABLED,
+            sql_state: "42000",
+            message: "Loading local data is disabled; this must be enabled on both the client and server sides".to_string(),
+        }
+    }
+
+    pub fn no_tables_used() -> Self {
+        ServerError {
+            code: ER_NO_TABLES_USED,
+            sql_state: "HY000",
+            message: "No tables used".to_string(),
+        }
+    }
+
+    // the connection is closed after this one
+    pub fn packet_too_large() -> Self {
+        ServerError {
+            code: ER_NET_PACKET_TOO_LARGE,
+            sql_state: "08S01",
+            message: "Got a packet bigger than 'max_allowed_packet' bytes".to_string(),
+        }
+    }
+
+    // the connection is closed after this one
+    pub fn packets_out_of_order() -> Self {
+        ServerError {
+            code: ER_NET_PACKETS_OUT_OF_ORDER,
+            sql_state: "08S01",
+            message: "Got packets out of order".to_string(),
+        }
+    }
+
+    pub fn unknown_system_variable(name: &str) -> Self {
+        ServerError {
+            code: ER_UNKNOWN_SYSTEM_VARIABLE,
+            sql_state: "HY000",
+            message: format!("Unknown system variable '{name}'"),
+        }
+    }
+
+    pub fn wrong_arguments(command: &str) -> Self {
+        ServerError {
+            code: ER_WRONG_ARGUMENTS,
+            sql_state: "HY000",
+            message: format!("Incorrect arguments to {command}"),
+        }
+    }
+
+    pub fn global_variable(name: &str) -> Self {
+        ServerError {
+            code: ER_GLOBAL_VARIABLE,
+            sql_state: "HY000",
+            message: format!("Variable '{name}' is a GLOBAL variable and should be set with SET GLOBAL"),
+        }
+    }
+
+    pub fn wrong_value_for_variable(name: &str, value: &str) -> Self {
+        ServerError {
+            code: ER_WRONG_VALUE_FOR_VAR,
+            sql_state: "42000",
+            message: format!("Variable '{name}' can't be set to the value of '{value}'"),
+        }
+    }
+
+    pub fn wrong_type_for_variable(name: &str) -> Self {
+        ServerError {
+            code: ER_WRONG_TYPE_FOR_VAR,
+            sql_state: "42000",
+            message: format!("Incorrect argument type to variable '{name}'"),
+        }
+    }
+
+    // reading the session value of a global variable
+    pub fn incorrect_scope(name: &str, scope: &str) -> Self {
+        ServerError {
+            code: ER_INCORRECT_GLOBAL_LOCAL_VAR,
+            sql_state: "HY000",
+            message: format!("Variable '{name}' is a {scope} variable"),
+        }
+    }
+
+    pub fn read_only_variable(name: &str) -> Self {
+        ServerError {
+            code: ER_INCORRECT_GLOBAL_LOCAL_VAR,
+            sql_state: "HY000",
+            message: format!("Variable '{name}' is a read only variable"),
+        }
+    }
+
+    pub fn collation_charset_mismatch(collation: &str, charset: &str) -> Self {
+        ServerError {
+            code: ER_COLLATION_CHARSET_MISMATCH,
+            sql_state: "42000",
+            message: format!("COLLATION '{collation}' is not valid for CHARACTER SET '{charset}'"),
+        }
+    }
+
+    pub fn unknown_collation(collation: &str) -> Self {
+        ServerError {
+            code: ER_UNKNOWN_COLLATION,
+            sql_state: "HY000",
+            message: format!("Unknown collation: '{collation}'"),
+        }
+    }
+
+    pub fn unknown_time_zone(time_zone: &str) -> Self {
+        ServerError {
+            code: ER_UNKNOWN_TIME_ZONE,
+            sql_state: "HY000",
+            message: format!("Unknown or incorrect time zone: '{time_zone}'"),
+        }
+    }
+
+    pub fn procedure_does_not_exist(db: &str, name: &str) -> Self {
+        ServerError {
+            code: ER_SP_DOES_NOT_EXIST,
+            sql_state: "42000",
+            message: format!("PROCEDURE {db}.{name} does not exist"),
+        }
+    }
+
+    // the client can't take the result sets of a CALL without CLIENT_MULTI_RESULTS
+    pub fn procedure_bad_select(name: &str) -> Self {
+        ServerError {
+            code: ER_SP_BADSELECT,
+            sql_state: "0A000",
+            message: format!("PROCEDURE {name} can't return a result set in the given context"),
+        }
+    }
+
+    pub fn procedure_wrong_args(db: &str, name: &str, expected: usize, got: usize) -> Self {
+        ServerError {
+            code: ER_SP_WRONG_NO_OF_ARGS,
+            sql_state: "42000",
+            message: format!("Incorrect number of arguments for PROCEDURE {db}.{name}; expected {expected}, got {got}"),
+        }
+    }
+
+    // a binlog dump that can't start, e.g. from a position past the end of the binary log
+    pub fn binlog_read_error(reason: &str) -> Self {
+        ServerError {
+            code: ER_SOURCE_FATAL_ERROR_READING_BINLOG,
+            sql_state: "HY000",
+            message: reason.to_string(),
+        }
+    }
+
+    // by id in the binary protocol, by name for EXECUTE and DEALLOCATE PREPARE
+    pub fn unknown_stmt_handler(handler: impl std::fmt::Display, command: &str) -> Self {
+        ServerError {
+            code: ER_UNKNOWN_STMT_HANDLER,
+            sql_state: "HY000",
+            message: format!("Unknown prepared statement handler ({handler}) given to {command}"),
+        }
+    }
+
+    pub fn no_open_cursor(stmt_id: u32) -> Self {
+        ServerError {
+            code: ER_STMT_HAS_NO_OPEN_CURSOR,
+            sql_state: "HY000",
+            message: format!("The statement ({stmt_id}) has no open cursor."),
+        }
+    }
+
+    pub fn wrong_param_count(function: &str) -> Self {
+        ServerError {
+            code: ER_WRONG_PARAMCOUNT_TO_NATIVE_FCT,
+            sql_state: "42000",
+            message: format!("Incorrect parameter count in the call to native function '{function}'"),
+        }
+    }
+
+    pub fn session_read_only(name: &str) -> Self {
+        ServerError {
+            code: ER_VARIABLE_IS_READONLY,
+            sql_state: "HY000",
+            message: format!("SESSION variable '{name}' is read-only. Use SET GLOBAL to assign the value"),
+        }
+    }
+
+    pub fn malformed_packet() -> Self {
+        ServerError {
+            code: ER_MALFORMED_PACKET,
+            sql_state: "HY000",
+            message: "Malformed communication packet.".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ERROR {} ({}): {}", self.code, self.sql_state, self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+// what MySQL answers a packet it can't read with
+impl From<ProtocolError> for ServerError {
+    fn from(error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::MalformedPacket => ServerError::malformed_packet(),
+            ProtocolError::PacketTooLarge => ServerError::packet_too_large(),
+            ProtocolError::PacketsOutOfOrder => ServerError::packets_out_of_order(),
+            ProtocolError::UnknownCommand(_) => ServerError::unknown_command(),
+        }
+    }
+}
+
+impl From<ServerError> for ErrorPacket {
+    fn from(error: ServerError) -> Self {
+        ErrorPacket { code: error.code, sql_state: error.sql_state.to_string(), message: error.message }
+    }
+}
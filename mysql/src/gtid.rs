@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// server_uuid, the source of the GTIDs the server gives its transactions.
+pub const SERVER_UUID: &str = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+
+// transaction number of the last GTID handed out
+static LAST_TRANSACTION: AtomicU64 = AtomicU64::new(0);
+
+// the transaction number of a transaction that's committed, `<server_uuid>:<number>` is its GTID
+pub fn commit() -> u64 {
+    LAST_TRANSACTION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+pub fn format(number: u64) -> String {
+    format!("{SERVER_UUID}:{number}")
+}
+
+// gtid_executed, `<server_uuid>:1-<number>`, empty before the first transaction
+pub fn executed() -> String {
+    match LAST_TRANSACTION.load(Ordering::Relaxed) {
+        0 => String::new(),
+        1 => format(1),
+        last => format!("{SERVER_UUID}:1-{last}"),
+    }
+}
+
+// the 16 bytes of the UUID, as GTID events carry it
+pub fn server_uuid_bytes() -> [u8; 16] {
+    let hex = SERVER_UUID.replace('-', "");
+    let mut bytes = [0; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    bytes
+}
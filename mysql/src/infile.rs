@@ -0,0 +1,512 @@
+use std::sync::Arc;
+
+use mysql_protocol::charset::Charset;
+use mysql_protocol::column::{
+    ColumnDefinition41, ColumnType, NO_DEFAULT_VALUE_FLAG, NOT_NULL_FLAG, UNSIGNED_FLAG, UTF8MB4_GENERAL_CI,
+};
+use mysql_protocol::packet::StateChange;
+
+use crate::binary::{parse_datetime, parse_time};
+use crate::catalog::{self, InsertCount, Table};
+use crate::error::ServerError;
+use crate::query::TableName;
+use crate::resultset::{Row, Value};
+use crate::session::Session;
+
+/// `FIELDS` and `LINES` clauses of LOAD DATA, by default tab separated fields and a row per line.
+///
+/// https://dev.mysql.com/doc/refman/9.4/en/load-data.html#load-data-field-line-handling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Format {
+    pub fields_terminated_by: Vec<u8>,
+    pub enclosed_by: Option<u8>,
+    pub escaped_by: Option<u8>,
+    pub lines_starting_by: Vec<u8>,
+    pub lines_terminated_by: Vec<u8>,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format {
+            fields_terminated_by: b"\t".to_vec(),
+            enclosed_by: None,
+            escaped_by: Some(b'\\'),
+            lines_starting_by: Vec::new(),
+            lines_terminated_by: b"\n".to_vec(),
+        }
+    }
+}
+
+// what a field ends with
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum End {
+    Field,
+    Line,
+    File,
+}
+
+impl Format {
+    // the fields of each line, `None` for NULL
+    fn read_rows(&self, data: &[u8]) -> Vec<Vec<Option<Vec<u8>>>> {
+        let mut rows = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            // anything up to the line prefix is skipped
+            if !self.lines_starting_by.is_empty() {
+                match find(&data[pos..], &self.lines_starting_by) {
+                    Some(i) => pos += i + self.lines_starting_by.len(),
+                    None => break,
+                }
+            }
+
+            let mut fields = Vec::new();
+            loop {
+                let (field, end) = self.read_field(data, &mut pos);
+                fields.push(field);
+                if end != End::Field {
+                    break;
+                }
+            }
+            rows.push(fields);
+        }
+
+        rows
+    }
+
+    fn read_field(&self, data: &[u8], pos: &mut usize) -> (Option<Vec<u8>>, End) {
+        let start = *pos;
+        let quoted = self.enclosed_by.is_some() && data.get(start) == self.enclosed_by.as_ref();
+        if quoted {
+            *pos += 1;
+        }
+
+        let mut value = Vec::new();
+        loop {
+            let rest = &data[*pos..];
+            if !quoted && let Some((end, len)) = self.terminator(rest) {
+                let null = self.is_null(&data[start..*pos]);
+                *pos += len;
+                return (if null { None } else { Some(value) }, end);
+            }
+
+            // a quote that's never closed runs to the end of the file
+            let Some(&c) = rest.first() else {
+                return (Some(value), End::File);
+            };
+
+            if Some(c) == self.escaped_by && rest.len() > 1 {
+                value.push(unescape(rest[1]));
+                *pos += 2;
+            } else if quoted && Some(c) == self.enclosed_by {
+                if rest.get(1) == Some(&c) {
+                    // doubled quote
+                    value.push(c);
+                    *pos += 2;
+                } else if let Some((end, len)) = self.terminator(&rest[1..]) {
+                    *pos += 1 + len;
+                    return (Some(value), end);
+                } else {
+                    value.push(c);
+                    *pos += 1;
+                }
+            } else {
+                value.push(c);
+                *pos += 1;
+            }
+        }
+    }
+
+    // the field or line terminator, or the end of the file, `rest` starts with
+    fn terminator(&self, rest: &[u8]) -> Option<(End, usize)> {
+        let starts_with = |terminator: &[u8]| !terminator.is_empty() && rest.starts_with(terminator);
+        if rest.is_empty() {
+            Some((End::File, 0))
+        } else if starts_with(&self.fields_terminated_by) {
+            Some((End::Field, self.fields_terminated_by.len()))
+        } else if starts_with(&self.lines_terminated_by) {
+            Some((End::Line, self.lines_terminated_by.len()))
+        } else {
+            None
+        }
+    }
+
+    // `\N` unquoted, or a bare `NULL` when fields can be quoted
+    fn is_null(&self, raw: &[u8]) -> bool {
+        matches!(self.escaped_by, Some(escape) if raw == [escape, b'N'])
+            || (self.enclosed_by.is_some() && raw == b"NULL")
+    }
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+fn unescape(c: u8) -> u8 {
+    match c {
+        b'0' => 0x00,
+        b'b' => 0x08,
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'Z' => 0x1a,
+        c => c,
+    }
+}
+
+/// `LOAD DATA LOCAL INFILE`, the client sends the file once the server asks for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadData {
+    pub file: String,
+    pub table: TableName,
+    pub replace: bool, // rows with a duplicate key replace the old ones instead of being skipped
+    pub format: Format,
+    pub ignore_lines: usize,
+    pub columns: Vec<String>, // the column of each field, all of the table's when empty
+}
+
+/// Counts of the OK packet that ends LOAD DATA.
+#[derive(Debug)]
+pub struct Loaded {
+    pub records: u64,
+    pub count: InsertCount,
+    pub warnings: u64,
+}
+
+impl Loaded {
+    pub fn affected_rows(&self) -> u64 {
+        self.count.inserted + self.count.deleted
+    }
+
+    pub fn info(&self) -> String {
+        format!(
+            "Records: {}  Deleted: {}  Skipped: {}  Warnings: {}",
+            self.records, self.count.deleted, self.count.skipped, self.warnings
+        )
+    }
+}
+
+impl LoadData {
+    // before the client is asked for the file
+    pub fn check(&self, session: &Session) -> Result<(), ServerError> {
+        if !session.local_files() {
+            return Err(ServerError::local_files_disabled());
+        }
+        if catalog::is_system_schema(&self.table.schema) {
+            return Err(ServerError::read_only_table(&self.table.name));
+        }
+        self.resolve(session).map(|_| ())
+    }
+
+    // the table and, per field of a line, the index of its column
+    fn resolve(&self, session: &Session) -> Result<(Arc<Table>, Vec<usize>), ServerError> {
+        let table = catalog::table(&self.table.schema, &self.table.name, session)
+            .ok_or_else(|| ServerError::no_such_table(&self.table.schema, &self.table.name))?;
+
+        let targets = if self.columns.is_empty() {
+            (0..table.columns.len()).collect()
+        } else {
+            self.columns.iter()
+                .map(|name| {
+                    table.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name)).ok_or_else(|| ServerError::bad_field(name))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        Ok((table, targets))
+    }
+
+    // values that don't fit their column are a warning rather than an error, as the file is already sent
+    pub fn load(&self, contents: &[u8], session: &mut Session) -> Result<Loaded, ServerError> {
+        let (table, targets) = self.resolve(session)?;
+        let (rows, mut warnings) = self.rows(contents, &table.columns, &targets, session.variables.client_charset());
+
+        let records = rows.len() as u64;
+        let count = catalog::insert(&self.table.schema, &self.table.name, rows, self.replace)?;
+        warnings += count.skipped;
+
+        if let Some(gtid) = &count.gtid {
+            session.track(StateChange::Gtid(gtid.clone()));
+        }
+
+        Ok(Loaded { records, count, warnings })
+    }
+
+    // the lines after the ignored ones as rows of the table, with the count of warnings
+    fn rows(&self, contents: &[u8], columns: &[ColumnDefinition41], targets: &[usize], charset: Charset) -> (Vec<Row>, u64) {
+        // NOT NULL columns left out of the column list
+        let without_default = columns.iter()
+            .enumerate()
+            .filter(|(i, c)| !targets.contains(i) && c.flags & NO_DEFAULT_VALUE_FLAG != 0)
+            .count() as u64;
+
+        let mut warnings = 0;
+        let mut rows = Vec::new();
+        for fields in self.format.read_rows(contents).into_iter().skip(self.ignore_lines) {
+            // too few or too many fields
+            if fields.len() != targets.len() {
+                warnings += 1;
+            }
+            warnings += without_default;
+
+            let mut values = columns.iter().map(default_value).collect::<Vec<_>>();
+            for (&i, field) in targets.iter().zip(&fields) {
+                values[i] = convert(&columns[i], field.as_deref(), charset, &mut warnings);
+            }
+            rows.push(Row(values));
+        }
+
+        (rows, warnings)
+    }
+}
+
+fn default_value(column: &ColumnDefinition41) -> Value {
+    if column.flags & NOT_NULL_FLAG == 0 {
+        Value::Null
+    } else {
+        zero_value(column)
+    }
+}
+
+// what a NOT NULL column gets instead of a NULL or a value that doesn't fit it
+fn zero_value(column: &ColumnDefinition41) -> Value {
+    match column.column_type {
+        ColumnType::Tiny | ColumnType::Short | ColumnType::Long | ColumnType::Int24 | ColumnType::LongLong | ColumnType::Year => {
+            Value::Int(0)
+        }
+        ColumnType::Float => Value::Float(0.0),
+        ColumnType::Double => Value::Double(0.0),
+        ColumnType::Decimal | ColumnType::NewDecimal => decimal(0.0, column.decimals),
+        ColumnType::Date => Value::Date(Default::default()),
+        ColumnType::DateTime | ColumnType::Timestamp => Value::DateTime(Default::default()),
+        ColumnType::Time => Value::Time(Default::default()),
+        _ => Value::Bytes(Vec::new()),
+    }
+}
+
+fn decimal(value: f64, decimals: u8) -> Value {
+    Value::Bytes(format!("{:.*}", decimals as usize, value).into_bytes())
+}
+
+// the smallest and largest value of an integer column, what's outside is clamped to them
+fn integer_range(column: &ColumnDefinition41) -> (i128, i128) {
+    let bits = match column.column_type {
+        ColumnType::Year => return (1901, 2155),
+        ColumnType::Tiny => 8,
+        ColumnType::Short => 16,
+        ColumnType::Int24 => 24,
+        ColumnType::Long => 32,
+        _ => 64,
+    };
+    if column.flags & UNSIGNED_FLAG != 0 {
+        (0, (1 << bits) - 1)
+    } else {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    }
+}
+
+// the text of a field in the client's charset as a value of its column, counting a warning for each one
+// that doesn't fit
+fn convert(column: &ColumnDefinition41, field: Option<&[u8]>, charset: Charset, warnings: &mut u64) -> Value {
+    let Some(field) = field else {
+        if column.flags & NOT_NULL_FLAG != 0 {
+            *warnings += 1;
+            return zero_value(column);
+        }
+        return Value::Null;
+    };
+
+    let text = charset.decode(field);
+    let number = text.trim();
+    let value = match column.column_type {
+        ColumnType::Tiny | ColumnType::Short | ColumnType::Long | ColumnType::Int24 | ColumnType::LongLong | ColumnType::Year => {
+            number.parse::<i128>().ok().map(|n| {
+                let (min, max) = integer_range(column);
+                // the zero year is in range too
+                let clamped = if column.column_type == ColumnType::Year && n == 0 { 0 } else { n.clamp(min, max) };
+                if clamped != n {
+                    *warnings += 1;
+                }
+                if column.flags & UNSIGNED_FLAG != 0 { Value::UInt(clamped as u64) } else { Value::Int(clamped as i64) }
+            })
+        }
+        ColumnType::Float => number.parse().ok().map(Value::Float),
+        ColumnType::Double => number.parse().ok().map(Value::Double),
+        ColumnType::Decimal | ColumnType::NewDecimal => number.parse().ok().map(|v| decimal(v, column.decimals)),
+        ColumnType::Date => parse_datetime(field).map(Value::Date),
+        ColumnType::DateTime | ColumnType::Timestamp => parse_datetime(field).map(Value::DateTime),
+        ColumnType::Time => parse_time(field).map(Value::Time),
+        // the length is in bytes, utf8mb4 takes up to 4 per character
+        _ if column.character_set == UTF8MB4_GENERAL_CI => {
+            let max = column.column_length as usize / 4;
+            if text.chars().count() > max {
+                *warnings += 1;
+            }
+            Some(Value::Bytes(text.chars().take(max).collect::<String>().into_bytes()))
+        }
+        _ => {
+            let max = column.column_length as usize;
+            if field.len() > max {
+                *warnings += 1;
+            }
+            Some(Value::Bytes(field[..field.len().min(max)].to_vec()))
+        }
+    };
+
+    value.unwrap_or_else(|| {
+        *warnings += 1;
+        zero_value(column)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(value: &str) -> Option<Vec<u8>> {
+        Some(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn default_format() {
+        let rows = Format::default().read_rows(b"1\tone\n2\t\\N\n3\ta\\tb\\\\c\\0\n");
+        assert_eq!(rows, [
+            vec![field("1"), field("one")],
+            vec![field("2"), None],
+            vec![field("3"), Some(b"a\tb\\c\0".to_vec())],
+        ]);
+        // without a line terminator at the end
+        assert_eq!(Format::default().read_rows(b"1\tlast"), [vec![field("1"), field("last")]]);
+    }
+
+    #[test]
+    fn fields_terminated_enclosed_and_escaped() {
+        let format = Format {
+            fields_terminated_by: b",".to_vec(),
+            enclosed_by: Some(b'"'),
+            ..Format::default()
+        };
+        let rows = format.read_rows(b"1,\"a,b\",\"say \"\"hi\"\"\"\n2,NULL,\"NULL\"\n3,\\N,\"x\\\"y\"\n");
+        assert_eq!(rows, [
+            vec![field("1"), field("a,b"), field("say \"hi\"")],
+            vec![field("2"), None, field("NULL")],
+            vec![field("3"), None, field("x\"y")],
+        ]);
+
+        // another escape character, `\N` is just text then
+        let format = Format { escaped_by: Some(b'$'), ..format };
+        assert_eq!(format.read_rows(b"$N,\\N,a$,b\n"), [vec![None, field("\\N"), field("a,b")]]);
+        let format = Format { escaped_by: None, ..format };
+        assert_eq!(format.read_rows(b"\\N,a\\,b\n"), [vec![field("\\N"), field("a\\"), field("b")]]);
+    }
+
+    #[test]
+    fn lines_starting_and_terminated() {
+        let format = Format {
+            lines_starting_by: b"xxx".to_vec(),
+            lines_terminated_by: b"\r\n".to_vec(),
+            ..Format::default()
+        };
+        // a line without the prefix is skipped, what comes before the prefix too
+        let rows = format.read_rows(b"xxx1\tA\r\nno prefix\r\nsome text xxx2\tB\r\n");
+        assert_eq!(rows, [vec![field("1"), field("A")], vec![field("2"), field("B")]]);
+    }
+
+    fn columns() -> Vec<ColumnDefinition41> {
+        vec![
+            ColumnDefinition41::new("id", ColumnType::Long, 11).with_flags(NOT_NULL_FLAG | NO_DEFAULT_VALUE_FLAG),
+            ColumnDefinition41::new("name", ColumnType::VarString, 40).with_charset(UTF8MB4_GENERAL_CI),
+        ]
+    }
+
+    fn load_data(ignore_lines: usize) -> LoadData {
+        LoadData {
+            file: "data.txt".to_string(),
+            table: TableName { schema: "test".to_string(), name: "t".to_string() },
+            replace: false,
+            format: Format::default(),
+            ignore_lines,
+            columns: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn ignore_lines() {
+        let (rows, warnings) = load_data(1).rows(b"id\tname\n1\tone\n2\ttwo\n", &columns(), &[0, 1], Charset::Utf8mb4);
+        let values = rows.into_iter().map(|row| row.0).collect::<Vec<_>>();
+        assert_eq!(values, [
+            vec![Value::Int(1), Value::Bytes(b"one".to_vec())],
+            vec![Value::Int(2), Value::Bytes(b"two".to_vec())],
+        ]);
+        assert_eq!(warnings, 0);
+    }
+
+    // a warning for a row with too few fields and for one with too many, the missing name is NULL
+    #[test]
+    fn short_and_long_rows() {
+        let (rows, warnings) = load_data(0).rows(b"1\n2\ttwo\textra\n3\tthree\n", &columns(), &[0, 1], Charset::Utf8mb4);
+        assert_eq!(rows[0].0, [Value::Int(1), Value::Null]);
+        assert_eq!(rows[1].0, [Value::Int(2), Value::Bytes(b"two".to_vec())]);
+        assert_eq!(warnings, 2);
+
+        // and one for each row that leaves a NOT NULL column without a default out
+        let (rows, warnings) = load_data(0).rows(b"one\ntwo\n", &columns(), &[1], Charset::Utf8mb4);
+        assert_eq!(rows[1].0, [Value::Int(0), Value::Bytes(b"two".to_vec())]);
+        assert_eq!(warnings, 2);
+    }
+
+    #[test]
+    fn integers_are_clamped() {
+        let cases = [
+            (ColumnType::Tiny, 0, "-129", Value::Int(-128)),
+            (ColumnType::Tiny, UNSIGNED_FLAG, "256", Value::UInt(255)),
+            (ColumnType::Short, 0, "40000", Value::Int(32767)),
+            (ColumnType::Short, UNSIGNED_FLAG, "-1", Value::UInt(0)),
+            (ColumnType::Int24, 0, "-9000000", Value::Int(-8388608)),
+            (ColumnType::Int24, UNSIGNED_FLAG, "20000000", Value::UInt(16777215)),
+            (ColumnType::Long, 0, "2147483648", Value::Int(2147483647)),
+            (ColumnType::Long, UNSIGNED_FLAG, "4294967296", Value::UInt(4294967295)),
+            (ColumnType::LongLong, 0, "9223372036854775808", Value::Int(i64::MAX)),
+            (ColumnType::Year, UNSIGNED_FLAG, "1900", Value::UInt(1901)),
+            (ColumnType::Year, UNSIGNED_FLAG, "2156", Value::UInt(2155)),
+        ];
+        for (column_type, flags, text, expected) in cases {
+            let column = ColumnDefinition41::new("n", column_type, 20).with_flags(flags);
+            let mut warnings = 0;
+            assert_eq!(convert(&column, Some(text.as_bytes()), Charset::Utf8mb4, &mut warnings), expected, "{column_type:?} {text}");
+            assert_eq!(warnings, 1, "{column_type:?} {text}");
+        }
+
+        for (column_type, text, expected) in [(ColumnType::Tiny, " 127 ", 127), (ColumnType::Long, "-2147483648", -2147483648)] {
+            let mut warnings = 0;
+            let column = ColumnDefinition41::new("n", column_type, 11);
+            assert_eq!(convert(&column, Some(text.as_bytes()), Charset::Utf8mb4, &mut warnings), Value::Int(expected));
+            assert_eq!(warnings, 0);
+        }
+        let year = ColumnDefinition41::new("y", ColumnType::Year, 4).with_flags(UNSIGNED_FLAG);
+        let mut warnings = 0;
+        assert_eq!(convert(&year, Some(b"0"), Charset::Utf8mb4, &mut warnings), Value::UInt(0));
+        assert_eq!(warnings, 0);
+    }
+
+    #[test]
+    fn fields_in_the_client_charset() {
+        let column = ColumnDefinition41::new("name", ColumnType::VarString, 40).with_charset(UTF8MB4_GENERAL_CI);
+        let mut warnings = 0;
+        let value = convert(&column, Some(b"caf\xe9 \x80"), Charset::Latin1, &mut warnings);
+        assert_eq!(value, Value::Bytes("caf\u{e9} \u{20ac}".as_bytes().to_vec()));
+        assert_eq!(warnings, 0);
+    }
+
+    #[test]
+    fn values_that_dont_fit() {
+        let mut warnings = 0;
+        let id = &columns()[0];
+        assert_eq!(convert(id, None, Charset::Utf8mb4, &mut warnings), Value::Int(0));
+        assert_eq!(convert(id, Some(b"abc"), Charset::Utf8mb4, &mut warnings), Value::Int(0));
+        let name = ColumnDefinition41::new("name", ColumnType::VarString, 8).with_charset(UTF8MB4_GENERAL_CI);
+        assert_eq!(convert(&name, Some(b"abc"), Charset::Utf8mb4, &mut warnings), Value::Bytes(b"ab".to_vec()));
+        assert_eq!(convert(&name, None, Charset::Utf8mb4, &mut warnings), Value::Null);
+        assert_eq!(warnings, 3);
+    }
+}
@@ -1,600 +1,652 @@
-use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::thread;
-
-const MAX_PACKET_SIZE: u32 = 16_777_215; // 2 ** 24 - 1
-
-#[derive(Debug)]
-enum Packet {
-    Greeting,
-    AuthSuccess,
-    OK,
-    ColumnCount(u8),
-    SimpleFieldPacket,
-    Eof,
-    SimpleRowPacket,
-    IdFieldPacket,
-    TitleFieldPacket,
-    DescriptionFieldPacket,
-    CategoryIdFieldPacket,
-    ComplexEof,
-    ComplexRow1Packet,
-    ComplexRow2Packet,
-    PrepareOk,
-    PreparedRowPacket,
+use std::time::Duration;
+
+use mysql_protocol::codec::{NULL_VALUE, Reader};
+use mysql_protocol::column::{BINARY_FLAG, ColumnDefinition41, ColumnType};
+use mysql_protocol::command::{Command, HandshakeResponse};
+use mysql_protocol::connection::Connection;
+use mysql_protocol::flags::{
+    CLIENT_MULTI_STATEMENTS, CURSOR_TYPE_READ_ONLY, MYSQL_OPTION_MULTI_STATEMENTS_OFF, MYSQL_OPTION_MULTI_STATEMENTS_ON,
+    SERVER_MORE_RESULTS_EXISTS, SERVER_STATUS_AUTOCOMMIT, SERVER_STATUS_CURSOR_EXISTS, SERVER_STATUS_LAST_ROW_SENT,
+    SERVER_STATUS_NO_INDEX_USED,
+};
+use mysql_protocol::packet::{Handshake, Packet};
+use mysql_protocol::ProtocolError;
+
+use crate::binlog::Dump;
+use crate::error::ServerError;
+use crate::process::ProcessHandle;
+use crate::query::QueryResult;
+use crate::resultset::{ResultSet, Row};
+use crate::session::{Cursor, PreparedStatement, Session};
+
+mod binary;
+mod binlog;
+mod catalog;
+mod error;
+mod gtid;
+mod infile;
+mod process;
+mod query;
+mod resultset;
+mod session;
+mod variables;
+
+// the greeting, what the server says it is and can do
+fn greeting(connection_id: u32) -> Handshake {
+    Handshake {
+        server_version: "9.4.0".to_string(),
+        connection_id,
+        auth_plugin_data: b"abcdabcdabcdabcdabcd".to_vec(),
+        capabilities: 0xDFFF_FFFF, // 0xFFFF and the extended 0xDFFF
+        collation: 0xFF, // utf8mb4 COLLATE utf8mb4_0900_ai_ci (255)
+        status_flags: SERVER_STATUS_AUTOCOMMIT,
+        auth_plugin: "caching_sha2_password".to_string(),
+    }
 }
 
-impl Packet {
-    fn as_bytes(&self) -> Vec<u8> {
-        let mut response = Vec::new();
-
-        match self {
-            Packet::Greeting => {
-                response.push(0x0A); // 10, protocol version number
-
-                response.extend(b"9.4.0\0");
-
-                response.extend([0x01, 0x00, 0x00, 0x00]); // thread ID
-
-                response.extend(b"abcdabcd\0"); // salt (part 1)
-
-                // server capabilities
-                // .... .... .... ...1 = Long Password: Set
-                // .... .... .... ..1. = Found Rows: Set
-                // .... .... .... .1.. = Long Column Flags: Set
-                // .... .... .... 1... = Connect With Database: Set
-                // .... .... ...1 .... = Don't Allow database.table.column: Set
-                // .... .... ..1. .... = Can use compression protocol: Set
-                // .... .... .1.. .... = ODBC Client: Set
-                // .... .... 1... .... = Can Use LOAD DATA LOCAL: Set
-                // .... ...1 .... .... = Ignore Spaces before '(': Set
-                // .... ..1. .... .... = Speaks 4.1 protocol (new flag): Set
-                // .... .1.. .... .... = Interactive Client: Set
-                // .... 1... .... .... = Switch to SSL after handshake: Set
-                // ...1 .... .... .... = Ignore sigpipes: Set
-                // ..1. .... .... .... = Knows about transactions: Set
-                // .1.. .... .... .... = Speaks 4.1 protocol (old flag): Set
-                // 1... .... .... .... = Can do 4.1 authentication: Set
-                response.extend([0xFF, 0xFF]);
-
-                // utf8mb4 COLLATE utf8mb4_0900_ai_ci (255)
-                response.push(0xFF);
-
-                // server status 0x0002
-                // .... .... .... ...0 = In transaction: Not set
-                // .... .... .... ..1. = AUTO_COMMIT: Set
-                // .... .... .... .0.. = Multi query / Unused: Not set
-                // .... .... .... 0... = More results: Not set
-                // .... .... ...0 .... = Bad index used: Not set
-                // .... .... ..0. .... = No index used: Not set
-                // .... .... .0.. .... = Cursor exists: Not set
-                // .... .... 0... .... = Last row sent: Not set
-                // .... ...0 .... .... = Database dropped: Not set
-                // .... ..0. .... .... = No backslash escapes: Not set
-                // .... .0.. .... .... = Metadata changed: Not set
-                // .... 0... .... .... = Query was slow: Not set
-                // ...0 .... .... .... = PS Out Params: Not set
-                // ..0. .... .... .... = In Trans Readonly: Not set
-                // .0.. .... .... .... = Session state changed: Not set
-                response.extend([0x02, 0x00]);
-
-                // extended server capabilities
-                response.extend([0xFF, 0xDF]);
-
-                // authentication plugin length
-                response.push(0x15);
-
-                // unused, 10 bytes
-                let unused = [0; 10];
-                response.extend(unused);
-
-                response.extend(b"abcdabcdabcd\0"); // salt (part 2)
-
-                // authentication plugin
-                response.extend(b"caching_sha2_password\0");
-            }
-            Packet::AuthSuccess => {
-                response.extend([0x01, 0x03]); // SHA2 Auth State: fast_auth_success
-            }
-            Packet::OK => {
-                response.push(0x00); // OK
-                response.push(0x00); // affected_rows
-                response.push(0x00); // last_insert_id
-
-                response.extend([0x02, 0x00]); // status_flags
-
-                response.extend([0x00, 0x00]); // warnings
-            }
-            Packet::ColumnCount(c) => {
-                response.push(*c);
-            }
-            Packet::Eof => {
-                response.push(0xfe);
-                response.extend([0x00, 0x00]); // warnings
-                response.extend([0x02, 0x00]); // status_flags
-            }
-            Packet::SimpleFieldPacket => {
-                // catalog
-                // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
-                response.push(3);
-                response.extend(b"def");
-
-                response.push(0x00); // database
-                response.push(0x00); // table
-                response.push(0x00); // original table
+// the client's limit can only make it smaller than the global max_allowed_packet
+fn max_allowed_packet(handshake_response: &HandshakeResponse) -> usize {
+    let max_allowed_packet = variables::max_allowed_packet();
+    match handshake_response.max_packet_size as usize {
+        0 => max_allowed_packet,
+        size => size.min(max_allowed_packet),
+    }
+}
 
-                // name
-                response.push(2);
-                response.extend(b"id");
+// column count, column definitions, rows and the closing EOF, or OK when the client set
+// CLIENT_DEPRECATE_EOF, in which case there's also no EOF after the column definitions
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset.html
+fn send_result_set(
+    conn: &mut Connection,
+    session: &mut Session,
+    columns: &[ColumnDefinition41],
+    rows: &[Vec<u8>],
+    status_flags: u16,
+) -> Result<(), std::io::Error> {
+    // an ERR in place of the result set, once the header is out the client expects every row
+    if !rows.iter().all(|row| conn.fits(row)) {
+        return conn.write_packet(&Packet::Error(ServerError::packet_too_large().into()).as_bytes());
+    }
 
-                response.push(0x00); // original name
+    conn.write_packet(&Packet::ColumnCount(columns.len() as u64).as_bytes())?;
 
-                response.push(0x0c); // length of fixed length fields, 0x0c (12)
+    for column in columns {
+        conn.write_packet(&column.as_bytes())?;
+    }
 
-                response.extend([0x3f, 0x00]); // charset number, 63, binary COLLATE
+    if !session.deprecate_eof() {
+        conn.write_packet(&Packet::Eof(status_flags).as_bytes())?;
+    }
 
-                response.extend([0x04, 0x00, 0x00, 0x00]); // length
+    for row in rows {
+        conn.write_packet(row)?;
+    }
 
-                response.push(0x08); // FIELD_TYPE_LONGLONG
+    conn.write_packet(&eof_packet(session, status_flags).as_bytes())
+}
 
-                // .... .... .... ...1 = Not null: Set
-                // .... .... .... ..0. = Primary key: Not set
-                // .... .... .... .0.. = Unique key: Not set
-                // .... .... .... 0... = Multiple key: Not set
-                // .... .... ...0 .... = Blob: Not set
-                // .... .... ..0. .... = Unsigned: Not set
-                // .... .... .0.. .... = Zero fill: Not set
-                // .... ...0 .... .... = Enum: Not set
-                // .... ..0. .... .... = Auto increment: Not set
-                // .... .0.. .... .... = Timestamp: Not set
-                // .... 0... .... .... = Set: Not set
-                response.extend([0x81, 0x00]); // flags
+// a result set, an OK, or the result sets of a CALL followed by its OK, all but the last
+// of them with SERVER_MORE_RESULTS_EXISTS
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_command_phase_sp.html
+fn send_query_result(
+    conn: &mut Connection,
+    session: &mut Session,
+    result: &QueryResult,
+    status_flags: u16,
+    binary: bool,
+) -> Result<(), std::io::Error> {
+    let results = session.variables.results_collation();
+    let mut send_rows = |session: &mut Session, result_set: &ResultSet, status_flags| {
+        let result_set = result_set.in_charset(results);
+        let rows = result_set.rows.iter()
+            .map(|row| if binary { row.as_binary_bytes(&result_set.columns) } else { row.as_text_bytes() })
+            .collect::<Vec<_>>();
+        send_result_set(conn, session, &result_set.columns, &rows, status_flags)
+    };
+
+    match result {
+        QueryResult::Rows(result_set) => send_rows(session, result_set, status_flags),
+        QueryResult::Call(result_sets) => {
+            for result_set in result_sets {
+                send_rows(session, result_set, status_flags | SERVER_MORE_RESULTS_EXISTS)?;
+            }
+            conn.write_packet(&ok_packet(session, status_flags, 0, 0, String::new()).as_bytes())
+        }
+        QueryResult::Loaded(loaded) => {
+            let warnings = loaded.warnings.min(u16::MAX as u64) as u16;
+            let ok = ok_packet(session, status_flags, loaded.affected_rows(), warnings, loaded.info());
+            conn.write_packet(&ok.as_bytes())
+        }
+        QueryResult::Done => conn.write_packet(&ok_packet(session, status_flags, 0, 0, String::new()).as_bytes()),
+        QueryResult::Prepared => {
+            conn.write_packet(&ok_packet(session, status_flags, 0, 0, "Statement prepared".to_string()).as_bytes())
+        }
+        // LOAD DATA LOCAL has its OK once the file is in, as `Loaded`
+        QueryResult::LocalInfile(_) => unreachable!("LOAD DATA can't be prepared"),
+    }
+}
 
-                response.push(0x00); // decimals
+// a response that broke off leaves the client in the middle of it, it gets the reason if there's
+// one for it, then the connection has to be closed
+fn send_protocol_error(conn: &mut Connection, e: &std::io::Error) {
+    if let Some(&error) = e.get_ref().and_then(|e| e.downcast_ref::<ProtocolError>()) {
+        let _ = conn.write_packet(&Packet::Error(ServerError::from(error).into()).as_bytes());
+        let _ = conn.flush();
+    }
+}
 
-                response.extend([0x00, 0x00]); // reserved
-            }
-            Packet::SimpleRowPacket => {
-                response.push(3);
-                response.extend(b"123");
-            }
-            Packet::PreparedRowPacket => {
-                response.push(0x00); // OK
-                response.push(0x00); // row null buffer
-                response.extend(123u64.to_le_bytes());
-            }
-            Packet::IdFieldPacket => {
-                // catalog
-                // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
-                response.push(3);
-                response.extend(b"def");
-
-                // database
-                response.push(0x09);
-                response.extend(b"protocols");
-                // table
-                response.push(0x08);
-                response.extend(b"products");
-                // original table
-                response.push(0x08);
-                response.extend(b"products");
-
-                // name
-                response.push(2);
-                response.extend(b"id");
-
-                // original name
-                response.push(2);
-                response.extend(b"id");
-
-                response.push(0x0c); // length of fixed length fields, 0x0c (12)
-
-                response.extend([0x3f, 0x00]); // charset number, 63, binary COLLATE
-
-                response.extend([0x04, 0x00, 0x00, 0x00]); // length
-
-                response.push(0x03); // FIELD_TYPE_LONG
-
-                // flags
-                // .... .... .... ...1 = Not null: Set
-                // .... .... .... ..1. = Primary key: Not set
-                // .... .... .... .0.. = Unique key: Not set
-                // .... .... .... 0... = Multiple key: Not set
-                // .... .... ...0 .... = Blob: Not set
-                // .... .... ..0. .... = Unsigned: Not set
-                // .... .... .0.. .... = Zero fill: Not set
-                // .... ...0 .... .... = Enum: Not set
-                // .... ..0. .... .... = Auto increment: Not set
-                // .... .0.. .... .... = Timestamp: Not set
-                // .... 0... .... .... = Set: Not set
-                response.extend([0x03, 0x50]);
-
-                response.push(0x00); // decimals
-
-                response.extend([0x00, 0x00]); // reserved
-            }
-            Packet::TitleFieldPacket => {
-                // catalog
-                // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
-                response.push(3);
-                response.extend(b"def");
-
-                // database
-                response.push(0x09);
-                response.extend(b"protocols");
-                // table
-                response.push(0x08);
-                response.extend(b"products");
-                // original table
-                response.push(0x08);
-                response.extend(b"products");
-
-                // name
-                response.push(5);
-                response.extend(b"title");
-
-                // original name
-                response.push(5);
-                response.extend(b"title");
-
-                response.push(0x0c); // length of fixed length fields, 0x0c (12)
-
-                response.extend([0x2d, 0x00]); // charset number, 45, utf8mb4 COLLATE utf8mb4_general_ci
-
-                response.extend([0x90, 0x01, 0x00, 0x00]); // length, 400
-
-                response.push(0xfd); // FIELD_TYPE_VAR_STRING
-
-                // flags
-                // .... .... .... ...1 = Not null: Set
-                // .... .... .... ..0. = Primary key: Not set
-                // .... .... .... .1.. = Unique key: Set
-                // .... .... .... 0... = Multiple key: Not set
-                // .... .... ...0 .... = Blob: Not set
-                // .... .... ..0. .... = Unsigned: Not set
-                // .... .... .0.. .... = Zero fill: Not set
-                // .... ...0 .... .... = Enum: Not set
-                // .... ..0. .... .... = Auto increment: Not set
-                // .... .0.. .... .... = Timestamp: Not set
-                // .... 0... .... .... = Set: Not set
-                response.extend([0x05, 0x50]);
-
-                response.push(0x00); // decimals
-
-                response.extend([0x00, 0x00]); // reserved
-            }
-            Packet::DescriptionFieldPacket => {
-                // catalog
-                // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
-                response.push(3);
-                response.extend(b"def");
-
-                // database
-                response.push(0x09);
-                response.extend(b"protocols");
-                // table
-                response.push(0x08);
-                response.extend(b"products");
-                // original table
-                response.push(0x08);
-                response.extend(b"products");
-
-                // name
-                response.push(0x0b);
-                response.extend(b"description");
-
-                // original name
-                response.push(0x0b);
-                response.extend(b"description");
-
-                response.push(0x0c); // length of fixed length fields, 0x0c (12)
-
-                response.extend([0x2d, 0x00]); // charset number, 45, utf8mb4 COLLATE utf8mb4_general_ci
-
-                response.extend([0xff, 0xff, 0xff, 0xff]); // length
-
-                response.push(0xfc); // FIELD_TYPE_BLOB
-
-                // flags
-                // .... .... .... ...0 = Not null: Not set
-                // .... .... .... ..0. = Primary key: Not set
-                // .... .... .... .0.. = Unique key: Not set
-                // .... .... .... 0... = Multiple key: Not set
-                // .... .... ...1 .... = Blob: Set
-                // .... .... ..0. .... = Unsigned: Not set
-                // .... .... .0.. .... = Zero fill: Not set
-                // .... ...0 .... .... = Enum: Not set
-                // .... ..0. .... .... = Auto increment: Not set
-                // .... .0.. .... .... = Timestamp: Not set
-                // .... 0... .... .... = Set: Not set
-                response.extend([0x10, 0x00]);
-
-                response.push(0x00); // decimals
-
-                response.extend([0x00, 0x00]); // reserved
-            }
-            Packet::CategoryIdFieldPacket => {
-                // catalog
-                // https://dev.mysql.com/doc/refman/9.4/en/information-schema-schemata-table.html
-                response.push(3);
-                response.extend(b"def");
-
-                // database
-                response.push(0x09);
-                response.extend(b"protocols");
-                // table
-                response.push(0x08);
-                response.extend(b"products");
-                // original table
-                response.push(0x08);
-                response.extend(b"products");
-
-                // name
-                response.push(0x0b);
-                response.extend(b"category_id");
-
-                // original name
-                response.push(0x0b);
-                response.extend(b"category_id");
-
-                response.push(0x0c); // length of fixed length fields, 0x0c (12)
-
-                response.extend([0x3f, 0x00]); // charset number, 63, binary COLLATE
-
-                response.extend([0x06, 0x00, 0x00, 0x0]); // length, 6
-
-                response.push(0x02); // FIELD_TYPE_SHORT
-
-                // flags
-                // .... .... .... ...0 = Not null: Not set
-                // .... .... .... ..0. = Primary key: Not set
-                // .... .... .... .0.. = Unique key: Not set
-                // .... .... .... 0... = Multiple key: Not set
-                // .... .... ...0 .... = Blob: Not set
-                // .... .... ..0. .... = Unsigned: Not set
-                // .... .... .0.. .... = Zero fill: Not set
-                // .... ...0 .... .... = Enum: Not set
-                // .... ..0. .... .... = Auto increment: Not set
-                // .... .0.. .... .... = Timestamp: Not set
-                // .... 0... .... .... = Set: Not set
-                response.extend([0x00, 0x00]);
-
-                response.push(0x00); // decimals
-
-                response.extend([0x00, 0x00]); // reserved
-            }
-            Packet::ComplexEof => {
-                response.push(0xfe);
-                response.extend([0x00, 0x00]); // warnings
-
-                // status_flags
-                // .... .... .... ..1. = AUTO_COMMIT: Set
-                // .... .... ..1. .... = No index used: Set
-                response.extend([0x22, 0x00]);
-            }
-            Packet::ComplexRow1Packet => {
-                // 1
-                response.push(1);
-                response.extend(b"1");
+// the OK packet that ends a command, with the session state changes it made
+fn ok_packet(session: &mut Session, status_flags: u16, affected_rows: u64, warnings: u16, info: String) -> Packet {
+    Packet::OkInfo { affected_rows, warnings, status_flags, info, session_state: session.take_state_changes() }
+}
 
-                // laptop
-                response.push(6);
-                response.extend(b"laptop");
+// the EOF that ends a result set, or the OK in its place with CLIENT_DEPRECATE_EOF, which also carries the
+// session state changes
+fn eof_packet(session: &mut Session, status_flags: u16) -> Packet {
+    if session.deprecate_eof() {
+        Packet::OkEof { status_flags, session_state: session.take_state_changes() }
+    } else {
+        Packet::Eof(status_flags)
+    }
+}
 
-                // null
-                response.push(0xfb);
+// events as OK packets, up to the end of the binary log and an EOF with BINLOG_DUMP_NON_BLOCK,
+// otherwise as they're logged until the replica goes away
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_replication.html
+fn send_binlog(conn: &mut Connection, dump: &mut Dump) -> Result<(), std::io::Error> {
+    let mut events = match dump.start() {
+        Ok(events) => events,
+        Err(error) => return conn.write_packet(&Packet::Error(error.into()).as_bytes()),
+    };
+    events.extend(dump.next(Duration::ZERO));
 
-                // 2
-                response.push(1);
-                response.extend(b"2");
-            }
-            Packet::ComplexRow2Packet => {
-                // 1
-                response.push(1);
-                response.extend(b"2");
-
-                // laptop
-                response.push(5);
-                response.extend(b"phone");
-
-                // null
-                response.push(0x11);
-                response.extend(b"Just a phone desc");
-
-                // 2
-                response.push(5);
-                response.extend(b"20000");
-            }
-            Packet::PrepareOk => {
-                response.push(0x00); // OK
-                response.extend(1u32.to_le_bytes()); // statement id
-                response.extend(1u16.to_le_bytes()); // number of fields
-                response.extend(0u16.to_le_bytes()); // number of parameters
-                response.push(0x00);
-                response.extend(0u16.to_le_bytes()); // warnings
-            }
+    loop {
+        for event in &events {
+            conn.write_packet(&[&[0x00], event.as_slice()].concat())?;
         }
+        conn.flush()?;
 
-        response
+        if dump.non_block() {
+            return conn.write_packet(&Packet::Eof(SERVER_STATUS_AUTOCOMMIT).as_bytes());
+        }
+        events = dump.next(Duration::from_secs(1));
+        if events.is_empty() && conn.is_closed() {
+            return Ok(());
+        }
     }
 }
 
-#[derive(Debug)]
-enum Command {
-    Ping,
-    Quit,
-    Query(String),
-    PrepareStmt(String),
-    CloseStmt(u32),
-    ExecuteStmt(u32, u8, u32), // stmt_id, flags, iterations
-}
+// asks the client for the file, it sends the contents in as many packets as it takes and an empty
+// one at the end, which is all there is when the client can't read the file
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_local_infile_request.html
+fn read_local_infile(conn: &mut Connection, file: &str) -> Result<Vec<u8>, std::io::Error> {
+    conn.write_packet(&Packet::LocalInfile(file.to_string()).as_bytes())?;
+    conn.flush()?;
 
-impl Command {
-    fn parse(data: &[u8]) -> Command {
-        let command = data[0];
-
-        match command {
-            14 => Command::Ping,
-            1 => Command::Quit,
-            3 => Command::Query(String::from_utf8_lossy(&data[1..]).to_string()),
-            22 => Command::PrepareStmt(String::from_utf8_lossy(&data[1..]).to_string()),
-            25 => {
-                let bytes: [u8; 4] = data[1..5].try_into().expect("slice with incorrect length");
-                Command::CloseStmt(u32::from_le_bytes(bytes))
-            }
-            23 => {
-                let bytes: [u8; 4] = data[1..5].try_into().expect("slice with incorrect length");
-                let stmt_id = u32::from_le_bytes(bytes);
-                let flags = u8::from(data[5]);
-                let bytes: [u8; 4] = data[6..10].try_into().expect("slice with incorrect length");
-                let iterations = u32::from_le_bytes(bytes);
-
-                Command::ExecuteStmt(stmt_id, flags, iterations)
-            }
-            _ => unimplemented!(),
+    let mut contents = Vec::new();
+    loop {
+        let packet = conn.read_packet()?;
+        if packet.is_empty() {
+            return Ok(contents);
         }
+        contents.extend(packet);
     }
 }
 
-fn print_message(data: impl AsRef<[u8]>, title: &str) {
-     let x = data.as_ref().iter()
-         .map(|b| format!("{:02x}", b))
-         .collect::<Vec<String>>()
-         .join(" ");
-    println!("{title}: {x}");
+// a column definition with its default value per matching column, closed by an EOF,
+// or an OK with CLIENT_DEPRECATE_EOF
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_field_list.html
+fn send_field_list(conn: &mut Connection, session: &mut Session, table: &str, wildcard: &str) -> Result<(), std::io::Error> {
+    let Some(database) = &session.database else {
+        return conn.write_packet(&Packet::Error(ServerError::no_database_selected().into()).as_bytes());
+    };
+    let Some(table) = catalog::table(database, table, session) else {
+        return conn.write_packet(&Packet::Error(ServerError::no_such_table(database, table).into()).as_bytes());
+    };
+
+    let results = session.variables.results_collation();
+    for column in &table.columns {
+        if wildcard.is_empty() || query::like(&column.name, wildcard) {
+            let mut definition = column.in_charset(results).as_bytes();
+            definition.push(NULL_VALUE); // none of the columns has a default value
+            conn.write_packet(&definition)?;
+        }
+    }
+
+    let status_flags = session.status_flags();
+    conn.write_packet(&eof_packet(session, status_flags).as_bytes())
 }
 
-fn send_packet(stream: &mut TcpStream, data: &[u8], packet_num: u8) -> Result<(), std::io::Error> {
-    // Header: length (3 bytes, little-endian) followed by a packet number
-    let packet_len = data.len() as u32;
-    if packet_len >= MAX_PACKET_SIZE {
-        return Err(std::io::ErrorKind::Unsupported.into());
+// column count and definitions of a cursor, closed by an EOF, or an OK with CLIENT_DEPRECATE_EOF,
+// that tells the client a cursor is open
+fn send_cursor_metadata(
+    conn: &mut Connection,
+    session: &mut Session,
+    columns: &[ColumnDefinition41],
+    status_flags: u16,
+) -> Result<(), std::io::Error> {
+    conn.write_packet(&Packet::ColumnCount(columns.len() as u64).as_bytes())?;
+
+    for column in columns {
+        conn.write_packet(&column.as_bytes())?;
     }
-    let packet_len_bytes = packet_len.to_le_bytes();
-    let header = [packet_len_bytes[0], packet_len_bytes[1], packet_len_bytes[2], packet_num];
 
-    stream.write_all(&header)?;
-    stream.write_all(data)?;
-    stream.flush()?;
-    Ok(())
+    conn.write_packet(&eof_packet(session, status_flags).as_bytes())
 }
 
-fn read_packet(stream: &mut TcpStream) -> Result<Vec<u8>, std::io::Error> {
-    let mut header = [0u8; 4];
-    stream.read_exact(&mut header)?;
+// the next `num_rows` rows of an open cursor, the cursor is closed once the last one is sent
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_fetch.html
+fn fetch_rows(conn: &mut Connection, session: &mut Session, stmt_id: u32, num_rows: usize) -> Result<(), std::io::Error> {
+    let mut status_flags = session.status_flags() | SERVER_STATUS_CURSOR_EXISTS;
+    let Some(prepared) = session.statement_mut(stmt_id) else {
+        let error = ServerError::unknown_stmt_handler(stmt_id, "mysqld_stmt_fetch");
+        return conn.write_packet(&Packet::Error(error.into()).as_bytes());
+    };
+    let Some(cursor) = prepared.cursor.as_mut() else {
+        return conn.write_packet(&Packet::Error(ServerError::no_open_cursor(stmt_id).into()).as_bytes());
+    };
+
+    let rows = cursor.fetch(num_rows).iter().map(|row| row.as_binary_bytes(&cursor.columns)).collect::<Vec<_>>();
+    if !rows.iter().all(|row| conn.fits(row)) {
+        return conn.write_packet(&Packet::Error(ServerError::packet_too_large().into()).as_bytes());
+    }
+    for row in rows {
+        conn.write_packet(&row)?;
+    }
+
+    if cursor.is_exhausted() {
+        status_flags |= SERVER_STATUS_LAST_ROW_SENT;
+        prepared.cursor = None;
+    }
+
+    conn.write_packet(&eof_packet(session, status_flags).as_bytes())
+}
 
-    let packet_num = header[3];
-    // let packet_len = header[0] as usize + ((header[1] as usize) << 8) + ((header[2] as usize) << 16);
-    let packet_len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+// COM_STMT_PREPARE_OK followed by a definition per parameter and per result column
+// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_prepare.html#sect_protocol_com_stmt_prepare_response_ok
+fn prepare_statement(conn: &mut Connection, session: &mut Session, query: &str) -> Result<(), std::io::Error> {
+    let prepared = query::parse(query, session.database.as_deref()).and_then(|(statement, params)| {
+        if !statement.can_prepare() {
+            return Err(ServerError::unsupported_ps());
+        }
+        let columns = statement.columns(session)?;
+        Ok(PreparedStatement::new(statement, params, columns))
+    });
+
+    let prepared = match prepared {
+        Ok(prepared) => prepared,
+        Err(error) => return conn.write_packet(&Packet::Error(error.into()).as_bytes()),
+    };
+
+    let params = prepared.params;
+    let results = session.variables.results_collation();
+    let columns = prepared.columns.iter().map(|column| column.in_charset(results)).collect::<Vec<_>>();
+    let statement_id = session.prepare(prepared);
+
+    conn.write_packet(&Packet::PrepareOk { statement_id, columns: columns.len() as u16, params: params as u16 }.as_bytes())?;
+
+    if params > 0 {
+        let param = ColumnDefinition41::new("?", ColumnType::LongLong, 0).with_flags(BINARY_FLAG);
+        for _ in 0..params {
+            conn.write_packet(&param.as_bytes())?;
+        }
+        if !session.deprecate_eof() {
+            conn.write_packet(&Packet::Eof(session.status_flags()).as_bytes())?;
+        }
+    }
 
-    let mut buffer = vec![0; packet_len];
-    stream.read_exact(&mut buffer)?;
+    if !columns.is_empty() {
+        for column in &columns {
+            conn.write_packet(&column.as_bytes())?;
+        }
+        if !session.deprecate_eof() {
+            conn.write_packet(&Packet::Eof(session.status_flags()).as_bytes())?;
+        }
+    }
 
-    Ok(buffer)
+    Ok(())
 }
 
-fn handle_connection(mut stream: TcpStream) {
+fn handle_connection(stream: TcpStream) {
     let peer_addr = stream.peer_addr().unwrap_or_else(|_| "unknown".parse().unwrap());
+    let process = ProcessHandle::register(peer_addr.to_string(), stream.try_clone().ok());
+    let mut conn = Connection::new(stream);
 
     // Authentication
-    {
+    let handshake_response = {
         // Send binary greeting message
-        let greeting = &Packet::Greeting.as_bytes();
-        if let Err(e) = send_packet(&mut stream, greeting, 0) {
+        let greeting = &Packet::Greeting(greeting(process.id)).as_bytes();
+        if conn.write_packet(greeting).is_err() || conn.flush().is_err() {
             return;
         }
 
         // auth packet
-        let _ = read_packet(&mut stream);
+        let Ok(Ok(handshake_response)) = conn.read_packet().map(|data| HandshakeResponse::parse(&data)) else {
+            return;
+        };
+
+        // the schema has to exist before the client gets in
+        if let Some(database) = &handshake_response.database
+            && !catalog::database_exists(database)
+        {
+            let _ = conn.write_packet(&Packet::Error(ServerError::bad_database(database).into()).as_bytes());
+            let _ = conn.flush();
+            return;
+        }
 
         // auth success packet
-        if let Err(_) = send_packet(&mut stream, &Packet::AuthSuccess.as_bytes(), 2) {
+        if conn.write_packet(&Packet::AuthSuccess.as_bytes()).is_err() {
             return;
         }
 
         // ok packet
-        if let Err(_) = send_packet(&mut stream, &Packet::OK.as_bytes(), 3) {
+        if conn.write_packet(&Packet::OK(SERVER_STATUS_AUTOCOMMIT).as_bytes()).is_err() || conn.flush().is_err() {
             return;
         }
-    }
+
+        // everything after the handshake goes through the compressed protocol, if the client asked for it
+        conn.enable_compression(handshake_response.compression());
+        conn.set_max_allowed_packet(max_allowed_packet(&handshake_response));
+
+        handshake_response
+    };
+    process.update(|p| {
+        p.user = handshake_response.user.clone();
+        p.db = handshake_response.database.clone();
+    });
+    process.set_command("Sleep", None);
+    let mut session = Session::new(
+        process.id,
+        handshake_response.capabilities,
+        handshake_response.collation,
+        handshake_response.database,
+    );
 
     // Command State
     loop {
-        match read_packet(&mut stream) {
+        match conn.read_command() {
             Ok(data) => {
-                let command = Command::parse(data.as_slice());
+                let command = match Command::parse(data.as_slice(), session.capabilities, session.variables.client_charset()) {
+                    Ok(command) => command,
+                    Err(error) => {
+                        let _ = conn.write_packet(&Packet::Error(ServerError::from(error).into()).as_bytes());
+                        if conn.flush().is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
 
                 match command {
                     Command::Ping => {
                         // ok packet
-                        let _ = send_packet(&mut stream, &Packet::OK.as_bytes(), 1);
+                        let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
                     }
-                    Command::CloseStmt(_) => {
-                        continue
+                    Command::CloseStmt(stmt_id) => {
+                        // no response, not even on an unknown statement
+                        session.close_statement(stmt_id);
+                        continue;
                     }
-                    Command::Quit => {
-                        return;
+                    Command::SendLongData(stmt_id, param_id, data) => {
+                        // no response, errors would only show up on the next execution, so data for
+                        // a parameter the statement doesn't have is dropped
+                        if let Some(prepared) = session.statement_mut(stmt_id).filter(|prepared| (param_id as usize) < prepared.params) {
+                            prepared.long_data.entry(param_id).or_default().extend(data);
+                        }
+                        continue;
                     }
-                    Command::PrepareStmt(query) => {
-                        match query.as_str() {
-                            "select 123 as id" => {
-                                let _ = send_packet(&mut stream, &Packet::PrepareOk.as_bytes(), 1);
-                                let _ = send_packet(&mut stream, &Packet::SimpleFieldPacket.as_bytes(), 2);
-                                let _ = send_packet(&mut stream, &Packet::Eof.as_bytes(), 3);
+                    Command::FetchStmt(stmt_id, num_rows) => {
+                        if let Err(e) = fetch_rows(&mut conn, &mut session, stmt_id, num_rows as usize) {
+                            send_protocol_error(&mut conn, &e);
+                            return;
+                        }
+                    }
+                    Command::ResetStmt(stmt_id) => {
+                        match session.statement_mut(stmt_id) {
+                            Some(prepared) => {
+                                prepared.long_data.clear();
+                                prepared.cursor = None;
+                                let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
                             }
-                            _ => {
-                                println!("Not supported: {}", query);
-                                return;
+                            None => {
+                                let error = ServerError::unknown_stmt_handler(stmt_id, "mysqld_stmt_reset");
+                                let _ = conn.write_packet(&Packet::Error(error.into()).as_bytes());
                             }
                         }
                     }
-                    Command::ExecuteStmt(stmt_id, _, _) => {
-                        match stmt_id {
-                            1 => {
-                                let _ = send_packet(&mut stream, &Packet::ColumnCount(1).as_bytes(), 1);
-                                let _ = send_packet(&mut stream, &Packet::SimpleFieldPacket.as_bytes(), 2);
-                                let _ = send_packet(&mut stream, &Packet::Eof.as_bytes(), 3);
-                                let _ = send_packet(&mut stream, &Packet::PreparedRowPacket.as_bytes(), 4);
-                                let _ = send_packet(&mut stream, &Packet::Eof.as_bytes(), 5);
-
+                    Command::InitDb(database) => {
+                        if database.is_empty() {
+                            let _ = conn.write_packet(&Packet::Error(ServerError::no_database_selected().into()).as_bytes());
+                        } else if catalog::database_exists(&database) {
+                            process.update(|p| p.db = Some(database.clone()));
+                            session.set_database(Some(database));
+                            let status_flags = session.status_flags();
+                            let _ = conn.write_packet(&ok_packet(&mut session, status_flags, 0, 0, String::new()).as_bytes());
+                        } else {
+                            let _ = conn.write_packet(&Packet::Error(ServerError::bad_database(&database).into()).as_bytes());
+                        }
+                    }
+                    Command::ChangeUser(change_user) => {
+                        let change_user = match &change_user.database {
+                            Some(database) if !catalog::database_exists(database) => Err(ServerError::bad_database(database)),
+                            _ => Ok(change_user),
+                        };
+
+                        match change_user {
+                            Ok(change_user) => {
+                                // there are no passwords to check, re-authentication answers the same as the handshake
+                                if let Some(collation) = change_user.collation {
+                                    session.collation = collation;
+                                }
+                                session.reset();
+                                session.set_database(change_user.database);
+                                process.update(|p| {
+                                    p.user = change_user.user;
+                                    p.db = session.database.clone();
+                                });
+                                let _ = conn.write_packet(&Packet::AuthSuccess.as_bytes());
+                                let status_flags = session.status_flags();
+                                let _ = conn.write_packet(&ok_packet(&mut session, status_flags, 0, 0, String::new()).as_bytes());
                             }
-                            _ => {
-                                println!("Not supported statement id: {}", stmt_id);
-                                return;
+                            Err(error) => {
+                                let _ = conn.write_packet(&Packet::Error(error.into()).as_bytes());
                             }
                         }
                     }
-                    Command::Query(query) => {
-                        match query.as_str() {
-                            "select 123 as id" => {
-                                let _ = send_packet(&mut stream, &Packet::ColumnCount(1).as_bytes(), 1);
-                                let _ = send_packet(&mut stream, &Packet::SimpleFieldPacket.as_bytes(), 2);
-                                let _ = send_packet(&mut stream, &Packet::Eof.as_bytes(), 3);
-                                let _ = send_packet(&mut stream, &Packet::SimpleRowPacket.as_bytes(), 4);
-                                let _ = send_packet(&mut stream, &Packet::Eof.as_bytes(), 5);
+                    Command::ResetConnection => {
+                        session.reset();
+                        let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
+                    }
+                    Command::FieldList(table, wildcard) => {
+                        let _ = send_field_list(&mut conn, &mut session, &table, &wildcard);
+                    }
+                    Command::Statistics => {
+                        // a bare string, no OK header
+                        let _ = conn.write_packet(process::statistics().as_bytes());
+                    }
+                    Command::ProcessInfo => {
+                        let result_set = process::result_set().in_charset(session.variables.results_collation());
+                        let rows = result_set.rows.iter().map(Row::as_text_bytes).collect::<Vec<_>>();
+                        let status_flags = session.status_flags();
+                        let sent = send_result_set(&mut conn, &mut session, &result_set.columns, &rows, status_flags);
+                        if let Err(e) = sent {
+                            send_protocol_error(&mut conn, &e);
+                            return;
+                        }
+                    }
+                    Command::ProcessKill(id) => {
+                        if id == process.id {
+                            // the client gets its OK before the connection is closed under it
+                            let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
+                            let _ = conn.flush();
+                            return;
+                        } else if !process::kill(id) {
+                            let _ = conn.write_packet(&Packet::Error(ServerError::no_such_thread(id).into()).as_bytes());
+                        } else {
+                            let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
+                        }
+                    }
+                    Command::RegisterReplica(server_id) => {
+                        println!("Replica {server_id} registered from {peer_addr}");
+                        let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
+                    }
+                    Command::BinlogDump(dump) => {
+                        let mut dump = Dump::new(dump);
+                        process.set_command("Binlog Dump", None);
+                        let _ = send_binlog(&mut conn, &mut dump);
+                        // the dump takes over the connection, it's closed once the dump ends
+                        let _ = conn.flush();
+                        return;
+                    }
+                    Command::Debug => {
+                        // the real server dumps debug information to its error log
+                        let _ = conn.write_packet(&Packet::OK(session.status_flags()).as_bytes());
+                    }
+                    Command::SetOption(option) => {
+                        match option {
+                            MYSQL_OPTION_MULTI_STATEMENTS_ON => session.capabilities |= CLIENT_MULTI_STATEMENTS,
+                            MYSQL_OPTION_MULTI_STATEMENTS_OFF => session.capabilities &= !CLIENT_MULTI_STATEMENTS,
+                            _ => {
+                                let _ = conn.write_packet(&Packet::Error(ServerError::unknown_command().into()).as_bytes());
+                                if conn.flush().is_err() {
+                                    return;
+                                }
+                                continue;
                             }
-                            "select id, title, description, category_id from products" => {
-                                let _ = send_packet(&mut stream, &Packet::ColumnCount(4).as_bytes(), 1);
-                                let _ = send_packet(&mut stream, &Packet::IdFieldPacket.as_bytes(), 2);
-                                let _ = send_packet(&mut stream, &Packet::TitleFieldPacket.as_bytes(), 3);
-                                let _ = send_packet(&mut stream, &Packet::DescriptionFieldPacket.as_bytes(), 4);
-                                let _ = send_packet(&mut stream, &Packet::CategoryIdFieldPacket.as_bytes(), 5);
-                                let _ = send_packet(&mut stream, &Packet::ComplexEof.as_bytes(), 6);
-                                let _ = send_packet(&mut stream, &Packet::ComplexRow1Packet.as_bytes(), 7);
-                                let _ = send_packet(&mut stream, &Packet::ComplexRow2Packet.as_bytes(), 8);
-                                let _ = send_packet(&mut stream, &Packet::ComplexEof.as_bytes(), 9);
+                        }
 
+                        let status_flags = session.status_flags();
+                        let _ = conn.write_packet(&eof_packet(&mut session, status_flags).as_bytes());
+                    }
+                    Command::Quit => {
+                        return;
+                    }
+                    Command::PrepareStmt(query) => {
+                        let _ = prepare_statement(&mut conn, &mut session, &query);
+                    }
+                    Command::ExecuteStmt(stmt_id, flags, _, data) => {
+                        process::count_question();
+                        process.set_command("Execute", None);
+                        let query_attributes = session.query_attributes();
+                        let bound = match session.statement_mut(stmt_id) {
+                            Some(prepared) => {
+                                let mut reader = Reader::new(&data);
+                                let long_data = std::mem::take(&mut prepared.long_data);
+                                binary::read_params(&mut reader, flags, prepared.params, query_attributes, &mut prepared.param_types, &long_data)
+                                    .map(|mut params| {
+                                        // anything past the placeholders is a query attribute
+                                        let attributes = params.split_off(prepared.params.min(params.len()));
+                                        let values = params.into_iter().map(|(_, value)| value).collect::<Vec<_>>();
+                                        (prepared.statement.clone(), values, attributes)
+                                    })
                             }
-                            _ => {
-                                println!("Not supported: {}", query);
-                                return;
+                            None => Err(ServerError::unknown_stmt_handler(stmt_id, "mysqld_stmt_execute")),
+                        };
+                        let result = bound.and_then(|(statement, values, attributes)| {
+                            session.attributes = attributes;
+                            statement.execute(&values, &mut session)
+                        });
+                        session.attributes.clear();
+
+                        match result {
+                            Ok(QueryResult::Rows(result_set)) if flags & CURSOR_TYPE_READ_ONLY != 0 => {
+                                let result_set = result_set.in_charset(session.variables.results_collation());
+                                // only the metadata now, the rows come with COM_STMT_FETCH
+                                let status_flags = session.status_flags() | SERVER_STATUS_CURSOR_EXISTS;
+                                let _ = send_cursor_metadata(&mut conn, &mut session, &result_set.columns, status_flags);
+                                if let Some(prepared) = session.statement_mut(stmt_id) {
+                                    prepared.cursor = Some(Cursor::new(result_set));
+                                }
+                            }
+                            Ok(result) => {
+                                let status_flags = session.status_flags();
+                                if let Err(e) = send_query_result(&mut conn, &mut session, &result, status_flags, true) {
+                                    send_protocol_error(&mut conn, &e);
+                                    return;
+                                }
+                            }
+                            Err(error) => {
+                                let _ = conn.write_packet(&Packet::Error(error.into()).as_bytes());
                             }
                         }
+                        process.set_command("Sleep", None);
+                    }
+                    Command::Query(query, attributes) => {
+                        process::count_question();
+                        session.attributes = match binary::query_attributes(attributes) {
+                            Ok(attributes) => attributes,
+                            Err(error) => {
+                                let _ = conn.write_packet(&Packet::Error(error.into()).as_bytes());
+                                if conn.flush().is_err() {
+                                    return;
+                                }
+                                continue;
+                            }
+                        };
+                        process.set_command("Query", Some(&query));
+
+                        // without CLIENT_MULTI_STATEMENTS a `;` in the middle is a syntax error
+                        let statements = match query::split_statements(&query) {
+                            statements if session.multi_statements() && !statements.is_empty() => statements,
+                            _ => vec![query.as_str()],
+                        };
+
+                        // one response per statement, up to the first error
+                        for (i, sql) in statements.iter().enumerate() {
+                            let result = query::parse(sql, session.database.as_deref()).and_then(|(statement, _)| {
+                                Ok((statement.no_index_used(), statement.execute(&[], &mut session)?))
+                            });
+
+                            // LOAD DATA LOCAL goes on with the file transfer before there's a result
+                            let result = match result {
+                                Ok((_, QueryResult::LocalInfile(load))) => match read_local_infile(&mut conn, &load.file) {
+                                    Ok(contents) => load.load(&contents, &mut session).map(|loaded| (false, QueryResult::Loaded(loaded))),
+                                    Err(e) => {
+                                        // the rest of the file would be taken for commands
+                                        send_protocol_error(&mut conn, &e);
+                                        return;
+                                    }
+                                },
+                                result => result,
+                            };
+
+                            match result {
+                                Ok((no_index_used, result)) => {
+                                    let mut status_flags = session.status_flags();
+                                    if no_index_used {
+                                        status_flags |= SERVER_STATUS_NO_INDEX_USED;
+                                    }
+                                    if i + 1 < statements.len() {
+                                        status_flags |= SERVER_MORE_RESULTS_EXISTS;
+                                    }
+
+                                    if let Err(e) = send_query_result(&mut conn, &mut session, &result, status_flags, false) {
+                                        send_protocol_error(&mut conn, &e);
+                                        return;
+                                    }
+                                }
+                                Err(error) => {
+                                    let _ = conn.write_packet(&Packet::Error(error.into()).as_bytes());
+                                    break;
+                                }
+                            }
+                        }
+                        session.attributes.clear();
+                        process.update(|p| p.db = session.database.clone()); // after a USE
+                        process.set_command("Sleep", None);
                     }
                 }
 
+                if conn.flush().is_err() {
+                    return;
+                }
             }
             Err(e) => {
+                // packets out of order or too large, the client gets the reason before the connection is closed
+                send_protocol_error(&mut conn, &e);
+
                 if e.kind() == std::io::ErrorKind::UnexpectedEof {
                     println!("Client {} disconnected", peer_addr);
                 } else {
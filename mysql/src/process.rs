@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+use mysql_protocol::column::{
+    BINARY_FLAG, ColumnDefinition41, ColumnType, NOT_NULL_FLAG, UNSIGNED_FLAG, UTF8MB4_GENERAL_CI,
+};
+
+use crate::resultset::{ResultSet, Row, Value};
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+static PROCESSES: LazyLock<Mutex<BTreeMap<u32, Process>>> = LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+static QUESTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// A connection in the process list.
+#[derive(Debug)]
+pub struct Process {
+    pub id: u32,
+    pub user: String,
+    pub host: String,
+    pub db: Option<String>,
+    pub command: &'static str,
+    pub since: Instant, // start of the current command
+    pub info: Option<String>, // statement being executed
+    stream: Option<TcpStream>,
+}
+
+/// Registration of the current connection, it leaves the process list when dropped.
+pub struct ProcessHandle {
+    pub id: u32,
+}
+
+impl ProcessHandle {
+    // `stream` is a clone of the connection's socket, shut down to kill the connection
+    pub fn register(host: String, stream: Option<TcpStream>) -> Self {
+        LazyLock::force(&STARTED);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let process = Process {
+            id,
+            user: "unauthenticated user".to_string(),
+            host,
+            db: None,
+            command: "Connect",
+            since: Instant::now(),
+            info: None,
+            stream,
+        };
+        PROCESSES.lock().unwrap().insert(id, process);
+
+        ProcessHandle { id }
+    }
+
+    // the command, and the time spent in it, shown in the process list
+    pub fn set_command(&self, command: &'static str, info: Option<&str>) {
+        self.update(|p| {
+            p.command = command;
+            p.since = Instant::now();
+            p.info = info.map(str::to_string);
+        });
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut Process)) {
+        if let Some(process) = PROCESSES.lock().unwrap().get_mut(&self.id) {
+            f(process);
+        }
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        PROCESSES.lock().unwrap().remove(&self.id);
+    }
+}
+
+// snapshot of the process list ordered by id, without the sockets
+pub fn list() -> Vec<Process> {
+    PROCESSES.lock().unwrap().values()
+        .map(|p| Process {
+            id: p.id,
+            user: p.user.clone(),
+            host: p.host.clone(),
+            db: p.db.clone(),
+            command: p.command,
+            since: p.since,
+            info: p.info.clone(),
+            stream: None,
+        })
+        .collect()
+}
+
+// closes the connection's socket, its thread sees the end of the stream and exits
+pub fn kill(id: u32) -> bool {
+    match PROCESSES.lock().unwrap().get(&id) {
+        Some(process) => {
+            if let Some(stream) = &process.stream {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+// KILL QUERY, a no-op: statements run synchronously on the connection's own thread and are done by
+// the time another one can be read, so there's never a query to interrupt. Like `kill`, it's only
+// false for an id without a connection, which is ER_NO_SUCH_THREAD
+pub fn kill_query(id: u32) -> bool {
+    PROCESSES.lock().unwrap().contains_key(&id)
+}
+
+pub fn count_question() {
+    QUESTIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+// the COM_STATISTICS string
+pub fn statistics() -> String {
+    let uptime = STARTED.elapsed().as_secs();
+    let questions = QUESTIONS.load(Ordering::Relaxed);
+    let threads = PROCESSES.lock().unwrap().len();
+
+    format!(
+        "Uptime: {uptime}  Threads: {threads}  Questions: {questions}  Slow queries: 0  Opens: 0  Flush tables: 1  Open tables: {}  Queries per second avg: {:.3}",
+        crate::catalog::table_count(),
+        questions as f64 / uptime.max(1) as f64
+    )
+}
+
+// SHOW PROCESSLIST columns and rows, also the COM_PROCESS_INFO response
+pub fn result_set() -> ResultSet {
+    let text = |name, len| ColumnDefinition41::new(name, ColumnType::VarString, len).with_charset(UTF8MB4_GENERAL_CI);
+    let columns = vec![
+        ColumnDefinition41::new("Id", ColumnType::LongLong, 21).with_flags(NOT_NULL_FLAG | UNSIGNED_FLAG | BINARY_FLAG),
+        text("User", 128).with_flags(NOT_NULL_FLAG),
+        text("Host", 1020).with_flags(NOT_NULL_FLAG),
+        text("db", 256),
+        text("Command", 64).with_flags(NOT_NULL_FLAG),
+        ColumnDefinition41::new("Time", ColumnType::Long, 7).with_flags(NOT_NULL_FLAG | BINARY_FLAG),
+        text("State", 120),
+        text("Info", 400),
+    ];
+
+    let rows = list().into_iter()
+        .map(|p| {
+            Row(vec![
+                Value::UInt(p.id as u64),
+                p.user.as_str().into(),
+                p.host.as_str().into(),
+                p.db.as_deref().map_or(Value::Null, Value::from),
+                p.command.into(),
+                Value::Int(p.since.elapsed().as_secs() as i64),
+                if p.command == "Sleep" { "".into() } else { "executing".into() },
+                p.info.as_deref().map_or(Value::Null, Value::from),
+            ])
+        })
+        .collect();
+
+    ResultSet { columns, rows }
+}
@@ -0,0 +1,1312 @@
+use std::sync::Arc;
+
+use mysql_protocol::column::{
+    BINARY_CHARSET, BINARY_FLAG, ColumnDefinition41, ColumnType, NOT_NULL_FLAG, UNSIGNED_FLAG, UTF8MB4_GENERAL_CI,
+};
+
+use crate::binlog;
+use crate::catalog::{self, Table};
+use crate::error::ServerError;
+use crate::infile::{Format, LoadData, Loaded};
+use crate::process;
+use crate::resultset::{ResultSet, Row, Value};
+use crate::session::{PreparedStatement, Session};
+use crate::variables::{self, Scope};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String), // keywords, names and `quoted names`
+    Str(String),
+    Number(String),
+    Placeholder,
+    Symbol(char),
+}
+
+// each token with its offset in `sql`
+fn tokenize(sql: &str) -> Result<Vec<(usize, Token)>, ServerError> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if let Some(len) = comment_len(&sql[i..]) {
+            while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+            continue;
+        }
+
+        match c {
+            c if c.is_whitespace() => {}
+            '?' => tokens.push((i, Token::Placeholder)),
+            '\'' | '"' | '`' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ch)) if ch == c => {
+                            // doubled quote is an escaped quote
+                            if chars.peek().is_some_and(|&(_, next)| next == c) {
+                                chars.next();
+                                value.push(c);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some((_, '\\')) if c != '`' => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 'r')) => value.push('\r'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, 'b')) => value.push('\x08'),
+                            Some((_, 'Z')) => value.push('\x1a'),
+                            Some((_, '0')) => value.push('\0'),
+                            Some((_, ch)) => value.push(ch),
+                            None => return Err(ServerError::parse_error(&sql[i..])),
+                        },
+                        Some((_, ch)) => value.push(ch),
+                        None => return Err(ServerError::parse_error(&sql[i..])),
+                    }
+                }
+                tokens.push((i, if c == '`' { Token::Ident(value) } else { Token::Str(value) }));
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = c.to_string();
+                while let Some(&(_, ch)) = chars.peek() && (ch.is_ascii_digit() || ch == '.') {
+                    value.push(ch);
+                    chars.next();
+                }
+                tokens.push((i, Token::Number(value)));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut value = c.to_string();
+                while let Some(&(_, ch)) = chars.peek() && (ch.is_alphanumeric() || ch == '_' || ch == '$') {
+                    value.push(ch);
+                    chars.next();
+                }
+                tokens.push((i, Token::Ident(value)));
+            }
+            c => tokens.push((i, Token::Symbol(c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+// length of the `-- `, `#` or `/* */` comment `sql` starts with
+fn comment_len(sql: &str) -> Option<usize> {
+    if sql.starts_with('#') || sql == "--" || sql.starts_with("-- ") || sql.starts_with("--\t") || sql.starts_with("--\n") {
+        return Some(sql.find('\n').unwrap_or(sql.len()));
+    }
+    sql.strip_prefix("/*").map(|body| body.find("*/").map_or(sql.len(), |end| end + 4))
+}
+
+/// Splits a multi-statement query at the semicolons outside of quotes and comments,
+/// leaving out the blank statements.
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut i = 0;
+
+    while let Some(c) = sql[i..].chars().next() {
+        let mut len = c.len_utf8();
+        match quote {
+            Some(q) if c == '\\' && q != '`' => len += sql[i + 1..].chars().next().map_or(0, char::len_utf8),
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None if c == ';' => {
+                statements.push(&sql[start..i]);
+                start = i + 1;
+            }
+            None => len = comment_len(&sql[i..]).unwrap_or(len),
+        }
+        i += len;
+    }
+    statements.push(&sql[start..]);
+
+    statements.retain(|statement| !is_blank(statement));
+    statements
+}
+
+// nothing but whitespace and comments
+fn is_blank(sql: &str) -> bool {
+    let mut rest = sql.trim_start();
+    while !rest.is_empty() {
+        match comment_len(rest) {
+            Some(len) => rest = rest[len..].trim_start(),
+            None => return false,
+        }
+    }
+    true
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Function(String, Vec<Expr>), // name, arguments
+    Literal(Value),
+    Placeholder(usize), // index of the parameter
+    SystemVariable(String), // what follows `@@`, as written
+    UserVariable(String), // `@<name>`
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableName {
+    pub schema: String,
+    pub name: String,
+}
+
+/// `SELECT <items> [FROM <table>] [WHERE <column> = <expr>]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub items: Vec<SelectItem>,
+    pub from: Option<TableName>,
+    pub filter: Option<(String, Expr)>,
+}
+
+/// One assignment of a `SET` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assignment {
+    Variable { scope: Scope, name: String, value: Option<Expr> }, // `None` for DEFAULT
+    Names { charset: Option<String>, collation: Option<String> }, // `SET NAMES`, no charset for DEFAULT
+    UserVariable { name: String, value: Expr },
+    NextTransaction(Vec<(&'static str, &'static str)>), // `SET TRANSACTION` without a scope, variable and value
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Select),
+    ShowProcessList,
+    ShowBinaryLogStatus,
+    ShowVariables { scope: Scope, pattern: Option<String> },
+    Set(Vec<Assignment>),
+    Kill { id: u32, query: bool }, // `KILL QUERY` leaves the connection open
+    Call { procedure: TableName, args: Vec<Expr> },
+    LoadData(LoadData),
+    Use(String),
+    Prepare { name: String, source: Expr }, // the text as a string literal or a user variable
+    Execute { name: String, using: Vec<String> }, // user variables for the placeholders
+    Deallocate(String),
+}
+
+/// What a statement sends back, rows or only an OK.
+#[derive(Debug)]
+pub enum QueryResult {
+    Rows(ResultSet),
+    Done,
+    Call(Vec<ResultSet>), // one per statement of the procedure body, followed by an OK
+    LocalInfile(LoadData), // asks the client for the file, the result comes once it's loaded
+    Loaded(Loaded),
+    Prepared, // an OK saying so
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    database: Option<&'a str>, // schema of unqualified table names
+    tokens: Vec<Token>,
+    offsets: Vec<usize>, // where each token starts in `sql`
+    pos: usize,
+    params: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // near the token at `pos`, the one that doesn't fit, or the end of the statement
+    fn error(&self) -> ServerError {
+        let offset = self.offsets.get(self.pos).copied().unwrap_or(self.sql.len());
+        ServerError::parse_error(&self.sql[offset..])
+    }
+
+    // the token `next` returned isn't one that fits
+    fn unexpected(&mut self) -> ServerError {
+        self.pos -= 1;
+        self.error()
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ServerError> {
+        if self.keyword(keyword) { Ok(()) } else { Err(self.error()) }
+    }
+
+    fn string(&mut self) -> Result<String, ServerError> {
+        match self.next() {
+            Some(Token::Str(value)) => Ok(value),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ServerError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // `name` or `schema.name`
+    fn qualified_name(&mut self) -> Result<TableName, ServerError> {
+        let name = self.ident()?;
+        if self.symbol('.') {
+            return Ok(TableName { schema: name, name: self.ident()? });
+        }
+        let schema = self.database.ok_or_else(ServerError::no_database_selected)?;
+        Ok(TableName { schema: schema.to_string(), name })
+    }
+
+    fn table_name(&mut self) -> Result<TableName, ServerError> {
+        let table = self.qualified_name()?;
+        if !catalog::database_exists(&table.schema) {
+            return Err(ServerError::no_such_table(&table.schema, &table.name));
+        }
+        Ok(table)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ServerError> {
+        match self.next() {
+            Some(Token::Placeholder) => {
+                self.params += 1;
+                Ok(Expr::Placeholder(self.params - 1))
+            }
+            Some(Token::Number(n)) => match n.parse::<i64>() {
+                Ok(v) => Ok(Expr::Literal(Value::Int(v))),
+                Err(_) => Ok(Expr::Literal(Value::Bytes(n.into_bytes()))),
+            },
+            Some(Token::Symbol('-')) => {
+                // a run of them is counted rather than recursed into, `- - 1` is 1
+                let mut negative = true;
+                while self.symbol('-') {
+                    negative = !negative;
+                }
+                let operand = self.pos;
+                match self.expr()? {
+                    Expr::Literal(Value::Int(v)) => Ok(Expr::Literal(Value::Int(if negative { -v } else { v }))),
+                    Expr::Literal(Value::Bytes(v)) if negative => Ok(Expr::Literal(Value::Bytes([b"-".as_slice(), &v].concat()))),
+                    literal @ Expr::Literal(Value::Bytes(_)) => Ok(literal),
+                    _ => {
+                        self.pos = operand;
+                        Err(self.error())
+                    }
+                }
+            }
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::Bytes(s.into_bytes()))),
+            // `@@<name>`, `@@session.<name>` or `@@global.<name>`
+            Some(Token::Symbol('@')) if self.symbol('@') => {
+                let mut reference = self.ident()?;
+                if self.symbol('.') {
+                    reference = format!("{reference}.{}", self.ident()?);
+                }
+                Ok(Expr::SystemVariable(reference))
+            }
+            Some(Token::Symbol('@')) => Ok(Expr::UserVariable(self.name()?)),
+            Some(Token::Ident(name)) if name.eq_ignore_ascii_case("null") => Ok(Expr::Literal(Value::Null)),
+            Some(Token::Ident(name)) if self.symbol('(') => {
+                let Some(&(_, arity)) = FUNCTIONS.iter().find(|(f, _)| f.eq_ignore_ascii_case(&name)) else {
+                    self.pos -= 2; // back to the name
+                    return Err(self.error());
+                };
+                let args = self.args()?;
+                if args.len() != arity {
+                    return Err(ServerError::wrong_param_count(&name));
+                }
+                Ok(Expr::Function(name, args))
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Column(name)),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // comma separated expressions after `(`, up to the closing `)`
+    fn args(&mut self) -> Result<Vec<Expr>, ServerError> {
+        let mut args = Vec::new();
+        if self.symbol(')') {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if !self.symbol(',') {
+                break;
+            }
+        }
+        if !self.symbol(')') {
+            return Err(self.error());
+        }
+        Ok(args)
+    }
+
+    fn select(&mut self) -> Result<Select, ServerError> {
+        let mut items = Vec::new();
+        loop {
+            if self.symbol('*') {
+                items.push(SelectItem::Wildcard);
+            } else {
+                let expr = self.expr()?;
+                // `as` is optional, a bare identifier other than a keyword is an alias too
+                let implicit = matches!(self.peek(), Some(Token::Ident(_))) && !self.is_keyword("from") && !self.is_keyword("where");
+                let alias = if self.keyword("as") || implicit {
+                    Some(self.ident()?)
+                } else {
+                    None
+                };
+                items.push(SelectItem::Expr { expr, alias });
+            }
+
+            if !self.symbol(',') {
+                break;
+            }
+        }
+
+        let from = if self.keyword("from") { Some(self.table_name()?) } else { None };
+
+        let filter = if self.keyword("where") {
+            let column = self.ident()?;
+            if !self.symbol('=') {
+                return Err(self.error());
+            }
+            Some((column, self.expr()?))
+        } else {
+            None
+        };
+
+        Ok(Select { items, from, filter })
+    }
+
+    // SET [GLOBAL | SESSION] <name> = <value>, SET @@[global. | session.]<name> = <value>, SET @<name> = <value>,
+    // SET NAMES <charset> [COLLATE <collation>], SET [GLOBAL | SESSION] TRANSACTION <characteristic>, ...
+    fn set(&mut self) -> Result<Vec<Assignment>, ServerError> {
+        let mut assignments = Vec::new();
+        loop {
+            if self.keyword("names") {
+                let charset = if self.keyword("default") { None } else { Some(self.name()?) };
+                let collation = if self.keyword("collate") { Some(self.name()?) } else { None };
+                assignments.push(Assignment::Names { charset, collation });
+            } else if self.peek() == Some(&Token::Symbol('@')) && self.tokens.get(self.pos + 1) != Some(&Token::Symbol('@')) {
+                self.pos += 1;
+                let name = self.name()?;
+                self.assignment_operator()?;
+                assignments.push(Assignment::UserVariable { name, value: self.expr()? });
+            } else {
+                let scope = self.scope();
+                if self.keyword("transaction") {
+                    let characteristics = self.transaction_characteristics()?;
+                    // without a scope it's only for the next transaction
+                    match scope {
+                        Some(scope) => {
+                            for (name, value) in characteristics {
+                                let value = Some(Expr::Literal(Value::from(value)));
+                                assignments.push(Assignment::Variable { scope, name: name.to_string(), value });
+                            }
+                        }
+                        None => assignments.push(Assignment::NextTransaction(characteristics)),
+                    }
+                } else {
+                    let (scope, name) = match scope {
+                        Some(scope) => (scope, self.ident()?),
+                        None if self.symbol('@') => {
+                            self.symbol('@');
+                            let name = self.ident()?;
+                            if self.symbol('.') {
+                                let scope = if name.eq_ignore_ascii_case("global") {
+                                    Scope::Global
+                                } else if name.eq_ignore_ascii_case("session") || name.eq_ignore_ascii_case("local") {
+                                    Scope::Session
+                                } else {
+                                    self.pos -= 2; // back to the scope
+                                    return Err(self.error());
+                                };
+                                (scope, self.ident()?)
+                            } else {
+                                (Scope::Session, name)
+                            }
+                        }
+                        None => (Scope::Session, self.ident()?),
+                    };
+
+                    self.assignment_operator()?;
+                    let value = if self.keyword("default") { None } else { Some(self.expr()?) };
+                    assignments.push(Assignment::Variable { scope, name, value });
+                }
+            }
+
+            if !self.symbol(',') {
+                break;
+            }
+        }
+        Ok(assignments)
+    }
+
+    // `=` or `:=`
+    fn assignment_operator(&mut self) -> Result<(), ServerError> {
+        if self.symbol('=') || (self.symbol(':') && self.symbol('=')) { Ok(()) } else { Err(self.error()) }
+    }
+
+    // ISOLATION LEVEL <level> | READ WRITE | READ ONLY, ..., as the variables they set
+    fn transaction_characteristics(&mut self) -> Result<Vec<(&'static str, &'static str)>, ServerError> {
+        let mut characteristics = Vec::new();
+        loop {
+            if self.keyword("isolation") {
+                self.expect_keyword("level")?;
+                let level = if self.keyword("serializable") {
+                    "SERIALIZABLE"
+                } else if self.keyword("repeatable") {
+                    self.expect_keyword("read")?;
+                    "REPEATABLE-READ"
+                } else {
+                    self.expect_keyword("read")?;
+                    if self.keyword("committed") {
+                        "READ-COMMITTED"
+                    } else {
+                        self.expect_keyword("uncommitted")?;
+                        "READ-UNCOMMITTED"
+                    }
+                };
+                characteristics.push(("transaction_isolation", level));
+            } else {
+                self.expect_keyword("read")?;
+                let read_only = if self.keyword("only") {
+                    "ON"
+                } else {
+                    self.expect_keyword("write")?;
+                    "OFF"
+                };
+                characteristics.push(("transaction_read_only", read_only));
+            }
+
+            // commas separate the characteristics as well as the assignments
+            if !(self.peek() == Some(&Token::Symbol(',')) && matches!(self.tokens.get(self.pos + 1), Some(Token::Ident(word))
+                if word.eq_ignore_ascii_case("isolation") || word.eq_ignore_ascii_case("read")))
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(characteristics)
+    }
+
+    // GLOBAL, or SESSION and its synonym LOCAL
+    fn scope(&mut self) -> Option<Scope> {
+        if self.keyword("global") {
+            Some(Scope::Global)
+        } else if self.keyword("session") || self.keyword("local") {
+            Some(Scope::Session)
+        } else {
+            None
+        }
+    }
+
+    // a charset, collation or user variable name, quoted or not
+    fn name(&mut self) -> Result<String, ServerError> {
+        match self.next() {
+            Some(Token::Ident(name) | Token::Str(name)) => Ok(name),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // LOAD DATA [LOW_PRIORITY | CONCURRENT] LOCAL INFILE '<file>' [REPLACE | IGNORE] INTO TABLE <table>
+    //     [CHARACTER SET <charset>] [FIELDS ...] [LINES ...] [IGNORE <n> LINES] [(<column>, ...)]
+    fn load_data(&mut self) -> Result<LoadData, ServerError> {
+        self.expect_keyword("data")?;
+        let _ = self.keyword("low_priority") || self.keyword("concurrent");
+        if !self.keyword("local") {
+            return Err(ServerError::secure_file_priv());
+        }
+        self.expect_keyword("infile")?;
+        let file = self.string()?;
+
+        // duplicates are skipped unless REPLACE, IGNORE is implied by LOCAL
+        let replace = self.keyword("replace");
+        if !replace {
+            self.keyword("ignore");
+        }
+        self.expect_keyword("into")?;
+        self.expect_keyword("table")?;
+        let table = self.table_name()?;
+
+        // the file is read in the connection's character set anyway
+        if self.keyword("character") {
+            self.expect_keyword("set")?;
+            self.ident()?;
+        }
+
+        // ENCLOSED BY and ESCAPED BY take a single character, or none
+        let single = |value: String| match value.as_bytes() {
+            [] => Ok(None),
+            [c] => Ok(Some(*c)),
+            _ => Err(ServerError::wrong_field_terminators()),
+        };
+
+        let mut format = Format::default();
+        if self.keyword("fields") || self.keyword("columns") {
+            if self.keyword("terminated") {
+                self.expect_keyword("by")?;
+                format.fields_terminated_by = self.string()?.into_bytes();
+            }
+            // quotes are taken off either way
+            let optionally = self.keyword("optionally");
+            if optionally || self.is_keyword("enclosed") {
+                self.expect_keyword("enclosed")?;
+                self.expect_keyword("by")?;
+                format.enclosed_by = single(self.string()?)?;
+            }
+            if self.keyword("escaped") {
+                self.expect_keyword("by")?;
+                format.escaped_by = single(self.string()?)?;
+            }
+        }
+        if self.keyword("lines") {
+            if self.keyword("starting") {
+                self.expect_keyword("by")?;
+                format.lines_starting_by = self.string()?.into_bytes();
+            }
+            if self.keyword("terminated") {
+                self.expect_keyword("by")?;
+                format.lines_terminated_by = self.string()?.into_bytes();
+            }
+        }
+
+        let mut ignore_lines = 0;
+        if self.keyword("ignore") {
+            ignore_lines = match self.next() {
+                Some(Token::Number(n)) => n.parse().map_err(|_| self.unexpected())?,
+                _ => return Err(self.unexpected()),
+            };
+            if !self.keyword("lines") {
+                self.expect_keyword("rows")?;
+            }
+        }
+
+        let mut columns = Vec::new();
+        if self.symbol('(') {
+            loop {
+                columns.push(self.ident()?);
+                if !self.symbol(',') {
+                    break;
+                }
+            }
+            if !self.symbol(')') {
+                return Err(self.error());
+            }
+        }
+
+        Ok(LoadData { file, table, replace, format, ignore_lines, columns })
+    }
+
+    fn statement(&mut self) -> Result<Statement, ServerError> {
+        let statement = if self.keyword("select") {
+            Statement::Select(self.select()?)
+        } else if self.keyword("show") {
+            if self.keyword("master") {
+                // the name before 8.2
+                self.expect_keyword("status")?;
+                Statement::ShowBinaryLogStatus
+            } else if self.keyword("binary") {
+                self.expect_keyword("log")?;
+                self.expect_keyword("status")?;
+                Statement::ShowBinaryLogStatus
+            } else if let Some(scope) = self.scope().or(self.is_keyword("variables").then_some(Scope::Session)) {
+                self.expect_keyword("variables")?;
+                let pattern = if self.keyword("like") { Some(self.string()?) } else { None };
+                Statement::ShowVariables { scope, pattern }
+            } else {
+                // FULL only makes a difference for long Info values, there are none
+                self.keyword("full");
+                if !self.keyword("processlist") {
+                    return Err(self.error());
+                }
+                Statement::ShowProcessList
+            }
+        } else if self.keyword("set") {
+            Statement::Set(self.set()?)
+        } else if self.keyword("kill") {
+            let query = self.keyword("query");
+            if !query {
+                self.keyword("connection");
+            }
+            match self.next() {
+                Some(Token::Number(id)) => Statement::Kill { id: id.parse().map_err(|_| self.unexpected())?, query },
+                _ => return Err(self.unexpected()),
+            }
+        } else if self.keyword("call") {
+            let procedure = self.qualified_name()?;
+            // the parentheses are optional without arguments
+            let args = if self.symbol('(') { self.args()? } else { Vec::new() };
+            Statement::Call { procedure, args }
+        } else if self.keyword("load") {
+            Statement::LoadData(self.load_data()?)
+        } else if self.keyword("use") {
+            Statement::Use(self.ident()?)
+        } else if self.keyword("prepare") {
+            let name = self.ident()?;
+            self.expect_keyword("from")?;
+            let start = self.pos;
+            let source = self.expr()?;
+            if !matches!(source, Expr::Literal(Value::Bytes(_)) | Expr::UserVariable(_)) {
+                self.pos = start;
+                return Err(self.error());
+            }
+            Statement::Prepare { name, source }
+        } else if self.keyword("execute") {
+            let name = self.ident()?;
+            let mut using = Vec::new();
+            if self.keyword("using") {
+                loop {
+                    let start = self.pos;
+                    match self.expr()? {
+                        Expr::UserVariable(variable) => using.push(variable),
+                        _ => {
+                            self.pos = start;
+                            return Err(self.error());
+                        }
+                    }
+                    if !self.symbol(',') {
+                        break;
+                    }
+                }
+            }
+            Statement::Execute { name, using }
+        } else if self.keyword("deallocate") || self.keyword("drop") {
+            self.expect_keyword("prepare")?;
+            Statement::Deallocate(self.ident()?)
+        } else {
+            return Err(self.error());
+        };
+
+        self.symbol(';');
+        if self.peek().is_some() {
+            return Err(self.error());
+        }
+
+        Ok(statement)
+    }
+}
+
+/// Parses a single statement, returns it with the number of `?` placeholders.
+///
+/// There's no real SQL parser behind the server, only the statement shapes it can answer.
+/// `database` is the current schema, if any.
+pub fn parse(sql: &str, database: Option<&str>) -> Result<(Statement, usize), ServerError> {
+    let (offsets, tokens) = tokenize(sql)?.into_iter().unzip();
+    let mut parser = Parser { sql, database, tokens, offsets, pos: 0, params: 0 };
+    let statement = parser.statement()?;
+    Ok((statement, parser.params))
+}
+
+// functions that can be called in a select list, with their number of arguments
+const FUNCTIONS: [(&str, usize); 4] = [
+    ("connection_id", 0),
+    ("database", 0),
+    ("schema", 0),
+    ("mysql_query_attribute_string", 1),
+];
+
+impl Statement {
+    // columns of the result, empty when the statement only gets an OK
+    pub fn columns(&self, session: &Session) -> Result<Vec<ColumnDefinition41>, ServerError> {
+        match self {
+            Statement::Select(select) => select.columns(session),
+            Statement::ShowProcessList => Ok(process::result_set().columns),
+            Statement::ShowBinaryLogStatus => Ok(binlog::status().columns),
+            Statement::ShowVariables { scope, .. } => Ok(variables::show(&session.variables, *scope, None).columns),
+            Statement::Set(_)
+            | Statement::Kill { .. }
+            | Statement::Call { .. }
+            | Statement::LoadData(_)
+            | Statement::Use(_)
+            | Statement::Prepare { .. }
+            | Statement::Execute { .. }
+            | Statement::Deallocate(_) => Ok(Vec::new()),
+        }
+    }
+
+    pub fn execute(&self, params: &[Value], session: &mut Session) -> Result<QueryResult, ServerError> {
+        // SET and the statements that handle prepared ones aren't transactions, EXECUTE runs one
+        if !matches!(self, Statement::Set(_) | Statement::Prepare { .. } | Statement::Execute { .. } | Statement::Deallocate(_)) {
+            session.end_transaction();
+        }
+
+        match self {
+            Statement::Select(select) => select.execute(params, session).map(QueryResult::Rows),
+            Statement::ShowProcessList => Ok(QueryResult::Rows(process::result_set())),
+            Statement::ShowBinaryLogStatus => Ok(QueryResult::Rows(binlog::status())),
+            Statement::ShowVariables { scope, pattern } => {
+                Ok(QueryResult::Rows(variables::show(&session.variables, *scope, pattern.as_deref())))
+            }
+            Statement::Set(assignments) => {
+                assign(assignments, params, session)?;
+                Ok(QueryResult::Done)
+            }
+            Statement::Kill { id, query } => {
+                let found = if *query { process::kill_query(*id) } else { process::kill(*id) };
+                if !found {
+                    return Err(ServerError::no_such_thread(*id));
+                }
+                Ok(QueryResult::Done)
+            }
+            Statement::Call { procedure, args } => call_procedure(procedure, args, params, session),
+            Statement::LoadData(load) => {
+                load.check(session)?;
+                Ok(QueryResult::LocalInfile(load.clone()))
+            }
+            Statement::Use(database) => {
+                if !catalog::database_exists(database) {
+                    return Err(ServerError::bad_database(database));
+                }
+                session.set_database(Some(database.clone()));
+                Ok(QueryResult::Done)
+            }
+            Statement::Prepare { name, source } => {
+                let sql = eval_constant(source, params, session)?.as_text().unwrap_or_default();
+                let (statement, params) = parse(&String::from_utf8_lossy(&sql), session.database.as_deref())?;
+                if !statement.can_prepare() {
+                    return Err(ServerError::unsupported_ps());
+                }
+                let columns = statement.columns(session)?;
+                session.prepare_named(name, PreparedStatement::new(statement, params, columns));
+                Ok(QueryResult::Prepared)
+            }
+            Statement::Execute { name, using } => {
+                let prepared = session.named_statement(name).ok_or_else(|| ServerError::unknown_stmt_handler(name, "EXECUTE"))?;
+                if using.len() != prepared.params {
+                    return Err(ServerError::wrong_arguments("EXECUTE"));
+                }
+                let statement = prepared.statement.clone();
+                let values = using.iter().map(|variable| session.user_variable(variable)).collect::<Vec<_>>();
+                statement.execute(&values, session)
+            }
+            Statement::Deallocate(name) => {
+                if !session.deallocate(name) {
+                    return Err(ServerError::unknown_stmt_handler(name, "DEALLOCATE PREPARE"));
+                }
+                Ok(QueryResult::Done)
+            }
+        }
+    }
+
+    // LOAD DATA LOCAL needs the text protocol, and statements can't prepare statements
+    pub fn can_prepare(&self) -> bool {
+        !matches!(self, Statement::LoadData(_) | Statement::Prepare { .. } | Statement::Execute { .. } | Statement::Deallocate(_))
+    }
+
+    // a full table scan, reported with SERVER_STATUS_NO_INDEX_USED
+    pub fn no_index_used(&self) -> bool {
+        matches!(self, Statement::Select(select) if select.from.is_some() && select.filter.is_none())
+    }
+}
+
+// where the values of a result column come from
+enum Source<'a> {
+    TableColumn(usize),
+    Expr(&'a Expr),
+}
+
+impl Select {
+    fn table(&self, session: &Session) -> Result<Option<Arc<Table>>, ServerError> {
+        match &self.from {
+            Some(from) => catalog::table(&from.schema, &from.name, session)
+                .map(Some)
+                .ok_or_else(|| ServerError::no_such_table(&from.schema, &from.name)),
+            None => Ok(None),
+        }
+    }
+
+    fn resolve(&self, table: Option<&Table>) -> Result<Vec<(ColumnDefinition41, Source<'_>)>, ServerError> {
+        let column_index = |name: &str| {
+            table
+                .and_then(|t| t.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name)))
+                .ok_or_else(|| ServerError::bad_field(name))
+        };
+
+        let mut columns = Vec::new();
+        for item in &self.items {
+            match item {
+                SelectItem::Wildcard => {
+                    let table = table.ok_or_else(ServerError::no_tables_used)?;
+                    for (i, column) in table.columns.iter().enumerate() {
+                        columns.push((column.clone(), Source::TableColumn(i)));
+                    }
+                }
+                SelectItem::Expr { expr: Expr::Column(name), alias } => {
+                    let i = column_index(name)?;
+                    let mut column = table.unwrap().columns[i].clone();
+                    if let Some(alias) = alias {
+                        column.name = alias.clone();
+                    }
+                    columns.push((column, Source::TableColumn(i)));
+                }
+                SelectItem::Expr { expr, alias } => {
+                    let name = alias.clone().unwrap_or_else(|| expr_name(expr));
+                    columns.push((expr_column(&name, expr), Source::Expr(expr)));
+                }
+            }
+        }
+
+        Ok(columns)
+    }
+
+    pub fn columns(&self, session: &Session) -> Result<Vec<ColumnDefinition41>, ServerError> {
+        let table = self.table(session)?;
+        Ok(self.resolve(table.as_deref())?.into_iter().map(|(column, _)| column).collect())
+    }
+
+    pub fn execute(&self, params: &[Value], session: &Session) -> Result<ResultSet, ServerError> {
+        let table = self.table(session)?;
+        let table = table.as_deref();
+        let resolved = self.resolve(table)?;
+
+        let eval = |expr: &Expr, row: Option<&Row>| -> Result<Value, ServerError> {
+            match expr {
+                Expr::Column(name) => {
+                    let table = table.ok_or_else(|| ServerError::bad_field(name))?;
+                    let i = table.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| ServerError::bad_field(name))?;
+                    Ok(row.map_or(Value::Null, |row| row.0[i].clone()))
+                }
+                expr => eval_constant(expr, params, session),
+            }
+        };
+
+        let project = |row: Option<&Row>| -> Result<Row, ServerError> {
+            let values = resolved.iter()
+                .map(|(_, source)| match source {
+                    Source::TableColumn(i) => Ok(row.map_or(Value::Null, |row| row.0[*i].clone())),
+                    Source::Expr(expr) => eval(expr, row),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Row(values))
+        };
+
+        let mut rows = Vec::new();
+        match table {
+            Some(table) => {
+                for row in &table.rows {
+                    if let Some((column, expr)) = &self.filter
+                        && !eval(&Expr::Column(column.clone()), Some(row))?.sql_eq(&eval(expr, Some(row))?)
+                    {
+                        continue;
+                    }
+                    rows.push(project(Some(row))?);
+                }
+            }
+            None => {
+                if self.filter.is_some() {
+                    return Err(ServerError::no_tables_used());
+                }
+                rows.push(project(None)?);
+            }
+        }
+
+        // a placeholder takes the type of the value bound to it, a user variable that of its value
+        let columns = resolved.into_iter()
+            .map(|(column, source)| match source {
+                Source::Expr(Expr::Placeholder(i)) => params.get(*i).map_or(column.clone(), |value| param_column(&column.name, value)),
+                Source::Expr(Expr::UserVariable(name)) => match session.user_variable(name) {
+                    Value::Bytes(_) => ColumnDefinition41::new(&column.name, ColumnType::LongBlob, 0).with_charset(UTF8MB4_GENERAL_CI),
+                    value => param_column(&column.name, &value),
+                },
+                _ => column,
+            })
+            .collect();
+
+        Ok(ResultSet { columns, rows })
+    }
+}
+
+// an expression that doesn't depend on a row
+fn eval_constant(expr: &Expr, params: &[Value], session: &Session) -> Result<Value, ServerError> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Placeholder(i) => Ok(params.get(*i).cloned().unwrap_or(Value::Null)),
+        Expr::Function(name, args) => {
+            let args = args.iter().map(|arg| eval_constant(arg, params, session)).collect::<Result<Vec<_>, _>>()?;
+            Ok(call(name, &args, session))
+        }
+        Expr::SystemVariable(reference) => session.variables.get(reference),
+        Expr::UserVariable(name) => Ok(session.user_variable(name)),
+        Expr::Column(name) => Err(ServerError::bad_field(name)),
+    }
+}
+
+// every value is checked before any is assigned
+fn assign(assignments: &[Assignment], params: &[Value], session: &mut Session) -> Result<(), ServerError> {
+    let mut settings = Vec::new();
+    let mut user_variables = Vec::new();
+    let mut next_transaction = Vec::new();
+    for assignment in assignments {
+        match assignment {
+            Assignment::Variable { scope, name, value } => {
+                // `SET autocommit = ON`, a bare word is the value itself
+                let value = match value {
+                    Some(Expr::Column(word)) => Some(Value::from(word.as_str())),
+                    Some(expr) => Some(eval_constant(expr, params, session)?),
+                    None => None,
+                };
+                settings.push(session.variables.check(*scope, name, value.as_ref())?);
+            }
+            Assignment::Names { charset, collation } => {
+                settings.extend(session.variables.check_names(charset.as_deref(), collation.as_deref())?);
+            }
+            Assignment::UserVariable { name, value } => {
+                user_variables.push((name.clone(), eval_constant(value, params, session)?));
+            }
+            Assignment::NextTransaction(characteristics) => next_transaction.extend(characteristics),
+        }
+    }
+
+    session.set_variables(settings);
+    for (name, value) in user_variables {
+        session.set_user_variable(&name, value);
+    }
+    if !next_transaction.is_empty() {
+        session.set_next_transaction(next_transaction);
+    }
+    Ok(())
+}
+
+// runs the body statements with the arguments bound to their placeholders
+fn call_procedure(name: &TableName, args: &[Expr], params: &[Value], session: &mut Session) -> Result<QueryResult, ServerError> {
+    let procedure = catalog::procedure(&name.schema, &name.name)
+        .ok_or_else(|| ServerError::procedure_does_not_exist(&name.schema, &name.name))?;
+    if args.len() != procedure.params {
+        return Err(ServerError::procedure_wrong_args(&name.schema, procedure.name, procedure.params, args.len()));
+    }
+    if !session.multi_results() {
+        return Err(ServerError::procedure_bad_select(&name.name));
+    }
+
+    let args = args.iter().map(|arg| eval_constant(arg, params, session)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut result_sets = Vec::new();
+    for sql in procedure.body {
+        let (statement, _) = parse(sql, Some(&name.schema))?;
+        if let QueryResult::Rows(result_set) = statement.execute(&args, session)? {
+            result_sets.push(result_set);
+        }
+    }
+
+    Ok(QueryResult::Call(result_sets))
+}
+
+// the parser only lets FUNCTIONS through, with the right number of arguments
+fn call(function: &str, args: &[Value], session: &Session) -> Value {
+    match function.to_ascii_lowercase().as_str() {
+        "connection_id" => Value::UInt(session.connection_id as u64),
+        "database" | "schema" => session.database.as_deref().map_or(Value::Null, Value::from),
+        // NULL for an attribute the client didn't send
+        "mysql_query_attribute_string" => {
+            let name = args[0].as_text().unwrap_or_default();
+            session.attributes.iter()
+                .find(|(attribute, _)| attribute.as_bytes() == name)
+                .and_then(|(_, value)| value.as_text())
+                .map_or(Value::Null, Value::Bytes)
+        }
+        _ => Value::Null,
+    }
+}
+
+// MySQL names a computed column after its expression text
+fn expr_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(Value::Null) => "NULL".to_string(),
+        Expr::Literal(value) => String::from_utf8_lossy(&value.as_text().unwrap_or_default()).to_string(),
+        Expr::Placeholder(_) => "?".to_string(),
+        Expr::Function(name, args) => {
+            let args = args.iter()
+                .map(|arg| match arg {
+                    Expr::Literal(Value::Bytes(text)) => format!("'{}'", String::from_utf8_lossy(text)),
+                    arg => expr_name(arg),
+                })
+                .collect::<Vec<_>>();
+            format!("{name}({})", args.join(","))
+        }
+        Expr::SystemVariable(reference) => format!("@@{reference}"),
+        Expr::UserVariable(name) => format!("@{name}"),
+        Expr::Column(name) => name.clone(),
+    }
+}
+
+fn param_column(name: &str, value: &Value) -> ColumnDefinition41 {
+    let (column_type, flags) = match value {
+        Value::Null => (ColumnType::Null, 0),
+        Value::Int(_) => (ColumnType::LongLong, 0),
+        Value::UInt(_) => (ColumnType::LongLong, UNSIGNED_FLAG),
+        Value::Float(_) => (ColumnType::Float, 0),
+        Value::Double(_) => (ColumnType::Double, 0),
+        Value::Date(_) => (ColumnType::Date, 0),
+        Value::DateTime(_) => (ColumnType::DateTime, 0),
+        Value::Time(_) => (ColumnType::Time, 0),
+        Value::Bytes(_) => return ColumnDefinition41::new(name, ColumnType::VarString, 0).with_charset(BINARY_CHARSET),
+    };
+
+    ColumnDefinition41::new(name, column_type, 0).with_flags(flags | BINARY_FLAG)
+}
+
+fn expr_column(name: &str, expr: &Expr) -> ColumnDefinition41 {
+    match expr {
+        Expr::Literal(Value::Null) => {
+            ColumnDefinition41::new(name, ColumnType::Null, 0).with_flags(BINARY_FLAG)
+        }
+        // display width with the sign
+        Expr::Literal(Value::Int(v)) => {
+            ColumnDefinition41::new(name, ColumnType::LongLong, v.unsigned_abs().to_string().len() as u32 + 1)
+                .with_flags(NOT_NULL_FLAG | BINARY_FLAG)
+        }
+        Expr::Literal(Value::Bytes(v)) => {
+            ColumnDefinition41::new(name, ColumnType::VarString, v.len() as u32 * 4)
+                .with_charset(UTF8MB4_GENERAL_CI)
+                .with_flags(NOT_NULL_FLAG)
+        }
+        Expr::Function(function, _) if function.eq_ignore_ascii_case("connection_id") => {
+            ColumnDefinition41::new(name, ColumnType::LongLong, 21).with_flags(NOT_NULL_FLAG | UNSIGNED_FLAG | BINARY_FLAG)
+        }
+        Expr::Function(..) => {
+            ColumnDefinition41::new(name, ColumnType::VarString, 256).with_charset(UTF8MB4_GENERAL_CI)
+        }
+        Expr::SystemVariable(reference) => variables::column(name, reference),
+        Expr::Literal(_) | Expr::Placeholder(_) | Expr::UserVariable(_) | Expr::Column(_) => {
+            ColumnDefinition41::new(name, ColumnType::VarString, 0).with_charset(BINARY_CHARSET)
+        }
+    }
+}
+
+// `LIKE` pattern, `%` matches any run of characters and `_` a single one, case-insensitive
+pub fn like(value: &str, pattern: &str) -> bool {
+    fn matches(value: &[char], pattern: &[char]) -> bool {
+        match pattern.split_first() {
+            None => value.is_empty(),
+            Some(('%', rest)) => (0..=value.len()).any(|i| matches(&value[i..], rest)),
+            Some(('\\', [c, rest @ ..])) => value.first() == Some(c) && matches(&value[1..], rest),
+            Some(('_', rest)) => !value.is_empty() && matches(&value[1..], rest),
+            Some((c, rest)) => value.first() == Some(c) && matches(&value[1..], rest),
+        }
+    }
+
+    let value = value.to_lowercase().chars().collect::<Vec<_>>();
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    matches(&value, &pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(sql: &str) -> String {
+        parse(sql, None).unwrap_err().message
+    }
+
+    fn near(rest: &str) -> String {
+        ServerError::parse_error(rest).message
+    }
+
+    // the expression of `SELECT <expr>`
+    fn selected(sql: &str) -> Expr {
+        match parse(sql, None).unwrap().0 {
+            Statement::Select(Select { mut items, .. }) => match items.remove(0) {
+                SelectItem::Expr { expr, .. } => expr,
+                item => panic!("{item:?}"),
+            },
+            statement => panic!("{statement:?}"),
+        }
+    }
+
+    #[test]
+    fn error_near_the_token_that_doesnt_fit() {
+        let cases = [
+            ("SELEC 1", "SELEC 1"),
+            ("SELECT 1 2", "2"),
+            ("SELECT 1 FROM", ""),
+            ("SELECT nosuch(1), 2", "nosuch(1), 2"),
+            ("SET @@foo.bar = 1", "foo.bar = 1"),
+            ("SET autocommit 1", "1"),
+            ("KILL QUERY x", "x"),
+            ("PREPARE s FROM 1", "1"),
+            ("EXECUTE s USING @a, 2", "2"),
+            ("SHOW FULL TABLES", "TABLES"),
+        ];
+        for (sql, rest) in cases {
+            assert_eq!(parse_error(sql), near(rest), "{sql}");
+        }
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(selected("SELECT -1"), Expr::Literal(Value::Int(-1)));
+        assert_eq!(selected("SELECT - - 1"), Expr::Literal(Value::Int(1)));
+        assert_eq!(selected("SELECT -'a'"), Expr::Literal(Value::Bytes(b"-a".to_vec())));
+        assert_eq!(selected("SELECT --'a'"), Expr::Literal(Value::Bytes(b"a".to_vec())));
+        assert_eq!(parse_error("SELECT - - abc"), near("abc"));
+
+        // as many as fit in a packet without running out of stack
+        let sql = format!("SELECT {}1", "- ".repeat(1_000_000));
+        assert_eq!(selected(&sql), Expr::Literal(Value::Int(1)));
+    }
+
+    #[test]
+    fn split_at_semicolons_outside_quotes_and_comments() {
+        let sql = "SELECT 'a;b'; SELECT \"c;\\\";d\"; SELECT `e;f` -- g;h\n; /* i;j */ SELECT 1 # k;l\n;SELECT 'it''s;'";
+        assert_eq!(split_statements(sql), [
+            "SELECT 'a;b'",
+            " SELECT \"c;\\\";d\"",
+            " SELECT `e;f` -- g;h\n",
+            " /* i;j */ SELECT 1 # k;l\n",
+            "SELECT 'it''s;'",
+        ]);
+    }
+
+    #[test]
+    fn blank_statements_are_left_out() {
+        assert_eq!(split_statements("SELECT 1;;  ;\n-- done\n"), ["SELECT 1"]);
+        assert_eq!(split_statements(" ; /* nothing */ ;# at all"), Vec::<&str>::new());
+        // a quote that isn't closed runs to the end
+        assert_eq!(split_statements("SELECT 'a;b"), ["SELECT 'a;b"]);
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("SELECT `a b`, 'it''s', \"x\\ty\", 12.5 -- comment\n/* more */ ?#end").unwrap();
+        assert_eq!(tokens, [
+            (0, Token::Ident("SELECT".to_string())),
+            (7, Token::Ident("a b".to_string())),
+            (12, Token::Symbol(',')),
+            (14, Token::Str("it's".to_string())),
+            (21, Token::Symbol(',')),
+            (23, Token::Str("x\ty".to_string())),
+            (29, Token::Symbol(',')),
+            (31, Token::Number("12.5".to_string())),
+            (58, Token::Placeholder),
+        ]);
+        // `--` without a space after it isn't a comment
+        assert_eq!(tokenize("1--2").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        assert_eq!(tokenize("SELECT 'abc").unwrap_err().message, near("'abc"));
+        assert_eq!(tokenize("SELECT \"ab\\").unwrap_err().message, near("\"ab\\"));
+    }
+
+    // numbered in the order they appear, the count comes with the statement
+    #[test]
+    fn placeholders() {
+        let (statement, params) = parse("SELECT ?, mysql_query_attribute_string(?) AS a, '?' FROM products WHERE id = ?", Some("protocols")).unwrap();
+        assert_eq!(params, 3);
+        let Statement::Select(select) = statement else { panic!("{statement:?}") };
+        assert_eq!(select.items[0], SelectItem::Expr { expr: Expr::Placeholder(0), alias: None });
+        assert_eq!(select.items[1], SelectItem::Expr {
+            expr: Expr::Function("mysql_query_attribute_string".to_string(), vec![Expr::Placeholder(1)]),
+            alias: Some("a".to_string()),
+        });
+        assert_eq!(select.items[2], SelectItem::Expr { expr: Expr::Literal(Value::Bytes(b"?".to_vec())), alias: None });
+        assert_eq!(select.from, Some(TableName { schema: "protocols".to_string(), name: "products".to_string() }));
+        assert_eq!(select.filter, Some(("id".to_string(), Expr::Placeholder(2))));
+
+        assert_eq!(parse("SELECT 1 /* ? */ -- ?\n", None).unwrap().1, 0);
+        assert_eq!(parse("SELECT ? ?", None).unwrap_err().message, near("?"));
+    }
+
+    #[test]
+    fn table_names() {
+        assert_eq!(parse("SELECT * FROM products", None).unwrap_err().message, ServerError::no_database_selected().message);
+        assert!(parse("SELECT * FROM information_schema.PROCESSLIST", None).is_ok());
+        assert!(parse("SELECT * FROM nosuch.t", Some("protocols")).is_err());
+        assert_eq!(parse("SELECT * FROM products WHERE id 1", Some("protocols")).unwrap_err().message, near("1"));
+    }
+
+    #[test]
+    fn variables() {
+        let items = |sql| match parse(sql, None).unwrap().0 {
+            Statement::Select(select) => select.items,
+            statement => panic!("{statement:?}"),
+        };
+        let expr = |expr| SelectItem::Expr { expr, alias: None };
+        assert_eq!(items("SELECT @a, @`b c`, @'d', @@version, @@session.autocommit, @@GLOBAL.sql_mode"), [
+            expr(Expr::UserVariable("a".to_string())),
+            expr(Expr::UserVariable("b c".to_string())),
+            expr(Expr::UserVariable("d".to_string())),
+            expr(Expr::SystemVariable("version".to_string())),
+            expr(Expr::SystemVariable("session.autocommit".to_string())),
+            expr(Expr::SystemVariable("GLOBAL.sql_mode".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn set() {
+        let set = |sql| match parse(sql, None).unwrap().0 {
+            Statement::Set(assignments) => assignments,
+            statement => panic!("{statement:?}"),
+        };
+        let variable = |scope, name: &str, value| Assignment::Variable { scope, name: name.to_string(), value };
+        assert_eq!(set("SET @a = 1, @b := 'x'"), [
+            Assignment::UserVariable { name: "a".to_string(), value: Expr::Literal(Value::Int(1)) },
+            Assignment::UserVariable { name: "b".to_string(), value: Expr::Literal(Value::Bytes(b"x".to_vec())) },
+        ]);
+        assert_eq!(set("SET autocommit = ON, GLOBAL max_connections = DEFAULT, @@local.sql_mode = ''"), [
+            variable(Scope::Session, "autocommit", Some(Expr::Column("ON".to_string()))),
+            variable(Scope::Global, "max_connections", None),
+            variable(Scope::Session, "sql_mode", Some(Expr::Literal(Value::Bytes(Vec::new())))),
+        ]);
+        assert_eq!(set("SET NAMES 'latin1' COLLATE latin1_bin"), [
+            Assignment::Names { charset: Some("latin1".to_string()), collation: Some("latin1_bin".to_string()) },
+        ]);
+        assert_eq!(set("SET TRANSACTION READ ONLY, ISOLATION LEVEL READ COMMITTED, @c = 2"), [
+            Assignment::NextTransaction(vec![("transaction_read_only", "ON"), ("transaction_isolation", "READ-COMMITTED")]),
+            Assignment::UserVariable { name: "c".to_string(), value: Expr::Literal(Value::Int(2)) },
+        ]);
+        assert_eq!(set("SET SESSION TRANSACTION ISOLATION LEVEL SERIALIZABLE"), [
+            variable(Scope::Session, "transaction_isolation", Some(Expr::Literal(Value::from("SERIALIZABLE")))),
+        ]);
+
+        assert_eq!(parse_error("SET @a 1"), near("1"));
+        assert_eq!(parse_error("SET TRANSACTION READ MAYBE"), near("MAYBE"));
+    }
+
+    #[test]
+    fn prepared_statements() {
+        let statement = |sql| parse(sql, None).unwrap().0;
+        assert_eq!(statement("PREPARE s FROM 'SELECT ?'"), Statement::Prepare {
+            name: "s".to_string(),
+            source: Expr::Literal(Value::Bytes(b"SELECT ?".to_vec())),
+        });
+        assert_eq!(statement("PREPARE s FROM @q"), Statement::Prepare { name: "s".to_string(), source: Expr::UserVariable("q".to_string()) });
+        assert_eq!(statement("EXECUTE s USING @a, @b"), Statement::Execute {
+            name: "s".to_string(),
+            using: vec!["a".to_string(), "b".to_string()],
+        });
+        assert_eq!(statement("EXECUTE s;"), Statement::Execute { name: "s".to_string(), using: Vec::new() });
+        assert_eq!(statement("DEALLOCATE PREPARE s"), Statement::Deallocate("s".to_string()));
+        assert_eq!(statement("DROP PREPARE s"), Statement::Deallocate("s".to_string()));
+
+        assert_eq!(parse_error("PREPARE s 'SELECT 1'"), near("'SELECT 1'"));
+        assert_eq!(parse_error("PREPARE s FROM @@version"), near("@@version"));
+        assert_eq!(parse_error("DEALLOCATE s"), near("s"));
+        assert_eq!(parse_error("EXECUTE s USING"), near(""));
+    }
+}
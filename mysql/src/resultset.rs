@@ -0,0 +1,230 @@
+use mysql_protocol::charset::{Charset, Collation};
+use mysql_protocol::column::ColumnDefinition41;
+use mysql_protocol::packet::TextRow;
+
+use crate::binary;
+
+/// DATE, DATETIME and TIMESTAMP value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: u32,
+}
+
+/// TIME value, a duration that can be negative and span days rather than a time of day.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Time {
+    pub negative: bool,
+    pub days: u32,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub microseconds: u32,
+}
+
+/// Column value of a result row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    Date(DateTime), // time of day is zero
+    DateTime(DateTime),
+    Time(Time),
+    Bytes(Vec<u8>), // strings, blobs, decimals and anything already in its text form
+}
+
+impl Value {
+    // text protocol representation, `None` for NULL
+    pub fn as_text(&self) -> Option<Vec<u8>> {
+        let text = match self {
+            Value::Null => return None,
+            Value::Int(v) => v.to_string(),
+            Value::UInt(v) => v.to_string(),
+            Value::Float(v) => v.to_string(),
+            Value::Double(v) => v.to_string(),
+            Value::Date(v) => format!("{:04}-{:02}-{:02}", v.year, v.month, v.day),
+            Value::DateTime(v) => {
+                let mut text = format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    v.year, v.month, v.day, v.hour, v.minute, v.second
+                );
+                if v.microsecond > 0 {
+                    text.push_str(&format!(".{:06}", v.microsecond));
+                }
+                text
+            }
+            Value::Time(v) => {
+                let mut text = format!(
+                    "{}{:02}:{:02}:{:02}",
+                    if v.negative { "-" } else { "" },
+                    v.days * 24 + v.hours as u32,
+                    v.minutes,
+                    v.seconds
+                );
+                if v.microseconds > 0 {
+                    text.push_str(&format!(".{:06}", v.microseconds));
+                }
+                text
+            }
+            Value::Bytes(v) => return Some(v.clone()),
+        };
+        Some(text.into_bytes())
+    }
+
+    // `=` in a WHERE clause, NULL is never equal to anything, numbers compare by value
+    // and everything else compares as case-insensitive text
+    pub fn sql_eq(&self, other: &Value) -> bool {
+        match (self.as_number(), other.as_number()) {
+            (Some(Number::Integer(a)), Some(Number::Integer(b))) => a == b,
+            (Some(a), Some(b)) => a.as_f64() == b.as_f64(),
+            _ => match (self.as_text(), other.as_text()) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(&b),
+                _ => false,
+            },
+        }
+    }
+
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Int(v) => Some(Number::Integer(*v as i128)),
+            Value::UInt(v) => Some(Number::Integer(*v as i128)),
+            Value::Float(v) => Some(Number::Real(*v as f64)),
+            Value::Double(v) => Some(Number::Real(*v)),
+            _ => None,
+        }
+    }
+}
+
+enum Number {
+    Integer(i128),
+    Real(f64),
+}
+
+impl Number {
+    fn as_f64(&self) -> f64 {
+        match self {
+            Number::Integer(v) => *v as f64,
+            Number::Real(v) => *v,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Bytes(value.as_bytes().to_vec())
+    }
+}
+
+/// Result row, one nullable value per column.
+#[derive(Debug, Clone)]
+pub struct Row(pub Vec<Value>);
+
+impl Row {
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query_response_text_resultset_row.html
+    pub fn as_text_bytes(&self) -> Vec<u8> {
+        TextRow(self.0.iter().map(Value::as_text).collect()).as_bytes()
+    }
+
+    // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_binary_resultset.html#sect_protocol_binary_resultset_row
+    pub fn as_binary_bytes(&self, columns: &[ColumnDefinition41]) -> Vec<u8> {
+        let mut response = vec![0x00]; // packet header
+
+        // NULL bitmap, the first two bits are reserved
+        let mut null_bitmap = vec![0u8; (self.0.len() + 7 + 2) / 8];
+        for (i, value) in self.0.iter().enumerate() {
+            if *value == Value::Null {
+                null_bitmap[(i + 2) / 8] |= 1 << ((i + 2) % 8);
+            }
+        }
+        response.extend(null_bitmap);
+
+        // NULLs are only in the bitmap
+        for (value, column) in self.0.iter().zip(columns) {
+            if *value != Value::Null {
+                binary::push_value(&mut response, value, column.column_type);
+            }
+        }
+
+        response
+    }
+}
+
+/// Column definitions and rows, encoded as text or binary rows depending on the protocol.
+#[derive(Debug, Clone)]
+pub struct ResultSet {
+    pub columns: Vec<ColumnDefinition41>,
+    pub rows: Vec<Row>,
+}
+
+impl ResultSet {
+    /// The result with its text values converted to character_set_results, as they're kept in UTF-8.
+    pub fn in_charset(&self, results: Option<&Collation>) -> ResultSet {
+        let columns = self.columns.iter().map(|column| column.in_charset(results)).collect();
+        let Some(results) = results.filter(|r| r.charset != Charset::Utf8mb4) else {
+            return ResultSet { columns, rows: self.rows.clone() };
+        };
+
+        let text_columns = self.columns.iter().map(|column| column.text_charset().is_some()).collect::<Vec<_>>();
+        let rows = self.rows.iter()
+            .map(|row| {
+                let values = row.0.iter().zip(&text_columns)
+                    .map(|(value, text)| match value {
+                        Value::Bytes(bytes) if *text => Value::Bytes(results.charset.encode(&String::from_utf8_lossy(bytes))),
+                        value => value.clone(),
+                    })
+                    .collect();
+                Row(values)
+            })
+            .collect();
+
+        ResultSet { columns, rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mysql_protocol::charset::collation_by_name;
+    use mysql_protocol::column::{ColumnType, UTF8MB4_GENERAL_CI};
+
+    use super::*;
+
+    #[test]
+    fn in_charset() {
+        let result_set = ResultSet {
+            columns: vec![
+                ColumnDefinition41::table_column("caf\u{e9}", "t", "\u{e9}t\u{e9}", ColumnType::VarString, 40).with_charset(UTF8MB4_GENERAL_CI),
+                ColumnDefinition41::new("\u{20ac}", ColumnType::Blob, 10),
+            ],
+            rows: vec![Row(vec![Value::Bytes("d\u{e9}j\u{e0} \u{20ac}".as_bytes().to_vec()), Value::Bytes(vec![0xe9])])],
+        };
+
+        let latin1 = result_set.in_charset(collation_by_name("latin1_swedish_ci"));
+        let column = latin1.columns[0].as_bytes();
+        assert!(column.windows(5).any(|name| name == b"\x04caf\xe9"));
+        assert!(column.windows(4).any(|name| name == b"\x03\xe9t\xe9"));
+        assert_eq!(latin1.columns[0].character_set, 8);
+        assert_eq!(latin1.columns[0].column_length, 10);
+        assert!(latin1.columns[1].as_bytes().windows(2).any(|name| name == b"\x01\x80"));
+        // binary values are left as they are
+        assert_eq!(latin1.rows[0].0, [Value::Bytes(b"d\xe9j\xe0 \x80".to_vec()), Value::Bytes(vec![0xe9])]);
+
+        // without character_set_results nothing is converted
+        let unconverted = result_set.in_charset(None);
+        assert_eq!(unconverted.columns[0].as_bytes(), result_set.columns[0].as_bytes());
+        assert_eq!(unconverted.rows[0].0, result_set.rows[0].0);
+    }
+}
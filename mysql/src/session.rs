@@ -0,0 +1,259 @@
+use std::collections::{HashMap, VecDeque};
+
+use mysql_protocol::column::ColumnDefinition41;
+use mysql_protocol::flags::{
+    CLIENT_DEPRECATE_EOF, CLIENT_LOCAL_FILES, CLIENT_MULTI_RESULTS, CLIENT_MULTI_STATEMENTS, CLIENT_QUERY_ATTRIBUTES,
+    CLIENT_SESSION_TRACK, SERVER_STATUS_AUTOCOMMIT,
+};
+use mysql_protocol::packet::StateChange;
+
+use crate::binary::ParamType;
+use crate::query::Statement;
+use crate::resultset::{ResultSet, Row, Value};
+use crate::variables::{Setting, Variables};
+
+/// Statement registered with COM_STMT_PREPARE, lives until COM_STMT_CLOSE or the end of the connection,
+/// or with `PREPARE <name> FROM`, until `DEALLOCATE PREPARE`.
+#[derive(Debug)]
+pub struct PreparedStatement {
+    pub statement: Statement,
+    pub params: usize,
+    pub columns: Vec<ColumnDefinition41>,
+    pub param_types: Vec<ParamType>,
+    pub long_data: HashMap<u16, Vec<u8>>, // COM_STMT_SEND_LONG_DATA chunks by parameter, until the next execution
+    pub cursor: Option<Cursor>,
+}
+
+impl PreparedStatement {
+    pub fn new(statement: Statement, params: usize, columns: Vec<ColumnDefinition41>) -> Self {
+        PreparedStatement {
+            statement,
+            params,
+            columns,
+            param_types: Vec::new(),
+            long_data: HashMap::new(),
+            cursor: None,
+        }
+    }
+}
+
+/// Result of a CURSOR_TYPE_READ_ONLY execution, handed out in batches by COM_STMT_FETCH.
+#[derive(Debug)]
+pub struct Cursor {
+    pub columns: Vec<ColumnDefinition41>,
+    rows: VecDeque<Row>,
+}
+
+impl Cursor {
+    pub fn new(result_set: ResultSet) -> Self {
+        Cursor {
+            columns: result_set.columns,
+            rows: result_set.rows.into(),
+        }
+    }
+
+    pub fn fetch(&mut self, num_rows: usize) -> Vec<Row> {
+        let n = num_rows.min(self.rows.len());
+        self.rows.drain(..n).collect()
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// Per connection state that outlives a single command.
+pub struct Session {
+    pub connection_id: u32,
+    pub capabilities: u32,
+    pub collation: u16, // the client's, from the handshake or COM_CHANGE_USER
+    pub database: Option<String>,
+    pub attributes: Vec<(String, Value)>, // query attributes of the command being run
+    pub variables: Variables,
+    statements: HashMap<u32, PreparedStatement>,
+    next_statement_id: u32,
+    named_statements: HashMap<String, PreparedStatement>, // by lowercase name
+    user_variables: HashMap<String, Value>, // `@<name>`, by lowercase name
+    state_changes: Vec<StateChange>, // since the last OK packet
+    next_transaction: Vec<(&'static str, &'static str)>, // SET TRANSACTION without a scope, variable and value
+}
+
+impl Session {
+    pub fn new(connection_id: u32, capabilities: u32, collation: u16, database: Option<String>) -> Self {
+        Session {
+            connection_id,
+            capabilities,
+            collation,
+            database,
+            attributes: Vec::new(),
+            variables: Variables::new(collation),
+            statements: HashMap::new(),
+            next_statement_id: 1,
+            named_statements: HashMap::new(),
+            user_variables: HashMap::new(),
+            state_changes: Vec::new(),
+            next_transaction: Vec::new(),
+        }
+    }
+
+    // COM_RESET_CONNECTION and COM_CHANGE_USER, the current schema stays
+    pub fn reset(&mut self) {
+        self.statements.clear();
+        self.named_statements.clear();
+        self.user_variables.clear();
+        self.next_transaction.clear();
+        self.variables = Variables::new(self.collation);
+    }
+
+    // server status of the OK and EOF packets
+    pub fn status_flags(&self) -> u16 {
+        if self.variables.autocommit() { SERVER_STATUS_AUTOCOMMIT } else { 0 }
+    }
+
+    // the current schema, reported to the client as a state change
+    pub fn set_database(&mut self, database: Option<String>) {
+        self.track(StateChange::Schema(database.clone().unwrap_or_default()));
+        self.database = database;
+    }
+
+    // assigns the checked values, reporting the ones session_track_system_variables lists
+    pub fn set_variables(&mut self, settings: Vec<Setting>) {
+        for setting in settings {
+            let tracker = setting.name() == "session_track_transaction_info";
+            if let Some((name, value)) = self.variables.assign(setting) {
+                self.track(StateChange::SystemVariable(name, value));
+            }
+            // a tracker that's turned on starts with where the session is
+            if tracker && self.variables.track_transaction_state() {
+                self.track(StateChange::TransactionState("________".to_string()));
+                self.track_transaction_characteristics();
+            }
+        }
+    }
+
+    // SET TRANSACTION without a scope, for the next transaction only
+    pub fn set_next_transaction(&mut self, characteristics: Vec<(&'static str, &'static str)>) {
+        for (name, value) in characteristics {
+            self.next_transaction.retain(|(n, _)| *n != name);
+            self.next_transaction.push((name, value));
+        }
+        self.track_transaction_characteristics();
+    }
+
+    // every statement is a transaction of its own, the first one after SET TRANSACTION uses its characteristics up
+    pub fn end_transaction(&mut self) {
+        if !self.next_transaction.is_empty() {
+            self.next_transaction.clear();
+            self.track_transaction_characteristics();
+        }
+    }
+
+    // the statements that would set up the next transaction the same way, empty once it's over
+    fn track_transaction_characteristics(&mut self) {
+        if !self.variables.track_transaction_characteristics() {
+            return;
+        }
+        let statements = self.next_transaction.iter()
+            .map(|(name, value)| match *name {
+                "transaction_isolation" => format!("SET TRANSACTION ISOLATION LEVEL {};", value.replace('-', " ")),
+                _ => format!("SET TRANSACTION READ {};", if *value == "ON" { "ONLY" } else { "WRITE" }),
+            })
+            .collect::<Vec<_>>();
+        self.track(StateChange::TransactionCharacteristics(statements.join(" ")));
+    }
+
+    // tracked as if started with session_track_state_change=ON and session_track_gtids=OWN_GTID, what a proxy
+    // needs to share its connections, the transaction trackers as session_track_transaction_info asks for them
+    pub fn track(&mut self, change: StateChange) {
+        if self.session_track() && !self.state_changes.contains(&change) {
+            self.state_changes.push(change);
+        }
+    }
+
+    // the changes for the OK packet, in the order of their trackers, `None` without CLIENT_SESSION_TRACK
+    pub fn take_state_changes(&mut self) -> Option<Vec<StateChange>> {
+        if !self.session_track() {
+            return None;
+        }
+
+        let mut changes = std::mem::take(&mut self.state_changes);
+        // a new GTID doesn't make the session any different
+        if changes.iter().any(|c| matches!(c, StateChange::SystemVariable(..) | StateChange::Schema(_)))
+            && !changes.contains(&StateChange::StateChanged)
+        {
+            changes.push(StateChange::StateChanged);
+        }
+        changes.sort_by_key(StateChange::tracker);
+        Some(changes)
+    }
+
+    pub fn session_track(&self) -> bool {
+        self.capabilities & CLIENT_SESSION_TRACK != 0
+    }
+
+    pub fn deprecate_eof(&self) -> bool {
+        self.capabilities & CLIENT_DEPRECATE_EOF != 0
+    }
+
+    pub fn local_files(&self) -> bool {
+        self.capabilities & CLIENT_LOCAL_FILES != 0
+    }
+
+    pub fn multi_statements(&self) -> bool {
+        self.capabilities & CLIENT_MULTI_STATEMENTS != 0
+    }
+
+    // multi-statements imply multiple results
+    pub fn multi_results(&self) -> bool {
+        self.capabilities & (CLIENT_MULTI_RESULTS | CLIENT_MULTI_STATEMENTS) != 0
+    }
+
+    pub fn query_attributes(&self) -> bool {
+        self.capabilities & CLIENT_QUERY_ATTRIBUTES != 0
+    }
+
+    // returns the statement id
+    pub fn prepare(&mut self, statement: PreparedStatement) -> u32 {
+        let id = self.next_statement_id;
+        self.next_statement_id = self.next_statement_id.wrapping_add(1).max(1);
+        self.statements.insert(id, statement);
+        id
+    }
+
+    pub fn statement_mut(&mut self, id: u32) -> Option<&mut PreparedStatement> {
+        self.statements.get_mut(&id)
+    }
+
+    pub fn close_statement(&mut self, id: u32) {
+        self.statements.remove(&id);
+    }
+
+    // PREPARE <name> FROM, replaces a statement of the same name
+    pub fn prepare_named(&mut self, name: &str, statement: PreparedStatement) {
+        self.named_statements.insert(name.to_lowercase(), statement);
+        self.track(StateChange::StateChanged);
+    }
+
+    pub fn named_statement(&self, name: &str) -> Option<&PreparedStatement> {
+        self.named_statements.get(&name.to_lowercase())
+    }
+
+    // false if there's no statement of that name
+    pub fn deallocate(&mut self, name: &str) -> bool {
+        let found = self.named_statements.remove(&name.to_lowercase()).is_some();
+        if found {
+            self.track(StateChange::StateChanged);
+        }
+        found
+    }
+
+    // NULL until it's set
+    pub fn user_variable(&self, name: &str) -> Value {
+        self.user_variables.get(&name.to_lowercase()).cloned().unwrap_or(Value::Null)
+    }
+
+    pub fn set_user_variable(&mut self, name: &str, value: Value) {
+        self.user_variables.insert(name.to_lowercase(), value);
+        self.track(StateChange::StateChanged);
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, RwLock};
+
+use mysql_protocol::charset::{self, Charset, Collation};
+use mysql_protocol::column::{ColumnDefinition41, ColumnType, NOT_NULL_FLAG, UNSIGNED_FLAG, UTF8MB4_GENERAL_CI};
+use mysql_protocol::connection::MAX_ALLOWED_PACKET;
+
+use crate::catalog::{PERFORMANCE_SCHEMA, Table};
+use crate::error::ServerError;
+use crate::gtid;
+use crate::resultset::{ResultSet, Row, Value};
+
+/// `GLOBAL` or `SESSION`, the value of a variable for new connections or for the current one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Global,
+    Session,
+}
+
+// values a variable takes
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Bool, // ON or OFF, 1 or 0 in a select
+    Integer { min: u64, max: u64, block: u64 }, // values are rounded down to a multiple of the block size
+    Text,
+    Enum(&'static [&'static str]),
+    Charset { nullable: bool }, // by name or by the number of one of its collations
+    Collation,
+    SqlMode,
+    TimeZone,
+}
+
+// where a variable can be read and set
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Access {
+    Both,
+    SessionReadOnly, // set only with SET GLOBAL, new connections take it up
+    Global,
+    ReadOnly, // global and fixed at startup
+}
+
+struct Definition {
+    name: &'static str,
+    kind: Kind,
+    access: Access,
+    default: &'static str,
+}
+
+// in the order SHOW VARIABLES lists them
+const SQL_MODES: [&str; 20] = [
+    "REAL_AS_FLOAT",
+    "PIPES_AS_CONCAT",
+    "ANSI_QUOTES",
+    "IGNORE_SPACE",
+    "ONLY_FULL_GROUP_BY",
+    "NO_UNSIGNED_SUBTRACTION",
+    "NO_DIR_IN_CREATE",
+    "NO_AUTO_VALUE_ON_ZERO",
+    "NO_BACKSLASH_ESCAPES",
+    "STRICT_TRANS_TABLES",
+    "STRICT_ALL_TABLES",
+    "NO_ZERO_IN_DATE",
+    "NO_ZERO_DATE",
+    "ALLOW_INVALID_DATES",
+    "ERROR_FOR_DIVISION_BY_ZERO",
+    "TRADITIONAL",
+    "HIGH_NOT_PRECEDENCE",
+    "NO_ENGINE_SUBSTITUTION",
+    "PAD_CHAR_TO_FULL_LENGTH",
+    "TIME_TRUNCATE_FRACTIONAL",
+];
+
+const ISOLATION_LEVELS: &[&str] = &["READ-UNCOMMITTED", "READ-COMMITTED", "REPEATABLE-READ", "SERIALIZABLE"];
+
+// what the transaction trackers report, the characteristics come with the state
+const TRANSACTION_INFO: &[&str] = &["OFF", "STATE", "CHARACTERISTICS"];
+
+const CHARSET: Kind = Kind::Charset { nullable: false };
+
+const fn integer(min: u64, max: u64) -> Kind {
+    Kind::Integer { min, max, block: 1 }
+}
+
+// sorted by name, as SHOW VARIABLES lists them
+// https://dev.mysql.com/doc/refman/9.4/en/server-system-variables.html
+const DEFINITIONS: [Definition; 37] = [
+    Definition { name: "auto_increment_increment", kind: integer(1, 65535), access: Access::Both, default: "1" },
+    Definition { name: "auto_increment_offset", kind: integer(1, 65535), access: Access::Both, default: "1" },
+    Definition { name: "autocommit", kind: Kind::Bool, access: Access::Both, default: "ON" },
+    Definition { name: "binlog_checksum", kind: Kind::Text, access: Access::ReadOnly, default: "CRC32" },
+    Definition { name: "binlog_format", kind: Kind::Text, access: Access::ReadOnly, default: "ROW" },
+    Definition { name: "binlog_row_image", kind: Kind::Text, access: Access::ReadOnly, default: "FULL" },
+    Definition { name: "character_set_client", kind: CHARSET, access: Access::Both, default: "utf8mb4" },
+    Definition { name: "character_set_connection", kind: CHARSET, access: Access::Both, default: "utf8mb4" },
+    Definition { name: "character_set_database", kind: CHARSET, access: Access::Both, default: "utf8mb4" },
+    Definition { name: "character_set_results", kind: Kind::Charset { nullable: true }, access: Access::Both, default: "utf8mb4" },
+    Definition { name: "character_set_server", kind: CHARSET, access: Access::Both, default: "utf8mb4" },
+    Definition { name: "character_set_system", kind: CHARSET, access: Access::ReadOnly, default: "utf8mb3" },
+    Definition { name: "collation_connection", kind: Kind::Collation, access: Access::Both, default: "utf8mb4_0900_ai_ci" },
+    Definition { name: "collation_database", kind: Kind::Collation, access: Access::Both, default: "utf8mb4_0900_ai_ci" },
+    Definition { name: "collation_server", kind: Kind::Collation, access: Access::Both, default: "utf8mb4_0900_ai_ci" },
+    Definition { name: "enforce_gtid_consistency", kind: Kind::Text, access: Access::ReadOnly, default: "ON" },
+    Definition { name: "gtid_mode", kind: Kind::Text, access: Access::ReadOnly, default: "ON" },
+    Definition { name: "init_connect", kind: Kind::Text, access: Access::Global, default: "" },
+    Definition { name: "interactive_timeout", kind: integer(1, 31_536_000), access: Access::Both, default: "28800" },
+    Definition { name: "license", kind: Kind::Text, access: Access::ReadOnly, default: "GPL" },
+    Definition { name: "log_bin", kind: Kind::Bool, access: Access::ReadOnly, default: "ON" },
+    Definition { name: "lower_case_table_names", kind: integer(0, 2), access: Access::ReadOnly, default: "0" },
+    Definition {
+        name: "max_allowed_packet",
+        kind: Kind::Integer { min: 1024, max: 1_073_741_824, block: 1024 },
+        access: Access::SessionReadOnly,
+        default: "67108864",
+    },
+    Definition { name: "net_read_timeout", kind: integer(1, 31_536_000), access: Access::Both, default: "30" },
+    Definition { name: "net_write_timeout", kind: integer(1, 31_536_000), access: Access::Both, default: "60" },
+    Definition { name: "performance_schema", kind: Kind::Bool, access: Access::ReadOnly, default: "ON" },
+    Definition { name: "server_id", kind: integer(0, 4_294_967_295), access: Access::ReadOnly, default: "1" },
+    Definition { name: "server_uuid", kind: Kind::Text, access: Access::ReadOnly, default: gtid::SERVER_UUID },
+    Definition {
+        name: "session_track_system_variables",
+        kind: Kind::Text,
+        access: Access::Both,
+        default: "time_zone,autocommit,character_set_client,character_set_results,character_set_connection",
+    },
+    Definition { name: "session_track_transaction_info", kind: Kind::Enum(TRANSACTION_INFO), access: Access::Both, default: "OFF" },
+    Definition {
+        name: "sql_mode",
+        kind: Kind::SqlMode,
+        access: Access::Both,
+        default: "ONLY_FULL_GROUP_BY,STRICT_TRANS_TABLES,NO_ZERO_IN_DATE,NO_ZERO_DATE,ERROR_FOR_DIVISION_BY_ZERO,NO_ENGINE_SUBSTITUTION",
+    },
+    Definition { name: "system_time_zone", kind: Kind::Text, access: Access::ReadOnly, default: "UTC" },
+    Definition { name: "time_zone", kind: Kind::TimeZone, access: Access::Both, default: "SYSTEM" },
+    Definition { name: "transaction_isolation", kind: Kind::Enum(ISOLATION_LEVELS), access: Access::Both, default: "REPEATABLE-READ" },
+    Definition { name: "transaction_read_only", kind: Kind::Bool, access: Access::Both, default: "OFF" },
+    Definition { name: "version", kind: Kind::Text, access: Access::ReadOnly, default: "9.4.0" },
+    Definition { name: "version_comment", kind: Kind::Text, access: Access::ReadOnly, default: "MySQL Community Server - GPL" },
+];
+
+static GLOBALS: LazyLock<RwLock<BTreeMap<&'static str, Value>>> = LazyLock::new(|| {
+    let globals = DEFINITIONS.iter()
+        .map(|d| (d.name, d.default_value()))
+        .collect();
+    RwLock::new(globals)
+});
+
+impl Definition {
+    fn default_value(&self) -> Value {
+        let value = match self.kind {
+            Kind::Integer { .. } => Value::UInt(self.default.parse().expect("numeric default")),
+            _ => self.default.into(),
+        };
+        self.kind.check(self.name, &value).expect("valid default")
+    }
+}
+
+fn definition(name: &str) -> Result<&'static Definition, ServerError> {
+    DEFINITIONS.iter()
+        .find(|d| d.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ServerError::unknown_system_variable(name))
+}
+
+impl Kind {
+    // the value as it's stored, numbers for booleans and integers, text for everything else
+    fn check(&self, name: &str, value: &Value) -> Result<Value, ServerError> {
+        let wrong_value = || {
+            let text = value.as_text().map_or("NULL".to_string(), |text| String::from_utf8_lossy(&text).to_string());
+            ServerError::wrong_value_for_variable(name, &text)
+        };
+        let text = || value.as_text().map(|text| String::from_utf8_lossy(&text).trim().to_uppercase());
+
+        match self {
+            Kind::Bool => match value {
+                Value::Int(v @ (0 | 1)) => Ok(Value::Int(*v)),
+                Value::UInt(v @ (0 | 1)) => Ok(Value::Int(*v as i64)),
+                Value::Bytes(_) => match text().as_deref() {
+                    Some("ON" | "TRUE" | "1") => Ok(Value::Int(1)),
+                    Some("OFF" | "FALSE" | "0") => Ok(Value::Int(0)),
+                    _ => Err(wrong_value()),
+                },
+                Value::Float(_) | Value::Double(_) => Err(ServerError::wrong_type_for_variable(name)),
+                _ => Err(wrong_value()),
+            },
+            // out of range values are clamped
+            Kind::Integer { min, max, block } => {
+                let v = match value {
+                    Value::Int(v) => (*v).max(0) as u64,
+                    Value::UInt(v) => *v,
+                    _ => return Err(ServerError::wrong_type_for_variable(name)),
+                };
+                Ok(Value::UInt(v.clamp(*min, *max) / block * block))
+            }
+            Kind::Text => match value {
+                Value::Null => Err(wrong_value()),
+                value => Ok(value.as_text().map_or(Value::Null, Value::Bytes)),
+            },
+            Kind::Charset { nullable } => {
+                let charset = match value {
+                    Value::Null if *nullable => return Ok(Value::Null),
+                    Value::Null => return Err(wrong_value()),
+                    Value::Int(_) | Value::UInt(_) => {
+                        let id = value.as_text().map(|text| String::from_utf8_lossy(&text).to_string()).unwrap_or_default();
+                        id.parse().ok().and_then(charset::collation).map(|c| c.charset).ok_or_else(|| ServerError::unknown_charset(&id))?
+                    }
+                    _ => {
+                        let name = value.as_text().map(|text| String::from_utf8_lossy(&text).trim().to_string()).unwrap_or_default();
+                        Charset::from_name(&name).ok_or_else(|| ServerError::unknown_charset(&name))?
+                    }
+                };
+                Ok(Value::from(charset.name()))
+            }
+            Kind::Collation => {
+                let name = value.as_text().map(|text| String::from_utf8_lossy(&text).trim().to_string()).ok_or_else(wrong_value)?;
+                let collation = match value {
+                    Value::Int(_) | Value::UInt(_) => name.parse().ok().and_then(charset::collation),
+                    _ => charset::collation_by_name(&name),
+                };
+                collation.map(|c| Value::from(c.name)).ok_or_else(|| ServerError::unknown_collation(&name))
+            }
+            Kind::Enum(values) => {
+                let text = text().ok_or_else(wrong_value)?;
+                values.iter().find(|v| **v == text).map(|v| Value::from(*v)).ok_or_else(wrong_value)
+            }
+            // the modes in their canonical order, each once
+            Kind::SqlMode => {
+                let text = text().ok_or_else(wrong_value)?;
+                let modes = text.split(',').map(str::trim).filter(|m| !m.is_empty()).collect::<Vec<_>>();
+                if let Some(unknown) = modes.iter().find(|m| !SQL_MODES.contains(m)) {
+                    return Err(ServerError::wrong_value_for_variable(name, unknown));
+                }
+                let modes = SQL_MODES.iter().filter(|known| modes.contains(known)).copied().collect::<Vec<_>>();
+                Ok(Value::from(modes.join(",").as_str()))
+            }
+            // SYSTEM or an offset, named time zones need the time zone tables
+            Kind::TimeZone => {
+                let text = text().ok_or_else(wrong_value)?;
+                if text == "SYSTEM" {
+                    return Ok(Value::from("SYSTEM"));
+                }
+                let offset = text.strip_prefix(['+', '-'])
+                    .and_then(|offset| offset.split_once(':'))
+                    .and_then(|(hours, minutes)| Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?)))
+                    .filter(|&(hours, minutes)| minutes < 60 && hours * 60 + minutes <= 14 * 60);
+                match offset {
+                    Some((hours, minutes)) => Ok(Value::from(format!("{}{hours:02}:{minutes:02}", &text[..1]).as_str())),
+                    None => Err(ServerError::unknown_time_zone(&String::from_utf8_lossy(&value.as_text().unwrap_or_default()))),
+                }
+            }
+        }
+    }
+
+    // the type of `SELECT @@<variable>`
+    fn column(&self, name: &str) -> ColumnDefinition41 {
+        match self {
+            Kind::Bool => ColumnDefinition41::new(name, ColumnType::LongLong, 1),
+            Kind::Integer { .. } => ColumnDefinition41::new(name, ColumnType::LongLong, 21).with_flags(UNSIGNED_FLAG),
+            _ => ColumnDefinition41::new(name, ColumnType::VarString, 4096).with_charset(UTF8MB4_GENERAL_CI),
+        }
+    }
+
+    // as SHOW VARIABLES and the trackers have it, ON and OFF rather than numbers
+    fn display(&self, value: &Value) -> Option<String> {
+        match (self, value) {
+            (Kind::Bool, Value::Int(v)) => Some(if *v != 0 { "ON" } else { "OFF" }.to_string()),
+            (_, value) => value.as_text().map(|text| String::from_utf8_lossy(&text).to_string()),
+        }
+    }
+}
+
+/// Value checked by `Variables::check`, for `Variables::assign`.
+pub struct Setting {
+    scope: Scope,
+    definition: &'static Definition,
+    value: Value,
+}
+
+impl Setting {
+    pub fn name(&self) -> &'static str {
+        self.definition.name
+    }
+}
+
+/// Session values of the variables, a copy of the global ones when the connection starts.
+#[derive(Debug, Clone)]
+pub struct Variables {
+    values: BTreeMap<&'static str, Value>,
+}
+
+impl Variables {
+    /// The global values, but the character set of the connection is the one of the collation the client asked for,
+    /// unless it's one the server doesn't know.
+    pub fn new(collation: u16) -> Self {
+        let mut values: BTreeMap<_, _> = GLOBALS.read().unwrap().iter()
+            .filter(|(name, _)| matches!(definition(name).map(|d| d.access), Ok(Access::Both | Access::SessionReadOnly)))
+            .map(|(name, value)| (*name, value.clone()))
+            .collect();
+
+        if let Some(collation) = charset::collation(collation) {
+            for name in ["character_set_client", "character_set_connection", "character_set_results"] {
+                values.insert(name, collation.charset.name().into());
+            }
+            values.insert("collation_connection", collation.name.into());
+        }
+        Variables { values }
+    }
+
+    /// `@@<name>`, `@@session.<name>` or `@@global.<name>`, the text after `@@` as the query has it.
+    ///
+    /// Without a scope it's the session value, or the global one of a variable that has no session value.
+    pub fn get(&self, reference: &str) -> Result<Value, ServerError> {
+        let (scope, name) = parse_reference(reference);
+        let definition = definition(name)?;
+        match (scope, self.values.get(definition.name)) {
+            (Some(Scope::Global) | None, None) => Ok(GLOBALS.read().unwrap()[definition.name].clone()),
+            (Some(Scope::Global), Some(_)) => Ok(GLOBALS.read().unwrap()[definition.name].clone()),
+            (Some(Scope::Session) | None, Some(value)) => Ok(value.clone()),
+            (Some(Scope::Session), None) => Err(ServerError::incorrect_scope(definition.name, "GLOBAL")),
+        }
+    }
+
+    /// Checks `SET [GLOBAL | SESSION] <name> = <value>`, `None` for DEFAULT, without assigning anything yet:
+    /// a statement that fails on any of its assignments changes none of the variables.
+    pub fn check(&self, scope: Scope, name: &str, value: Option<&Value>) -> Result<Setting, ServerError> {
+        let definition = definition(name)?;
+        match (scope, definition.access) {
+            (_, Access::ReadOnly) => return Err(ServerError::read_only_variable(definition.name)),
+            (Scope::Session, Access::Global) => return Err(ServerError::global_variable(definition.name)),
+            (Scope::Session, Access::SessionReadOnly) => return Err(ServerError::session_read_only(definition.name)),
+            _ => {}
+        }
+
+        // DEFAULT is the global value for a session, and the compiled-in one globally
+        let value = match value {
+            Some(value) => definition.kind.check(definition.name, value)?,
+            None if scope == Scope::Session => GLOBALS.read().unwrap()[definition.name].clone(),
+            None => definition.default_value(),
+        };
+        Ok(Setting { scope, definition, value })
+    }
+
+    /// `SET NAMES <charset> [COLLATE <collation>]`, `None` for DEFAULT, the character set of the server.
+    pub fn check_names(&self, charset: Option<&str>, collation: Option<&str>) -> Result<Vec<Setting>, ServerError> {
+        let charset = match charset {
+            Some(name) => Charset::from_name(name).ok_or_else(|| ServerError::unknown_charset(name))?,
+            None => self.charset("character_set_server").unwrap_or(Charset::Utf8mb4),
+        };
+        let collation = match collation {
+            Some(name) => charset::collation_by_name(name).ok_or_else(|| ServerError::unknown_collation(name))?,
+            None => charset.default_collation(),
+        };
+        if collation.charset != charset {
+            return Err(ServerError::collation_charset_mismatch(collation.name, charset.name()));
+        }
+
+        let mut settings = Vec::new();
+        for name in ["character_set_client", "character_set_connection", "character_set_results"] {
+            settings.push(self.check(Scope::Session, name, Some(&charset.name().into()))?);
+        }
+        settings.push(self.check(Scope::Session, "collation_connection", Some(&collation.name.into()))?);
+        Ok(settings)
+    }
+
+    // the name as the tracker reports it and the new value, when `SET SESSION` changed what the session tracks
+    pub fn assign(&mut self, setting: Setting) -> Option<(String, String)> {
+        let Setting { scope, definition, value } = setting;
+        if scope == Scope::Global {
+            GLOBALS.write().unwrap().insert(definition.name, value);
+            return None;
+        }
+
+        let display = definition.kind.display(&value).unwrap_or_default();
+        self.values.insert(definition.name, value);
+        self.tracked(definition.name).then(|| (definition.name.to_string(), display))
+    }
+
+    // session_track_system_variables lists it, or is `*`
+    fn tracked(&self, name: &str) -> bool {
+        let tracked = self.values.get("session_track_system_variables").and_then(Value::as_text).unwrap_or_default();
+        String::from_utf8_lossy(&tracked).split(',').map(str::trim).any(|t| t == "*" || t.eq_ignore_ascii_case(name))
+    }
+
+    // `None` for NULL
+    fn charset(&self, name: &str) -> Option<Charset> {
+        self.values.get(name).and_then(Value::as_text).and_then(|text| Charset::from_name(&String::from_utf8_lossy(&text)))
+    }
+
+    // what the query text comes in
+    pub fn client_charset(&self) -> Charset {
+        self.charset("character_set_client").unwrap_or(Charset::Utf8mb4)
+    }
+
+    // what results go out in and the number their text columns report, the connection's collation if it's
+    // of the same charset, `None` sends them as they are
+    pub fn results_collation(&self) -> Option<&'static Collation> {
+        let charset = self.charset("character_set_results")?;
+        let connection = self.values.get("collation_connection")
+            .and_then(Value::as_text)
+            .and_then(|name| charset::collation_by_name(&String::from_utf8_lossy(&name)));
+        Some(connection.filter(|c| c.charset == charset).unwrap_or(charset.default_collation()))
+    }
+
+    pub fn autocommit(&self) -> bool {
+        self.values.get("autocommit") == Some(&Value::Int(1))
+    }
+
+    pub fn track_transaction_state(&self) -> bool {
+        self.values.get("session_track_transaction_info").is_some_and(|value| *value != Value::from("OFF"))
+    }
+
+    pub fn track_transaction_characteristics(&self) -> bool {
+        self.values.get("session_track_transaction_info") == Some(&Value::from("CHARACTERISTICS"))
+    }
+
+    // each variable with its value for the scope, as SHOW VARIABLES and performance_schema list them
+    fn list(&self, scope: Scope) -> Vec<(&'static str, Option<String>)> {
+        let globals = GLOBALS.read().unwrap();
+        DEFINITIONS.iter()
+            .map(|d| {
+                let value = match scope {
+                    Scope::Session => self.values.get(d.name).unwrap_or(&globals[d.name]),
+                    Scope::Global => &globals[d.name],
+                };
+                (d.name, d.kind.display(value))
+            })
+            .collect()
+    }
+}
+
+// the scope and the name of `session.<name>`, `global.<name>`, `local.<name>` or `<name>`
+fn parse_reference(reference: &str) -> (Option<Scope>, &str) {
+    match reference.split_once('.') {
+        Some((scope, name)) if scope.eq_ignore_ascii_case("global") => (Some(Scope::Global), name),
+        Some((scope, name)) if scope.eq_ignore_ascii_case("session") || scope.eq_ignore_ascii_case("local") => {
+            (Some(Scope::Session), name)
+        }
+        _ => (None, reference),
+    }
+}
+
+// the column of `SELECT @@<reference>`
+pub fn column(name: &str, reference: &str) -> ColumnDefinition41 {
+    match definition(parse_reference(reference).1) {
+        Ok(definition) => definition.kind.column(name),
+        Err(_) => ColumnDefinition41::new(name, ColumnType::VarString, 4096).with_charset(UTF8MB4_GENERAL_CI),
+    }
+}
+
+// SHOW [GLOBAL | SESSION] VARIABLES [LIKE '<pattern>']
+pub fn show(variables: &Variables, scope: Scope, pattern: Option<&str>) -> ResultSet {
+    let text = |name, len| ColumnDefinition41::new(name, ColumnType::VarString, len).with_charset(UTF8MB4_GENERAL_CI);
+    let columns = vec![text("Variable_name", 256).with_flags(NOT_NULL_FLAG), text("Value", 4096)];
+
+    let rows = variables.list(scope).into_iter()
+        .filter(|(name, _)| pattern.is_none_or(|pattern| crate::query::like(name, pattern)))
+        .map(|(name, value)| Row(vec![name.into(), value.as_deref().map_or(Value::Null, Value::from)]))
+        .collect();
+
+    ResultSet { columns, rows }
+}
+
+// performance_schema.session_variables and global_variables
+pub fn table(variables: &Variables, name: &str) -> Option<Table> {
+    let (name, scope) = if name.eq_ignore_ascii_case("session_variables") {
+        ("session_variables", Scope::Session)
+    } else if name.eq_ignore_ascii_case("global_variables") {
+        ("global_variables", Scope::Global)
+    } else {
+        return None;
+    };
+
+    let column = |column, length| {
+        ColumnDefinition41::table_column(PERFORMANCE_SCHEMA, name, column, ColumnType::VarString, length).with_charset(UTF8MB4_GENERAL_CI)
+    };
+    let columns = vec![column("VARIABLE_NAME", 256).with_flags(NOT_NULL_FLAG), column("VARIABLE_VALUE", 4096)];
+
+    let rows = variables.list(scope).into_iter()
+        .map(|(name, value)| Row(vec![name.into(), value.as_deref().map_or(Value::Null, Value::from)]))
+        .collect();
+
+    Some(Table { name, columns, rows })
+}
+
+// max_allowed_packet of new connections
+pub fn max_allowed_packet() -> usize {
+    match GLOBALS.read().unwrap().get("max_allowed_packet") {
+        Some(Value::UInt(v)) => *v as usize,
+        _ => MAX_ALLOWED_PACKET,
+    }
+}
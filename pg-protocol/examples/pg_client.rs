@@ -0,0 +1,70 @@
+//! Runs queries against a PostgreSQL server with the simple query protocol and prints the rows as
+//! tab separated values.
+//!
+//! cargo run -p pg-protocol --example pg_client -- 127.0.0.1:5432 "select 123 as id"
+
+use std::io::Write;
+use std::net::TcpStream;
+
+use pg_protocol::backend::BackendMessage;
+use pg_protocol::encoding::Encoding;
+use pg_protocol::frontend::{FrontendMessage, PROTOCOL_VERSION, StartupMessage};
+use pg_protocol::{read_message, write_message, write_startup_message};
+
+// everything up to the next ReadyForQuery, client_encoding changes on the way are followed
+fn read_response(stream: &mut TcpStream, encoding: &mut Encoding) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        let (message_type, data) = read_message(stream)?;
+        match BackendMessage::parse(message_type, &data, *encoding)? {
+            BackendMessage::RowDescription(fields) => {
+                println!("{}", fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>().join("\t"));
+            }
+            BackendMessage::DataRow(values) => {
+                let values = values.iter()
+                    .map(|value| match value {
+                        Some(value) => encoding.decode(value).unwrap_or_else(|_| format!("{value:02x?}")),
+                        None => "NULL".to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", values.join("\t"));
+            }
+            BackendMessage::CommandComplete(tag) => println!("{tag}"),
+            BackendMessage::ParameterStatus { name, value } => {
+                if name == "client_encoding" && let Some(client_encoding) = Encoding::from_name(&value) {
+                    *encoding = client_encoding;
+                }
+            }
+            BackendMessage::ErrorResponse { severity, code, message } => println!("{}: {message} ({code})", severity.name()),
+            BackendMessage::ReadyForQuery(_) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:5432".to_string());
+
+    let mut stream = TcpStream::connect(addr)?;
+    let mut encoding = Encoding::Utf8;
+
+    let startup = StartupMessage {
+        version: PROTOCOL_VERSION,
+        parameters: vec![("user".to_string(), "postgres".to_string()), ("client_encoding".to_string(), encoding.name().to_string())],
+    };
+    write_startup_message(&mut stream, &startup.as_bytes())?;
+    read_response(&mut stream, &mut encoding)?;
+
+    for query in args {
+        println!("> {query}");
+        let message = FrontendMessage::Query(query);
+        write_message(&mut stream, message.message_type(), &message.as_bytes(encoding)?)?;
+        read_response(&mut stream, &mut encoding)?;
+    }
+
+    let message = FrontendMessage::Terminate;
+    write_message(&mut stream, message.message_type(), &message.as_bytes(encoding)?)?;
+    stream.flush()?;
+
+    Ok(())
+}
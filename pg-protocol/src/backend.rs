@@ -0,0 +1,254 @@
+use crate::DecodeError;
+use crate::encoding::{Encoding, EncodingError};
+use crate::reader::Reader;
+
+/// Where the session is between transactions, sent with every ReadyForQuery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionStatus {
+    Idle, // I
+    InTransaction, // T
+    Failed, // E, the rest of the transaction is ignored
+}
+
+/// A column of a RowDescription.
+///
+/// https://www.postgresql.org/docs/current/protocol-message-formats.html#PROTOCOL-MESSAGE-FORMATS-ROWDESCRIPTION
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescription {
+    pub name: String,
+    pub table_oid: u32, // 0 when the column isn't from a table
+    pub column: u16, // attribute number in that table, 0 otherwise
+    pub type_oid: u32,
+    pub type_size: i16, // negative for variable length types
+    pub type_modifier: i32, // -1 when there's none
+    pub format: u16, // 0 text, 1 binary
+}
+
+impl FieldDescription {
+    // a computed column in text format, e.g. `select 123 as id`
+    pub fn new(name: &str, type_oid: u32, type_size: i16) -> Self {
+        FieldDescription { name: name.to_string(), table_oid: 0, column: 0, type_oid, type_size, type_modifier: -1, format: 0 }
+    }
+}
+
+/// How bad an ErrorResponse is, FATAL ends the session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Fatal,
+    Panic,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "ERROR",
+            Severity::Fatal => "FATAL",
+            Severity::Panic => "PANIC",
+        }
+    }
+}
+
+/// Messages the server sends.
+///
+/// Data row values are left as they go out, text in the client encoding or binary as the
+/// RowDescription or the Bind asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendMessage {
+    AuthenticationOk,
+    ParameterStatus { name: String, value: String },
+    ReadyForQuery(TransactionStatus),
+    EmptyQueryResponse,
+    RowDescription(Vec<FieldDescription>),
+    DataRow(Vec<Option<Vec<u8>>>),
+    CommandComplete(String), // tag, e.g. `SELECT 2`
+    ParseComplete,
+    ParameterDescription(Vec<u32>), // type OIDs
+    BindComplete,
+    CloseComplete,
+    NoData,
+    ErrorResponse { severity: Severity, code: String, message: String },
+}
+
+impl BackendMessage {
+    // an ERROR that doesn't end the session
+    pub fn error(code: &str, message: String) -> Self {
+        BackendMessage::ErrorResponse { severity: Severity::Error, code: code.to_string(), message }
+    }
+
+    // a row of text-format values, in the client encoding
+    pub fn text_row(values: &[Option<&str>], encoding: Encoding) -> Result<BackendMessage, EncodingError> {
+        let values = values.iter()
+            .map(|value| value.map(|value| encoding.encode(value)).transpose())
+            .collect::<Result<_, _>>()?;
+        Ok(BackendMessage::DataRow(values))
+    }
+
+    pub fn message_type(&self) -> u8 {
+        match self {
+            BackendMessage::AuthenticationOk => 0x52, // R
+            BackendMessage::ParameterStatus { .. } => 0x53, // S
+            BackendMessage::ReadyForQuery(_) => 0x5a, // Z
+            BackendMessage::EmptyQueryResponse => 0x49, // I
+            BackendMessage::RowDescription(_) => 0x54, // T
+            BackendMessage::DataRow(_) => 0x44, // D
+            BackendMessage::CommandComplete(_) => 0x43, // C
+            BackendMessage::ParseComplete => 0x31, // 1
+            BackendMessage::ParameterDescription(_) => 0x74, // t
+            BackendMessage::BindComplete => 0x32, // 2
+            BackendMessage::CloseComplete => 0x33, // 3
+            BackendMessage::NoData => 0x6e, // n
+            BackendMessage::ErrorResponse { .. } => 0x45, // E
+        }
+    }
+
+    pub fn as_bytes(&self, encoding: Encoding) -> Result<Vec<u8>, EncodingError> {
+        let mut response = Vec::new();
+
+        match self {
+            BackendMessage::AuthenticationOk => {
+                response.extend(0u32.to_be_bytes()); // OK(0), 4 bytes
+            }
+            BackendMessage::ParameterStatus { name, value } => {
+                response.extend(encoding.encode(name)?);
+                response.push(0x00);
+                response.extend(encoding.encode(value)?);
+                response.push(0x00);
+            }
+            BackendMessage::ReadyForQuery(status) => {
+                response.push(match status {
+                    TransactionStatus::Idle => b'I',
+                    TransactionStatus::InTransaction => b'T',
+                    TransactionStatus::Failed => b'E',
+                });
+            }
+            BackendMessage::RowDescription(fields) => {
+                response.extend((fields.len() as u16).to_be_bytes()); // field count
+                for field in fields {
+                    response.extend(encoding.encode(&field.name)?); // column name
+                    response.push(0x00);
+                    response.extend(field.table_oid.to_be_bytes()); // table OID
+                    response.extend(field.column.to_be_bytes()); // column index
+                    response.extend(field.type_oid.to_be_bytes()); // type OID
+                    response.extend(field.type_size.to_be_bytes()); // column length
+                    response.extend(field.type_modifier.to_be_bytes()); // type modifier
+                    response.extend(field.format.to_be_bytes()); // format
+                }
+            }
+            BackendMessage::DataRow(values) => {
+                response.extend((values.len() as u16).to_be_bytes()); // field count
+                for value in values {
+                    match value {
+                        Some(value) => {
+                            response.extend((value.len() as u32).to_be_bytes()); // column length
+                            response.extend(value);
+                        }
+                        None => response.extend([0xff, 0xff, 0xff, 0xff]), // column length (-1)
+                    }
+                }
+            }
+            BackendMessage::CommandComplete(tag) => {
+                response.extend(encoding.encode(tag)?);
+                response.push(0x00);
+            }
+            BackendMessage::ParameterDescription(types) => {
+                response.extend((types.len() as u16).to_be_bytes()); // parameters
+                for type_oid in types {
+                    response.extend(type_oid.to_be_bytes());
+                }
+            }
+            BackendMessage::EmptyQueryResponse
+            | BackendMessage::ParseComplete
+            | BackendMessage::BindComplete
+            | BackendMessage::CloseComplete
+            | BackendMessage::NoData => {}
+            // severity, localized and not, SQLSTATE and message, the message has to get through somehow
+            // https://www.postgresql.org/docs/current/protocol-error-fields.html
+            BackendMessage::ErrorResponse { severity, code, message } => {
+                for (field, value) in [(b'S', severity.name()), (b'V', severity.name()), (b'C', code.as_str())] {
+                    response.push(field);
+                    response.extend(value.as_bytes());
+                    response.push(0x00);
+                }
+                response.push(b'M');
+                response.extend(encoding.encode_lossy(message));
+                response.push(0x00);
+                response.push(0x00);
+            }
+        }
+
+        Ok(response)
+    }
+
+    pub fn parse(message_type: u8, data: &[u8], encoding: Encoding) -> Result<BackendMessage, DecodeError> {
+        let mut reader = Reader::new(message_type, data);
+
+        let message = match message_type {
+            // only trust, nothing asks for a password
+            b'R' if reader.read_u32()? == 0 => BackendMessage::AuthenticationOk,
+            b'S' => BackendMessage::ParameterStatus { name: reader.read_string(encoding)?, value: reader.read_string(encoding)? },
+            b'Z' => BackendMessage::ReadyForQuery(match reader.read_u8()? {
+                b'I' => TransactionStatus::Idle,
+                b'T' => TransactionStatus::InTransaction,
+                b'E' => TransactionStatus::Failed,
+                _ => return Err(reader.malformed()),
+            }),
+            b'I' => BackendMessage::EmptyQueryResponse,
+            b'T' => {
+                let count = reader.read_u16()?;
+                let mut fields = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    fields.push(FieldDescription {
+                        name: reader.read_string(encoding)?,
+                        table_oid: reader.read_u32()?,
+                        column: reader.read_u16()?,
+                        type_oid: reader.read_u32()?,
+                        type_size: reader.read_u16()? as i16,
+                        type_modifier: reader.read_u32()? as i32,
+                        format: reader.read_u16()?,
+                    });
+                }
+                BackendMessage::RowDescription(fields)
+            }
+            b'D' => {
+                let values = (0..reader.read_u16()?)
+                    .map(|_| reader.read_nullable_bytes().map(|value| value.map(<[u8]>::to_vec)))
+                    .collect::<Result<_, _>>()?;
+                BackendMessage::DataRow(values)
+            }
+            b'C' => BackendMessage::CommandComplete(reader.read_string(encoding)?),
+            b'1' => BackendMessage::ParseComplete,
+            b't' => BackendMessage::ParameterDescription((0..reader.read_u16()?).map(|_| reader.read_u32()).collect::<Result<_, _>>()?),
+            b'2' => BackendMessage::BindComplete,
+            b'3' => BackendMessage::CloseComplete,
+            b'n' => BackendMessage::NoData,
+            b'E' => {
+                let (mut severity, mut code, mut message) = (Severity::Error, String::new(), String::new());
+                loop {
+                    let field = reader.read_u8()?;
+                    if field == 0 {
+                        break;
+                    }
+                    let value = reader.read_cstr()?;
+                    match field {
+                        b'V' => {
+                            severity = match value {
+                                b"FATAL" => Severity::Fatal,
+                                b"PANIC" => Severity::Panic,
+                                _ => Severity::Error,
+                            }
+                        }
+                        b'C' => code = String::from_utf8_lossy(value).to_string(),
+                        // the server would rather send `?` than drop the message, so should the client
+                        b'M' => message = encoding.decode(value).unwrap_or_else(|_| String::from_utf8_lossy(value).to_string()),
+                        _ => {} // detail, hint, position and the like
+                    }
+                }
+                BackendMessage::ErrorResponse { severity, code, message }
+            }
+            _ => return Err(DecodeError::UnknownMessage(message_type)),
+        };
+
+        Ok(message)
+    }
+}
@@ -0,0 +1,196 @@
+/// Client encodings the server converts to and from, text is UTF-8 inside the server.
+///
+/// https://www.postgresql.org/docs/current/multibyte.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Win1252,
+    SqlAscii, // no conversion at all, but the text still has to be valid in the server encoding
+}
+
+/// Text that can't be converted, reported as `character_not_in_repertoire`.
+#[derive(Debug)]
+pub struct EncodingError {
+    pub message: String,
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+pub const CHARACTER_NOT_IN_REPERTOIRE: &str = "22021";
+
+/// 0x80 to 0x9f of WIN1252, `None` for the five bytes it leaves undefined. The rest of the bytes are
+/// the same as in LATIN1.
+pub const WIN1252_HIGH: [Option<char>; 32] = [
+    Some('\u{20ac}'), None, Some('\u{201a}'), Some('\u{0192}'), Some('\u{201e}'), Some('\u{2026}'), Some('\u{2020}'), Some('\u{2021}'),
+    Some('\u{02c6}'), Some('\u{2030}'), Some('\u{0160}'), Some('\u{2039}'), Some('\u{0152}'), None, Some('\u{017d}'), None,
+    None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201c}'), Some('\u{201d}'), Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'),
+    Some('\u{02dc}'), Some('\u{2122}'), Some('\u{0161}'), Some('\u{203a}'), Some('\u{0153}'), None, Some('\u{017e}'), Some('\u{0178}'),
+];
+
+// as PostgreSQL prints bytes in its messages, `0xe2 0x82 0xac`
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("0x{b:02x}")).collect::<Vec<_>>().join(" ")
+}
+
+impl Encoding {
+    // case and punctuation don't matter, `utf-8` is UTF8 too
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        let name = name.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_ascii_lowercase();
+        match name.as_str() {
+            "utf8" | "unicode" => Some(Encoding::Utf8),
+            "latin1" | "iso88591" => Some(Encoding::Latin1),
+            "win1252" | "windows1252" => Some(Encoding::Win1252),
+            "sqlascii" => Some(Encoding::SqlAscii),
+            _ => None,
+        }
+    }
+
+    // the name ParameterStatus reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF8",
+            Encoding::Latin1 => "LATIN1",
+            Encoding::Win1252 => "WIN1252",
+            Encoding::SqlAscii => "SQL_ASCII",
+        }
+    }
+
+    // text from the client
+    pub fn decode(self, bytes: &[u8]) -> Result<String, EncodingError> {
+        match self {
+            Encoding::Utf8 | Encoding::SqlAscii => match std::str::from_utf8(bytes) {
+                Ok(text) => Ok(text.to_string()),
+                Err(e) => {
+                    let invalid = &bytes[e.valid_up_to()..];
+                    let invalid = &invalid[..e.error_len().unwrap_or(invalid.len())];
+                    Err(EncodingError { message: format!("invalid byte sequence for encoding \"UTF8\": {}", hex(invalid)) })
+                }
+            },
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+            Encoding::Win1252 => bytes.iter()
+                .map(|&b| match b {
+                    0x80..=0x9f => WIN1252_HIGH[(b - 0x80) as usize].ok_or_else(|| EncodingError {
+                        message: format!("character with byte sequence {} in encoding \"WIN1252\" has no equivalent in encoding \"UTF8\"", hex(&[b])),
+                    }),
+                    b => Ok(b as char),
+                })
+                .collect(),
+        }
+    }
+
+    // text for the client
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, EncodingError> {
+        let untranslatable = |c: char| EncodingError {
+            message: format!(
+                "character with byte sequence {} in encoding \"UTF8\" has no equivalent in encoding \"{}\"",
+                hex(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                self.name(),
+            ),
+        };
+
+        match self {
+            Encoding::Utf8 | Encoding::SqlAscii => Ok(text.as_bytes().to_vec()),
+            Encoding::Latin1 => text.chars().map(|c| u8::try_from(c).map_err(|_| untranslatable(c))).collect(),
+            Encoding::Win1252 => text.chars()
+                .map(|c| match c as u32 {
+                    0x00..=0x7f | 0xa0..=0xff => Ok(c as u8),
+                    _ => WIN1252_HIGH.iter().position(|&high| high == Some(c)).map(|i| 0x80 + i as u8).ok_or_else(|| untranslatable(c)),
+                })
+                .collect(),
+        }
+    }
+
+    // for error messages, which have to get through somehow
+    pub fn encode_lossy(self, text: &str) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| self.encode(c.encode_utf8(&mut [0; 4])).unwrap_or_else(|_| b"?".to_vec()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let cases = [
+            (Encoding::Utf8, "caf\u{e9} \u{20ac} \u{1f600}"),
+            (Encoding::Latin1, "caf\u{e9} \u{ff}"),
+            (Encoding::Win1252, "caf\u{e9} \u{20ac} \u{2122} \u{178}"),
+            (Encoding::SqlAscii, "caf\u{e9}"),
+        ];
+        for (encoding, text) in cases {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(encoding.decode(&bytes).unwrap(), text, "{encoding:?}");
+        }
+        assert_eq!(Encoding::Win1252.encode("\u{20ac}\u{e9}").unwrap(), [0x80, 0xe9]);
+        assert_eq!(Encoding::Latin1.encode("\u{e9}").unwrap(), [0xe9]);
+    }
+
+    // every byte of LATIN1 is a character, so all of them come back
+    #[test]
+    fn latin1_every_byte() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let text = Encoding::Latin1.decode(&bytes).unwrap();
+        assert_eq!(Encoding::Latin1.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn win1252_undefined_bytes() {
+        for b in [0x81, 0x8d, 0x8f, 0x90, 0x9d] {
+            let error = Encoding::Win1252.decode(&[b'a', b]).unwrap_err();
+            let expected = format!("character with byte sequence 0x{b:02x} in encoding \"WIN1252\" has no equivalent in encoding \"UTF8\"");
+            assert_eq!(error.message, expected);
+        }
+        // C1 controls aren't in WIN1252 either, the holes don't decode to them
+        assert!(Encoding::Win1252.encode("\u{81}").is_err());
+    }
+
+    #[test]
+    fn untranslatable_character() {
+        let error = Encoding::Latin1.encode("a\u{100}").unwrap_err();
+        assert_eq!(error.message, "character with byte sequence 0xc4 0x80 in encoding \"UTF8\" has no equivalent in encoding \"LATIN1\"");
+        let error = Encoding::Win1252.encode("\u{20ac}\u{3b1}").unwrap_err();
+        assert_eq!(error.message, "character with byte sequence 0xce 0xb1 in encoding \"UTF8\" has no equivalent in encoding \"WIN1252\"");
+        assert_eq!(Encoding::Latin1.encode_lossy("a\u{100}b"), b"a?b");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let error = Encoding::Utf8.decode(b"ab\xe2\x82").unwrap_err();
+        assert_eq!(error.message, "invalid byte sequence for encoding \"UTF8\": 0xe2 0x82");
+        let error = Encoding::SqlAscii.decode(b"\xffab").unwrap_err();
+        assert_eq!(error.message, "invalid byte sequence for encoding \"UTF8\": 0xff");
+    }
+
+    #[test]
+    fn from_name_aliases() {
+        let cases = [
+            ("UTF8", Some(Encoding::Utf8)),
+            ("utf-8", Some(Encoding::Utf8)),
+            ("Unicode", Some(Encoding::Utf8)),
+            ("LATIN1", Some(Encoding::Latin1)),
+            ("ISO_8859_1", Some(Encoding::Latin1)),
+            ("iso-8859-1", Some(Encoding::Latin1)),
+            ("WIN1252", Some(Encoding::Win1252)),
+            ("Windows-1252", Some(Encoding::Win1252)),
+            ("SQL_ASCII", Some(Encoding::SqlAscii)),
+            ("latin2", None),
+            ("", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(Encoding::from_name(name), expected, "{name}");
+        }
+        for encoding in [Encoding::Utf8, Encoding::Latin1, Encoding::Win1252, Encoding::SqlAscii] {
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        }
+    }
+}
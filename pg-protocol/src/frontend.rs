@@ -0,0 +1,212 @@
+use crate::DecodeError;
+use crate::encoding::{Encoding, EncodingError};
+use crate::reader::Reader;
+
+/// Protocol version 3.0, the major version in the high 16 bits.
+pub const PROTOCOL_VERSION: u32 = 196608;
+
+/// The first message of a connection, the protocol version and the run-time parameters, e.g. `user`,
+/// `database` and `client_encoding`.
+///
+/// https://www.postgresql.org/docs/current/protocol-message-formats.html#PROTOCOL-MESSAGE-FORMATS-STARTUPMESSAGE
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupMessage {
+    pub version: u32,
+    pub parameters: Vec<(String, String)>,
+}
+
+impl StartupMessage {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(self.version.to_be_bytes());
+        for (name, value) in &self.parameters {
+            data.extend(name.as_bytes());
+            data.push(0x00);
+            data.extend(value.as_bytes());
+            data.push(0x00);
+        }
+        data.push(0x00); // no more parameters
+        data
+    }
+
+    // name and value pairs up to an empty name, taken as ASCII before there's a client_encoding
+    pub fn parse(data: &[u8]) -> Result<StartupMessage, DecodeError> {
+        let mut reader = Reader::new(0, data);
+        let version = reader.read_u32()?;
+
+        let mut parameters = Vec::new();
+        loop {
+            let name = reader.read_cstr()?;
+            if name.is_empty() {
+                break;
+            }
+            let value = reader.read_cstr()?;
+            parameters.push((String::from_utf8_lossy(name).to_string(), String::from_utf8_lossy(value).to_string()));
+        }
+
+        Ok(StartupMessage { version, parameters })
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// What Describe and Close refer to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Statement, // S
+    Portal, // P
+}
+
+impl Target {
+    fn as_byte(self) -> u8 {
+        match self {
+            Target::Statement => b'S',
+            Target::Portal => b'P',
+        }
+    }
+
+    fn parse(reader: &mut Reader) -> Result<Target, DecodeError> {
+        match reader.read_u8()? {
+            b'S' => Ok(Target::Statement),
+            b'P' => Ok(Target::Portal),
+            _ => Err(reader.malformed()),
+        }
+    }
+}
+
+/// Messages a client sends once it's in, the simple query and the extended query protocol.
+///
+/// Names and query text are in the client encoding on the wire, parameter values are left as they
+/// come since only the statement knows their format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontendMessage {
+    Query(String),
+    Parse { name: String, query: String, param_types: Vec<u32> },
+    Bind {
+        portal: String,
+        statement: String,
+        param_formats: Vec<u16>, // 0 text, 1 binary, none for all text, one for all of them
+        params: Vec<Option<Vec<u8>>>,
+        result_formats: Vec<u16>,
+    },
+    Describe(Target, String),
+    Execute { portal: String, max_rows: u32 }, // 0 for all of them
+    Close(Target, String),
+    Sync,
+    Flush,
+    Terminate,
+}
+
+fn push_string(data: &mut Vec<u8>, value: &str, encoding: Encoding) -> Result<(), EncodingError> {
+    data.extend(encoding.encode(value)?);
+    data.push(0x00);
+    Ok(())
+}
+
+fn push_formats(data: &mut Vec<u8>, formats: &[u16]) {
+    data.extend((formats.len() as u16).to_be_bytes());
+    for format in formats {
+        data.extend(format.to_be_bytes());
+    }
+}
+
+fn read_formats(reader: &mut Reader) -> Result<Vec<u16>, DecodeError> {
+    (0..reader.read_u16()?).map(|_| reader.read_u16()).collect()
+}
+
+impl FrontendMessage {
+    pub fn message_type(&self) -> u8 {
+        match self {
+            FrontendMessage::Query(_) => b'Q',
+            FrontendMessage::Parse { .. } => b'P',
+            FrontendMessage::Bind { .. } => b'B',
+            FrontendMessage::Describe(..) => b'D',
+            FrontendMessage::Execute { .. } => b'E',
+            FrontendMessage::Close(..) => b'C',
+            FrontendMessage::Sync => b'S',
+            FrontendMessage::Flush => b'H',
+            FrontendMessage::Terminate => b'X',
+        }
+    }
+
+    pub fn as_bytes(&self, encoding: Encoding) -> Result<Vec<u8>, EncodingError> {
+        let mut data = Vec::new();
+
+        match self {
+            FrontendMessage::Query(query) => push_string(&mut data, query, encoding)?,
+            FrontendMessage::Parse { name, query, param_types } => {
+                push_string(&mut data, name, encoding)?;
+                push_string(&mut data, query, encoding)?;
+                data.extend((param_types.len() as u16).to_be_bytes());
+                for param_type in param_types {
+                    data.extend(param_type.to_be_bytes());
+                }
+            }
+            FrontendMessage::Bind { portal, statement, param_formats, params, result_formats } => {
+                push_string(&mut data, portal, encoding)?;
+                push_string(&mut data, statement, encoding)?;
+                push_formats(&mut data, param_formats);
+                data.extend((params.len() as u16).to_be_bytes());
+                for param in params {
+                    match param {
+                        Some(value) => {
+                            data.extend((value.len() as u32).to_be_bytes());
+                            data.extend(value);
+                        }
+                        None => data.extend((-1i32).to_be_bytes()),
+                    }
+                }
+                push_formats(&mut data, result_formats);
+            }
+            FrontendMessage::Describe(target, name) | FrontendMessage::Close(target, name) => {
+                data.push(target.as_byte());
+                push_string(&mut data, name, encoding)?;
+            }
+            FrontendMessage::Execute { portal, max_rows } => {
+                push_string(&mut data, portal, encoding)?;
+                data.extend(max_rows.to_be_bytes());
+            }
+            FrontendMessage::Sync | FrontendMessage::Flush | FrontendMessage::Terminate => {}
+        }
+
+        Ok(data)
+    }
+
+    pub fn parse(message_type: u8, data: &[u8], encoding: Encoding) -> Result<FrontendMessage, DecodeError> {
+        let mut reader = Reader::new(message_type, data);
+
+        let message = match message_type {
+            b'Q' => FrontendMessage::Query(reader.read_string(encoding)?),
+            b'P' => {
+                let name = reader.read_string(encoding)?;
+                let query = reader.read_string(encoding)?;
+                let param_types = (0..reader.read_u16()?).map(|_| reader.read_u32()).collect::<Result<_, _>>()?;
+                FrontendMessage::Parse { name, query, param_types }
+            }
+            b'B' => {
+                let portal = reader.read_string(encoding)?;
+                let statement = reader.read_string(encoding)?;
+                let param_formats = read_formats(&mut reader)?;
+                let params = (0..reader.read_u16()?)
+                    .map(|_| reader.read_nullable_bytes().map(|value| value.map(<[u8]>::to_vec)))
+                    .collect::<Result<_, _>>()?;
+                let result_formats = read_formats(&mut reader)?;
+                FrontendMessage::Bind { portal, statement, param_formats, params, result_formats }
+            }
+            b'D' => FrontendMessage::Describe(Target::parse(&mut reader)?, reader.read_string(encoding)?),
+            b'E' => {
+                let portal = reader.read_string(encoding)?;
+                FrontendMessage::Execute { portal, max_rows: reader.read_u32()? }
+            }
+            b'C' => FrontendMessage::Close(Target::parse(&mut reader)?, reader.read_string(encoding)?),
+            b'S' => FrontendMessage::Sync,
+            b'H' => FrontendMessage::Flush,
+            b'X' => FrontendMessage::Terminate,
+            _ => return Err(DecodeError::UnknownMessage(message_type)),
+        };
+
+        Ok(message)
+    }
+}
@@ -0,0 +1,83 @@
+//! Messages of the PostgreSQL frontend/backend protocol, version 3.0, for servers and clients alike.
+//!
+//! [`frontend::FrontendMessage`] is what a client sends and [`backend::BackendMessage`] what the server
+//! answers, both with `as_bytes` for the message body and `parse` to read one back. The framing, a type
+//! byte and a length in front of every body, is left to [`read_message`] and [`write_message`].
+//!
+//! Text is UTF-8 on this side, [`encoding::Encoding`] converts it to and from the client_encoding.
+//!
+//! https://www.postgresql.org/docs/current/protocol-message-formats.html
+
+use std::io::{Read, Write};
+
+pub mod backend;
+pub mod encoding;
+pub mod frontend;
+
+mod reader;
+
+use crate::encoding::EncodingError;
+
+/// A message body that doesn't read as the message it claims to be.
+#[derive(Debug)]
+pub enum DecodeError {
+    Malformed(u8), // message type
+    UnknownMessage(u8),
+    Encoding(EncodingError), // text that isn't valid in the client encoding
+}
+
+impl From<EncodingError> for DecodeError {
+    fn from(error: EncodingError) -> Self {
+        DecodeError::Encoding(error)
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Malformed(message_type) => write!(f, "malformed message '{}'", *message_type as char),
+            DecodeError::UnknownMessage(message_type) => write!(f, "unknown message type 0x{message_type:02x}"),
+            DecodeError::Encoding(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Reads the type and the body of the next message.
+pub fn read_message(stream: &mut impl Read) -> Result<(u8, Vec<u8>), std::io::Error> {
+    let mut message_type = [0u8; 1];
+    stream.read_exact(&mut message_type)?;
+
+    Ok((message_type[0], read_body(stream)?))
+}
+
+/// Reads the first message of a connection, which has no type.
+pub fn read_startup_message(stream: &mut impl Read) -> Result<Vec<u8>, std::io::Error> {
+    read_body(stream)
+}
+
+fn read_body(stream: &mut impl Read) -> Result<Vec<u8>, std::io::Error> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+    let message_len = (u32::from_be_bytes(length) as usize)
+        .checked_sub(4) // subtract 4 bytes representing length
+        .ok_or(std::io::ErrorKind::InvalidData)?;
+
+    let mut buf = vec![0; message_len];
+    stream.read_exact(&mut buf)?;
+
+    Ok(buf)
+}
+
+pub fn write_message(stream: &mut impl Write, message_type: u8, data: &[u8]) -> Result<(), std::io::Error> {
+    stream.write_all(&[message_type])?;
+    write_startup_message(stream, data)
+}
+
+pub fn write_startup_message(stream: &mut impl Write, data: &[u8]) -> Result<(), std::io::Error> {
+    let message_len = (data.len() as u32) + 4; // 4 bytes is length itself
+
+    stream.write_all(&message_len.to_be_bytes())?;
+    stream.write_all(data)
+}
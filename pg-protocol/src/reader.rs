@@ -0,0 +1,59 @@
+use crate::DecodeError;
+use crate::encoding::Encoding;
+
+/// Cursor over a message body, big-endian as everything in the protocol, running past its end
+/// makes the message malformed.
+pub struct Reader<'a> {
+    message_type: u8,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(message_type: u8, data: &'a [u8]) -> Self {
+        Reader { message_type, data, pos: 0 }
+    }
+
+    pub fn malformed(&self) -> DecodeError {
+        DecodeError::Malformed(self.message_type)
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self.data.get(self.pos..self.pos.saturating_add(len)).ok_or_else(|| self.malformed())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    // a length of -1 is NULL
+    pub fn read_nullable_bytes(&mut self) -> Result<Option<&'a [u8]>, DecodeError> {
+        match self.read_u32()? as i32 {
+            -1 => Ok(None),
+            len if len < 0 => Err(self.malformed()),
+            len => self.read_bytes(len as usize).map(Some),
+        }
+    }
+
+    pub fn read_cstr(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.data.get(self.pos..).unwrap_or_default().iter().position(|&b| b == 0).ok_or_else(|| self.malformed())?;
+        let bytes = self.read_bytes(len)?;
+        self.pos += 1; // terminating 0
+        Ok(bytes)
+    }
+
+    // a null-terminated string in the client encoding
+    pub fn read_string(&mut self, encoding: Encoding) -> Result<String, DecodeError> {
+        Ok(encoding.decode(self.read_cstr()?)?)
+    }
+}
@@ -1,328 +1,282 @@
-use std::ffi::CStr;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::net::{TcpListener, TcpStream};
 use std::thread;
-use crate::RequestMessage::{Bind, Close, Describe, Execute, Parse, SimpleQuery, Sync, Termination};
 
-#[derive(Debug)]
-enum ResponseMessage {
-    AuthRequestOK,
-    ParameterStatus(String, String),
-    ReadyForQuery,
-    EmptyQuery,
-    SimpleRowDescription,
-    SimpleDataRow,
-    SimpleCommandCompletion,
-    ComplexRowDescription,
-    ComplexDataRow1,
-    ComplexDataRow2,
-    ComplexCommandCompletion,
-    ParseCompletion,
-    ParameterDescription,
-    BindCompletion,
-    DataRow,
-    CloseCompletion,
-}
-
-#[derive(Debug)]
-enum RequestMessage {
-    SimpleQuery(String),
-    Termination,
-    Parse,
-    Describe,
-    Sync,
-    Bind,
-    Execute,
-    Close,
-}
-
-impl ResponseMessage {
-    fn as_bytes(&self) -> Vec<u8> {
-        let mut response = Vec::new();
-
-        match self {
-            ResponseMessage::AuthRequestOK => {
-                response.extend(0u32.to_be_bytes()); // OK(0), 4 bytes
-            }
-            ResponseMessage::ParameterStatus(name, value) => {
-                response.extend(name.as_bytes());
-                response.push(0x00);
-                response.extend(value.as_bytes());
-                response.push(0x00);
-            }
-            ResponseMessage::ReadyForQuery => {
-                response.push(0x49); // Idle (73)
-            }
-            ResponseMessage::EmptyQuery => {}
-            ResponseMessage::SimpleRowDescription => {
-                response.extend(1u16.to_be_bytes()); // field count
-                response.extend(b"id\0"); // column name
-                response.extend(0u32.to_be_bytes()); // table OID
-                response.extend(0u16.to_be_bytes()); // column index
-                response.extend(23u32.to_be_bytes()); // type OID
-                response.extend(4u16.to_be_bytes()); // column length
-                response.extend([0xff, 0xff, 0xff, 0xff]); // type modifier
-                response.extend(0u16.to_be_bytes()); // format: text (0)
-            }
-            ResponseMessage::SimpleDataRow => {
-                response.extend(1u16.to_be_bytes()); // field count
-                response.extend(3u32.to_be_bytes()); // column length
-                response.extend(b"123");
-            }
-            ResponseMessage::SimpleCommandCompletion => {
-                response.extend(b"SELECT 1\0");
-            }
-            ResponseMessage::ComplexRowDescription => {
-                response.extend(4u16.to_be_bytes()); // field count
+use pg_protocol::backend::{BackendMessage, FieldDescription, Severity, TransactionStatus};
+use pg_protocol::encoding::{CHARACTER_NOT_IN_REPERTOIRE, Encoding, EncodingError};
+use pg_protocol::frontend::{FrontendMessage, StartupMessage};
+use pg_protocol::{DecodeError, read_message, read_startup_message, write_message};
 
-                response.extend(b"id\0"); // column name
-                response.extend([0x00, 0x00, 0x40, 0x01]); // table OID
-                response.extend(1u16.to_be_bytes()); // column index
-                response.extend(23u32.to_be_bytes()); // type OID
-                response.extend(4u16.to_be_bytes()); // column length
-                response.extend([0xff, 0xff, 0xff, 0xff]); // type modifier (-1)
-                response.extend(0u16.to_be_bytes()); // format: text (0)
+// SQLSTATE of a bad SET or startup parameter
+const INVALID_PARAMETER_VALUE: &str = "22023";
 
-                response.extend(b"title\0"); // column name
-                response.extend([0x00, 0x00, 0x40, 0x01]); // table OID
-                response.extend(3u16.to_be_bytes()); // column index
-                response.extend(1043u32.to_be_bytes()); // type OID
-                response.extend([0xff, 0xff]); // column length
-                response.extend([0x00, 0x00, 0x00, 0x68]); // type modifier (104)
-                response.extend(0u16.to_be_bytes()); // format: text (0)
+const READY_FOR_QUERY: BackendMessage = BackendMessage::ReadyForQuery(TransactionStatus::Idle);
 
-                response.extend(b"description\0"); // column name
-                response.extend([0x00, 0x00, 0x40, 0x01]); // table OID
-                response.extend(4u16.to_be_bytes()); // column index
-                response.extend(25u32.to_be_bytes()); // type OID
-                response.extend([0xff, 0xff]); // column length
-                response.extend([0xff, 0xff, 0xff, 0xff]); // type modifier (-1)
-                response.extend(0u16.to_be_bytes()); // format: text (0)
-
-                response.extend(b"category_id\0"); // column name
-                response.extend([0x00, 0x00, 0x40, 0x01]); // table OID
-                response.extend(5u16.to_be_bytes()); // column index
-                response.extend(21u32.to_be_bytes()); // type OID
-                response.extend(2u16.to_be_bytes()); // column length
-                response.extend([0xff, 0xff, 0xff, 0xff]); // type modifier (-1)
-                response.extend(0u16.to_be_bytes()); // format: text (0)
-            }
-            ResponseMessage::ComplexDataRow1 => {
-                response.extend(4u16.to_be_bytes()); // field count
-
-                response.extend(1u32.to_be_bytes()); // column length
-                response.extend(b"1");
-
-                response.extend(6u32.to_be_bytes()); // column length
-                response.extend(b"laptop");
-
-                response.extend([0xff, 0xff, 0xff, 0xff]); // column length (-1)
+fn simple_row_description() -> BackendMessage {
+    BackendMessage::RowDescription(vec![FieldDescription::new("id", 23, 4)])
+}
 
-                response.extend(1u32.to_be_bytes()); // column length
-                response.extend(b"2");
-            }
-            ResponseMessage::ComplexDataRow2 => {
-                response.extend(4u16.to_be_bytes()); // field count
+// columns of the products table
+fn complex_row_description() -> BackendMessage {
+    let column = |name: &str, column, type_oid, type_size, type_modifier| FieldDescription {
+        name: name.to_string(),
+        table_oid: 0x4001,
+        column,
+        type_oid,
+        type_size,
+        type_modifier,
+        format: 0,
+    };
+    BackendMessage::RowDescription(vec![
+        column("id", 1, 23, 4, -1),
+        column("title", 3, 1043, -1, 104),
+        column("description", 4, 25, -1, -1),
+        column("category_id", 5, 21, 2, -1),
+    ])
+}
 
-                response.extend(1u32.to_be_bytes()); // column length
-                response.extend(b"2");
+fn command_completion(tag: &str) -> BackendMessage {
+    BackendMessage::CommandComplete(tag.to_string())
+}
 
-                response.extend(5u32.to_be_bytes()); // column length
-                response.extend(b"phone");
+fn select_id(encoding: Encoding) -> Result<Vec<BackendMessage>, EncodingError> {
+    Ok(vec![
+        simple_row_description(),
+        BackendMessage::text_row(&[Some("123")], encoding)?,
+        command_completion("SELECT 1"),
+        READY_FOR_QUERY,
+    ])
+}
 
-                response.extend(17u32.to_be_bytes()); // column length
-                response.extend(b"Just a phone desc");
+fn select_products(encoding: Encoding) -> Result<Vec<BackendMessage>, EncodingError> {
+    Ok(vec![
+        complex_row_description(),
+        BackendMessage::text_row(&[Some("1"), Some("laptop"), None, Some("2")], encoding)?,
+        BackendMessage::text_row(&[Some("2"), Some("phone"), Some("Just a phone desc"), Some("20000")], encoding)?,
+        command_completion("SELECT 2"),
+        READY_FOR_QUERY,
+    ])
+}
 
-                response.extend(5u32.to_be_bytes()); // column length
-                response.extend(b"20000");
-            }
-            ResponseMessage::ComplexCommandCompletion => {
-                response.extend(b"SELECT 2\0");
-            }
-            ResponseMessage::ParseCompletion => {}
-            ResponseMessage::ParameterDescription => {
-                response.extend([0x00, 0x00]); // parameters
-            }
-            ResponseMessage::BindCompletion => {}
-            ResponseMessage::DataRow => {
-                response.extend(1u16.to_be_bytes()); // field count
-                response.extend(4u32.to_be_bytes()); // column length
-                response.extend(123u32.to_be_bytes());
+// the messages in the client encoding, a message that can't be converted is replaced by an error
+// and the ones after it are dropped, up to the ReadyForQuery
+fn send_response(stream: &mut TcpStream, messages: Vec<BackendMessage>, encoding: Encoding) -> Result<(), std::io::Error> {
+    let mut failed = false;
+    for msg in messages {
+        if failed && !matches!(msg, BackendMessage::ReadyForQuery(_)) {
+            continue;
+        }
+        match msg.as_bytes(encoding) {
+            Ok(data) => write_message(stream, msg.message_type(), &data)?,
+            Err(error) => {
+                failed = true;
+                let error = BackendMessage::error(CHARACTER_NOT_IN_REPERTOIRE, error.message);
+                write_message(stream, error.message_type(), &error.as_bytes(encoding).unwrap_or_default())?;
             }
-            ResponseMessage::CloseCompletion => {}
         }
-
-        response
     }
 
-    fn message_type(&self) -> u8 {
-        match self {
-            ResponseMessage::AuthRequestOK => 0x52, // R
-            ResponseMessage::ParameterStatus(_, _) => 0x53, // S
-            ResponseMessage::ReadyForQuery => 0x5a, // Z
-            ResponseMessage::EmptyQuery => 0x49, // I
-
-            ResponseMessage::SimpleRowDescription => 0x54, // T
-            ResponseMessage::SimpleDataRow => 0x44, // D
-            ResponseMessage::SimpleCommandCompletion => 0x43, // C
-
-            ResponseMessage::ComplexRowDescription => 0x54, // T
-            ResponseMessage::ComplexDataRow1 => 0x44, // D
-            ResponseMessage::ComplexDataRow2 => 0x44, // D
-            ResponseMessage::ComplexCommandCompletion => 0x43, // C
-
-            ResponseMessage::ParseCompletion => 0x31,
-            ResponseMessage::ParameterDescription => 0x74,
-
-            ResponseMessage::BindCompletion => 0x32,
-            ResponseMessage::DataRow => 0x44, // D
+    stream.flush()
+}
 
-            ResponseMessage::CloseCompletion => 0x33,
+// SET [SESSION | LOCAL] client_encoding { TO | = } { 'value' | value | DEFAULT }, SET NAMES value and
+// RESET client_encoding, `Some(None)` when it goes back to the default
+fn client_encoding_assignment(query: &str) -> Option<Option<String>> {
+    let query = query.trim().trim_end_matches(';').replace('=', " = ");
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    let keyword = |i: usize, expected: &str| words.get(i).is_some_and(|word| word.eq_ignore_ascii_case(expected));
+
+    let value = if keyword(0, "reset") && keyword(1, "client_encoding") && words.len() == 2 {
+        return Some(None);
+    } else if keyword(0, "set") && keyword(1, "names") && words.len() == 3 {
+        words[2]
+    } else if keyword(0, "set") {
+        let start = if keyword(1, "session") || keyword(1, "local") { 2 } else { 1 };
+        if !keyword(start, "client_encoding") || !(keyword(start + 1, "to") || keyword(start + 1, "=")) || words.len() != start + 3 {
+            return None;
         }
+        words[start + 2]
+    } else {
+        return None;
+    };
+
+    if value.eq_ignore_ascii_case("default") {
+        Some(None)
+    } else {
+        Some(Some(value.trim_matches('\'').to_string()))
     }
 }
 
-fn print_message(data: impl AsRef<[u8]>, title: &str) {
-    let x = data.as_ref().iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<Vec<String>>()
-        .join(" ");
-    println!("{title}: {x}");
+fn invalid_client_encoding(value: &str) -> String {
+    format!("invalid value for parameter \"client_encoding\": \"{value}\"")
 }
 
-fn send_message(stream: &mut TcpStream, msg: ResponseMessage) -> Result<(), std::io::Error> {
-    stream.write_all(&[msg.message_type()])?;
-
-    let data = msg.as_bytes();
-    let message_len = (data.len() as u32) + 4; // 4 bytes is length itself
-
-    stream.write_all(&message_len.to_be_bytes())?;
-    stream.write_all(&data)?;
-
-    stream.flush()?;
-    Ok(())
+// the next message, one that doesn't decode is an InvalidData error with the DecodeError inside
+fn read_request(stream: &mut TcpStream, encoding: Encoding) -> Result<FrontendMessage, std::io::Error> {
+    let (message_type, data) = read_message(stream)?;
+    FrontendMessage::parse(message_type, &data, encoding).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
-fn read_message(stream: &mut TcpStream) -> Result<RequestMessage, std::io::Error> {
-    let mut buf = [0u8; 1];
-    stream.read_exact(&mut buf)?;
-    let message_type = u8::from_be_bytes(buf);
-
-    let mut length = [0u8; 4];
-    stream.read_exact(&mut length)?;
-    let message_len = u32::from_be_bytes(length) as usize - 4; // subtract 4 bytes representing length
-
-    let mut buf = vec![0; message_len];
-    stream.read_exact(&mut buf)?;
-
-    match message_type {
-        // Simple Query (Q)
-        0x51 => {
-            buf.pop(); // remove last 0
-            Ok(SimpleQuery(String::from_utf8_lossy(buf.as_slice()).to_string()))
-        }
-        0x58 => Ok(Termination), // X
-        0x50 => Ok(Parse), // P
-        0x44 => Ok(Describe), // D
-        0x53 => Ok(Sync), // S
-        0x42 => Ok(Bind), // B
-        0x45 => Ok(Execute), // E
-        0x43 => Ok(Close), // C
-        _ => Err(std::io::ErrorKind::Unsupported.into()),
+fn encoding_error(e: &std::io::Error) -> Option<&EncodingError> {
+    match e.get_ref().and_then(|e| e.downcast_ref::<DecodeError>()) {
+        Some(DecodeError::Encoding(error)) => Some(error),
+        _ => None,
     }
 }
 
-fn read_startup_message(stream: &mut TcpStream) -> Result<Vec<u8>, std::io::Error> {
-    let mut length = [0u8; 4];
-    stream.read_exact(&mut length)?;
-    let message_len = u32::from_be_bytes(length) as usize - 4; // subtract 4 bytes representing length
-    // println!("Message length: {}", message_len);
-
-    let mut buf = vec![0; message_len];
-    stream.read_exact(&mut buf)?;
-
-    Ok(buf)
+// an extended query message that isn't valid in the client encoding, as PostgreSQL the server reports
+// it and ignores what the client sends up to the Sync, which gets the only ReadyForQuery
+fn discard_to_sync(stream: &mut TcpStream, message: String, encoding: Encoding) -> Result<(), std::io::Error> {
+    send_response(stream, vec![BackendMessage::error(CHARACTER_NOT_IN_REPERTOIRE, message)], encoding)?;
+    while read_message(stream)?.0 != b'S' {}
+    send_response(stream, vec![READY_FOR_QUERY], encoding)
 }
 
 fn handle_connection(mut stream: TcpStream) {
     let peer_addr = stream.peer_addr().unwrap_or_else(|_| "unknown".parse().unwrap());
     println!("New connection from: {}", peer_addr);
 
-    let startup_message = read_startup_message(&mut stream);
-    // print_message(startup_message.unwrap(), "startup");
+    let startup_message = read_startup_message(&mut stream).ok().and_then(|data| StartupMessage::parse(&data).ok());
+
+    // what DEFAULT and RESET go back to
+    let default_encoding = match startup_message.as_ref().and_then(|message| message.parameter("client_encoding")) {
+        None => Encoding::Utf8,
+        Some(value) => match Encoding::from_name(value) {
+            Some(encoding) => encoding,
+            None => {
+                let error = BackendMessage::ErrorResponse {
+                    severity: Severity::Fatal,
+                    code: INVALID_PARAMETER_VALUE.to_string(),
+                    message: invalid_client_encoding(value),
+                };
+                let _ = send_response(&mut stream, vec![error], Encoding::Utf8);
+                return;
+            }
+        },
+    };
+    let mut encoding = default_encoding;
 
-    let _ = send_message(&mut stream, ResponseMessage::AuthRequestOK);
-    let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
+    let _ = send_response(&mut stream, vec![
+        BackendMessage::AuthenticationOk,
+        BackendMessage::ParameterStatus { name: "client_encoding".to_string(), value: encoding.name().to_string() },
+        BackendMessage::ParameterStatus { name: "server_encoding".to_string(), value: Encoding::Utf8.name().to_string() },
+        READY_FOR_QUERY,
+    ], encoding);
 
     loop {
-        match read_message(&mut stream) {
-            Ok(msg) => match msg {
-                SimpleQuery(query) => {
-                    match query.as_str() {
-                        // ping
-                        ";" => {
-                                let _ = send_message(&mut stream, ResponseMessage::EmptyQuery);
-                                let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
-                        }
-                        "select 123 as id" => {
-                            let _ = send_message(&mut stream, ResponseMessage::SimpleRowDescription);
-                            let _ = send_message(&mut stream, ResponseMessage::SimpleDataRow);
-                            let _ = send_message(&mut stream, ResponseMessage::SimpleCommandCompletion);
-                            let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
-                        }
-                        "select id, title, description, category_id from products" => {
-                            let _ = send_message(&mut stream, ResponseMessage::ComplexRowDescription);
-                            let _ = send_message(&mut stream, ResponseMessage::ComplexDataRow1);
-                            let _ = send_message(&mut stream, ResponseMessage::ComplexDataRow2);
-                            let _ = send_message(&mut stream, ResponseMessage::ComplexCommandCompletion);
-                            let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
-                        }
-                        _ => unimplemented!(),
-                    }
+        let (message_type, data) = match read_message(&mut stream) {
+            Ok(message) => message,
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    println!("Client {} disconnected", peer_addr);
+                } else {
+                    println!("Error reading from {}: {}", peer_addr, e);
                 }
-                Termination => {
-                    break;
+                break;
+            }
+        };
+
+        let msg = match FrontendMessage::parse(message_type, &data, encoding) {
+            Ok(msg) => msg,
+            // a query that isn't valid in the client encoding
+            Err(DecodeError::Encoding(error)) if message_type == b'Q' => {
+                let error = BackendMessage::error(CHARACTER_NOT_IN_REPERTOIRE, error.message);
+                let _ = send_response(&mut stream, vec![error, READY_FOR_QUERY], encoding);
+                continue;
+            }
+            Err(DecodeError::Encoding(error)) => {
+                let _ = discard_to_sync(&mut stream, error.message, encoding);
+                continue;
+            }
+            Err(error) => {
+                println!("Error reading from {}: {}", peer_addr, error);
+                break;
+            }
+        };
+
+        match msg {
+            FrontendMessage::Query(query) => {
+                if let Some(value) = client_encoding_assignment(&query) {
+                    let requested = match &value {
+                        None => Some(default_encoding),
+                        Some(name) => Encoding::from_name(name),
+                    };
+                    let response = match requested {
+                        None => vec![
+                            BackendMessage::error(INVALID_PARAMETER_VALUE, invalid_client_encoding(value.as_deref().unwrap_or_default())),
+                            READY_FOR_QUERY,
+                        ],
+                        Some(requested) if requested == encoding => vec![command_completion("SET"), READY_FOR_QUERY],
+                        Some(requested) => {
+                            encoding = requested;
+                            vec![
+                                command_completion("SET"),
+                                BackendMessage::ParameterStatus { name: "client_encoding".to_string(), value: encoding.name().to_string() },
+                                READY_FOR_QUERY,
+                            ]
+                        }
+                    };
+                    let _ = send_response(&mut stream, response, encoding);
+                    continue;
                 }
-                Parse => {
-                    if let Ok(desc_msg) = read_message(&mut stream) &&
-                        let Ok(sync_msg) = read_message(&mut stream) &&
-                        matches!(desc_msg, Describe) && matches!(sync_msg, Sync) {
 
-                        let _ = send_message(&mut stream, ResponseMessage::ParseCompletion);
-                        let _ = send_message(&mut stream, ResponseMessage::ParameterDescription);
-                        let _ = send_message(&mut stream, ResponseMessage::SimpleRowDescription);
-                        let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
+                let response = match query.as_str() {
+                    // ping
+                    ";" => Ok(vec![BackendMessage::EmptyQueryResponse, READY_FOR_QUERY]),
+                    "select 123 as id" => select_id(encoding),
+                    "select id, title, description, category_id from products" => select_products(encoding),
+                    _ => unimplemented!(),
+                };
+                let response = response.unwrap_or_else(|error| vec![BackendMessage::error(CHARACTER_NOT_IN_REPERTOIRE, error.message), READY_FOR_QUERY]);
+                let _ = send_response(&mut stream, response, encoding);
+            }
+            FrontendMessage::Terminate => {
+                break;
+            }
+            FrontendMessage::Parse { .. } => {
+                match read_request(&mut stream, encoding).and_then(|desc_msg| Ok((desc_msg, read_request(&mut stream, encoding)?))) {
+                    Ok((FrontendMessage::Describe(..), FrontendMessage::Sync)) => {
+                        let _ = send_response(&mut stream, vec![
+                            BackendMessage::ParseComplete,
+                            BackendMessage::ParameterDescription(Vec::new()),
+                            simple_row_description(),
+                            READY_FOR_QUERY,
+                        ], encoding);
                     }
-                }
-                Bind => {
-                    if let Ok(exec_msg) = read_message(&mut stream) &&
-                        let Ok(sync_msg) = read_message(&mut stream) &&
-                        matches!(exec_msg, Execute) && matches!(sync_msg, Sync) {
-
-                        let _ = send_message(&mut stream, ResponseMessage::BindCompletion);
-                        let _ = send_message(&mut stream, ResponseMessage::DataRow);
-                        let _ = send_message(&mut stream, ResponseMessage::SimpleCommandCompletion);
-                        let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
+                    Err(ref e) if let Some(error) = encoding_error(e) => {
+                        let _ = discard_to_sync(&mut stream, error.message.clone(), encoding);
                     }
+                    _ => {}
                 }
-                Close => {
-                    if let Ok(sync_msg) = read_message(&mut stream) &&
-                        matches!(sync_msg, Sync) {
-
-                        let _ = send_message(&mut stream, ResponseMessage::CloseCompletion);
-                        let _ = send_message(&mut stream, ResponseMessage::ReadyForQuery);
+            }
+            FrontendMessage::Bind { .. } => {
+                match read_request(&mut stream, encoding).and_then(|exec_msg| Ok((exec_msg, read_request(&mut stream, encoding)?))) {
+                    Ok((FrontendMessage::Execute { .. }, FrontendMessage::Sync)) => {
+                        let _ = send_response(&mut stream, vec![
+                            BackendMessage::BindComplete,
+                            BackendMessage::DataRow(vec![Some(123u32.to_be_bytes().to_vec())]),
+                            command_completion("SELECT 1"),
+                            READY_FOR_QUERY,
+                        ], encoding);
+                    }
+                    Err(ref e) if let Some(error) = encoding_error(e) => {
+                        let _ = discard_to_sync(&mut stream, error.message.clone(), encoding);
                     }
+                    _ => {}
                 }
-                _ => unimplemented!(),
             }
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                    println!("Client {} disconnected", peer_addr);
-                } else {
-                    println!("Error reading from {}: {}", peer_addr, e);
+            FrontendMessage::Close(..) => {
+                if let Ok(sync_msg) = read_request(&mut stream, encoding) &&
+                    matches!(sync_msg, FrontendMessage::Sync) {
+
+                    let _ = send_response(&mut stream, vec![BackendMessage::CloseComplete, READY_FOR_QUERY], encoding);
                 }
-                break;
             }
+            // the end of an extended query the server has nothing more to say about
+            FrontendMessage::Sync => {
+                let _ = send_response(&mut stream, vec![READY_FOR_QUERY], encoding);
+            }
+            _ => unimplemented!(),
         }
     }
 }
@@ -344,4 +298,45 @@ fn main() {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_encoding_assignments() {
+        let some = |value: &str| Some(Some(value.to_string()));
+        let cases = [
+            ("SET client_encoding TO LATIN1", some("LATIN1")),
+            ("set client_encoding = 'win1252';", some("win1252")),
+            ("SET client_encoding='UTF8'", some("UTF8")),
+            ("SET SESSION client_encoding TO 'SQL_ASCII'", some("SQL_ASCII")),
+            ("SET LOCAL client_encoding = latin1", some("latin1")),
+            ("SET NAMES 'LATIN1'", some("LATIN1")),
+            ("set names utf8", some("utf8")),
+            ("SET client_encoding TO DEFAULT", Some(None)),
+            ("SET SESSION client_encoding = default;", Some(None)),
+            ("RESET client_encoding", Some(None)),
+            ("  reset CLIENT_ENCODING ; ", Some(None)),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(client_encoding_assignment(query), expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn not_client_encoding_assignments() {
+        for query in [
+            "SET client_encoding",
+            "SET client_encoding TO",
+            "SET client_encoding TO LATIN1 UTF8",
+            "SET GLOBAL client_encoding TO LATIN1",
+            "SET search_path TO public",
+            "SET NAMES",
+            "RESET ALL",
+            "select 123 as id",
+        ] {
+            assert_eq!(client_encoding_assignment(query), None, "{query}");
+        }
+    }
+}